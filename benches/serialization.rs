@@ -0,0 +1,60 @@
+//! Compares the YAML and the binary round trips of a large synthetic loop nest.
+//! Run with `cargo bench --bench serialization`.
+
+use std::time::{Duration, Instant};
+
+use loopana::representations::loops::LoopNest;
+
+const ROUNDS: u32 = 20;
+
+/// A loop nest of `depth` iterators (at most 6) with `body_len` instructions of each kind
+fn synthetic_loop_nest(depth: usize, body_len: usize) -> LoopNest {
+    let names: Vec<char> = "ijklmn".chars().take(depth).collect();
+    let iters: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| format!("  - for {} in (0..{})", name, 16 * (idx + 1)))
+        .collect();
+    let mut body = Vec::new();
+    for offset in 0..body_len {
+        let index: String = names
+            .iter()
+            .map(|name| format!("[{} + {}]", name, offset))
+            .collect();
+        body.push(format!("  - Ra <= A{}", index));
+        body.push(format!("  - Rb <= B{}", index));
+        body.push("  - mac Rc Ra, Rb".to_string());
+        body.push(format!("  - Rc => C{}", index));
+    }
+    let yaml = format!("iters:\n{}\nbody:\n{}\n", iters.join("\n"), body.join("\n"));
+    serde_yaml::from_str(&yaml).expect("The synthetic loop nest is valid")
+}
+
+fn time_round_trips(round_trip: impl Fn() -> LoopNest) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        std::hint::black_box(round_trip());
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let loop_nest = synthetic_loop_nest(6, 2000);
+
+    let yaml = time_round_trips(|| {
+        let yaml_str = serde_yaml::to_string(&loop_nest).unwrap();
+        serde_yaml::from_str(&yaml_str).unwrap()
+    });
+    let binary = time_round_trips(|| LoopNest::from_binary(&loop_nest.to_binary()).unwrap());
+
+    println!(
+        "YAML round trip:   {:?} ({} bytes)",
+        yaml,
+        serde_yaml::to_string(&loop_nest).unwrap().len()
+    );
+    println!(
+        "Binary round trip: {:?} ({} bytes)",
+        binary,
+        loop_nest.to_binary().len()
+    );
+}
@@ -23,3 +23,117 @@ pub fn derive_property_hook(input: TokenStream) -> TokenStream {
     // Convert the generated code into a TokenStream and return it
     TokenStream::from(expanded)
 }
+
+/// Implements `PassRun` for a type implementing `InstPass`:
+/// `pass_inst` is called on every instruction of the loop body.
+/// `PassRun`, `InstPass` and `Workspace` must be in scope.
+#[proc_macro_derive(InstPass)]
+pub fn derive_inst_pass(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let expanded = quote! {
+        impl PassRun for #name {
+            fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+                Ok(())
+            }
+
+            fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+                let loop_nest = workspace.loop_nest.clone();
+                for inst in loop_nest.all_instructions() {
+                    let properties = self.pass_inst(inst);
+                    for property in properties {
+                        workspace.add_property(inst, property);
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Implements `PassRun` for a type implementing `IterPass`:
+/// `pass_iter` is called on every iterator of the loop nest.
+/// `PassRun`, `IterPass` and `Workspace` must be in scope.
+#[proc_macro_derive(IterPass)]
+pub fn derive_iter_pass(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let expanded = quote! {
+        impl PassRun for #name {
+            fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+                Ok(())
+            }
+
+            fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+                let loop_nest = workspace.loop_nest.clone();
+                for iter in loop_nest.all_iters() {
+                    let properties = self.pass_iter(iter);
+                    for property in properties {
+                        workspace.add_property(iter, property);
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Implements `PassRun` for a type implementing `WorkspacePass`:
+/// `pass_workspace` is called once and its properties are attached to the workspace.
+/// `PassRun`, `WorkspacePass` and `Workspace` must be in scope.
+#[proc_macro_derive(WorkspacePass)]
+pub fn derive_workspace_pass(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let expanded = quote! {
+        impl PassRun for #name {
+            fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+                Ok(())
+            }
+
+            fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+                let properties = self.pass_workspace(workspace);
+                for property in properties {
+                    workspace.add_global_property(property);
+                }
+                Ok(())
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Implements `PassRun` for a type implementing `LoopPass`:
+/// `pass_loop` is called on the loop nest and its properties are attached to the loop nest hook.
+/// `PassRun`, `LoopPass` and `Workspace` must be in scope.
+#[proc_macro_derive(LoopPass)]
+pub fn derive_loop_pass(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let expanded = quote! {
+        impl PassRun for #name {
+            fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+                Ok(())
+            }
+
+            fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+                let properties = self.pass_loop(&workspace.loop_nest);
+                for property in properties {
+                    workspace.add_loop_property(property);
+                }
+                Ok(())
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
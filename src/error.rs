@@ -0,0 +1,112 @@
+use std::fmt;
+
+/// An error of the parsers of the representations, e.g. of an expression or an instruction of a
+/// `.loop` file, located in the parsed input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub input: String,
+    /// Byte offset in `input` at which the parsing failed
+    pub offset: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(input: &str, offset: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            input: input.to_string(),
+            offset,
+            message: message.into(),
+        }
+    }
+
+    /// Locate the error of a nom parser which was given `input`
+    pub fn from_nom(input: &str, error: nom::Err<nom::error::Error<&str>>) -> ParseError {
+        match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => {
+                ParseError::new(input, offset_in(input, e.input), describe(e.code))
+            }
+            nom::Err::Incomplete(_) => {
+                ParseError::new(input, input.len(), "unexpected end of input")
+            }
+        }
+    }
+}
+
+/// Byte offset of `rest` in `input`, `rest` being a slice of `input`, e.g. the text between
+/// two brackets, or its end
+fn offset_in(input: &str, rest: &str) -> usize {
+    let start = input.as_ptr() as usize;
+    let position = rest.as_ptr() as usize;
+    if (start..=start + input.len()).contains(&position) {
+        position - start
+    } else {
+        input.len().saturating_sub(rest.len())
+    }
+}
+
+/// What the parser expected at the error
+fn describe(kind: nom::error::ErrorKind) -> &'static str {
+    use nom::error::ErrorKind;
+    match kind {
+        ErrorKind::Digit => "expected a number",
+        ErrorKind::MapRes => "the number is out of range",
+        ErrorKind::Alpha | ErrorKind::AlphaNumeric | ErrorKind::TakeWhile1 => {
+            "expected an identifier"
+        }
+        ErrorKind::Eof => "unexpected trailing input",
+        ErrorKind::Verify => "inconsistent value",
+        ErrorKind::Char | ErrorKind::Tag => "unexpected token",
+        _ => "unexpected input",
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Cannot parse \"{}\" at offset {}: {}",
+            self.input, self.offset, self.message
+        )?;
+        if self.offset < self.input.len() {
+            write!(f, " near \"{}\"", &self.input[self.offset..])?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseError;
+    use crate::representations::instruction::Instruction;
+    use crate::representations::transforms::{Transform, Transforms};
+
+    #[test]
+    fn test_from_nom() {
+        // the error is located at the factor, not at the keyword
+        let error = Transform::from_str("!Unroll j by x").unwrap_err();
+        assert_eq!(error.input, "!Unroll j by x");
+        assert_eq!(error.offset, 13);
+        assert_eq!(
+            error.to_string(),
+            "Cannot parse \"!Unroll j by x\" at offset 13: expected a number near \"x\""
+        );
+        let error = Transforms::from_str(" - !Unroll j by x").unwrap_err();
+        assert_eq!(error.offset, 16);
+        assert_eq!(error.message, "Error in transform 0: expected a number");
+
+        let error = serde_yaml::from_str::<Instruction>("Ra <= A[i").unwrap_err();
+        assert!(
+            error.to_string().contains("Cannot parse \"Ra <= A[i\""),
+            "{}",
+            error
+        );
+
+        let error = ParseError::new("x +", 3, "unexpected end of input");
+        assert_eq!(
+            error.to_string(),
+            "Cannot parse \"x +\" at offset 3: unexpected end of input"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use core::fmt;
 
+use super::feature::Feature;
 use super::passes::*;
 use super::workspace::Workspace;
 use crate::representations::arch::Arch;
@@ -45,12 +46,12 @@ impl PassInfo for ArchInfoBuilder {
         "Builds the architecture information"
     }
 
-    fn required_features(&self) -> Vec<String> {
+    fn required_features(&self) -> Vec<Feature> {
         vec![]
     }
 
-    fn produced_features(&self) -> Vec<String> {
-        vec!["ArchInfo".to_string()]
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::ArchInfo]
     }
 }
 
@@ -0,0 +1,103 @@
+use core::fmt;
+
+use crate::representations::affine_expr::AffineExpr;
+
+use super::compute_count::ComputeCountProp;
+use super::feature::Feature;
+use super::memory_footprint::MemoryFootprintProp;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// Size of an array element when no architecture is given
+pub const DEFAULT_BYTES_PER_ELEMENT: i64 = 4;
+
+/// Flops per byte transferred, the operational intensity of the roofline model
+#[derive(Clone)]
+pub struct ArithmeticIntensityProp {
+    pub total_flops: i64,
+    pub total_bytes: i64,
+    pub intensity: f64,
+}
+
+impl Property for ArithmeticIntensityProp {
+    fn property_id(&self) -> String {
+        "ArithmeticIntensityProp".to_string()
+    }
+}
+
+impl fmt::Display for ArithmeticIntensityProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Arithmetic Intensity: {:.3} flops/byte ({} flops, {} bytes)",
+            self.intensity, self.total_flops, self.total_bytes
+        )
+    }
+}
+
+/// Computes the arithmetic intensity from the compute count and the memory footprint.
+/// Each array element is transferred once, its size is the data width of the architecture.
+pub struct ArithmeticIntensityPass;
+
+impl PassRun for ArithmeticIntensityPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let total_flops = workspace
+            .get_typed_property::<ComputeCountProp>(&*workspace)
+            .first()
+            .ok_or("The compute count is missing")?
+            .total_flops;
+        let elements = workspace
+            .get_typed_property::<MemoryFootprintProp>(&*workspace)
+            .first()
+            .ok_or("The memory footprint is missing")?
+            .footprints
+            .values()
+            .map(|footprint| match footprint {
+                AffineExpr::Const(elements) => Ok(*elements as i64),
+                _ => Err("The memory footprint is not constant"),
+            })
+            .sum::<Result<i64, &'static str>>()?;
+        let bytes_per_element = workspace
+            .arch
+            .as_ref()
+            .map(|arch| arch.pe_arch.data_width as i64)
+            .unwrap_or(DEFAULT_BYTES_PER_ELEMENT);
+        let total_bytes = elements * bytes_per_element;
+        if total_bytes == 0 {
+            return Err("The loop nest does not transfer any data");
+        }
+        workspace.add_global_property(Box::new(ArithmeticIntensityProp {
+            total_flops,
+            total_bytes,
+            intensity: total_flops as f64 / total_bytes as f64,
+        }));
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for ArithmeticIntensityPass {
+    fn name(&self) -> &str {
+        "Arithmetic Intensity"
+    }
+
+    fn description(&self) -> &str {
+        "Computes the flops per byte transferred of the loop nest"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![
+            Feature::MemAccess,
+            Feature::ComputeCount,
+            Feature::MemoryFootprint,
+        ]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::ArithmeticIntensity]
+    }
+}
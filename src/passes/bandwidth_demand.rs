@@ -0,0 +1,289 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+
+use crate::representations::affine_expr::AffineExpr;
+use crate::representations::arch::{Arch, DataPort};
+use crate::representations::instruction::Instruction;
+use crate::representations::loops::LoopNest;
+use crate::representations::mapping::{Mapping, MappingType};
+
+use super::arch_info::ArchInfo;
+use super::feature::Feature;
+use super::mapping_info::MappingInfo;
+use super::memory_footprint::MemoryFootprintProp;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+#[derive(Clone)]
+pub struct BandwidthDemandProp {
+    pub port_name: String,
+    pub bytes_per_cycle: f64,
+}
+
+impl Property for BandwidthDemandProp {
+    fn property_id(&self) -> String {
+        "BandwidthDemandProp".to_string()
+    }
+}
+
+impl fmt::Display for BandwidthDemandProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Bandwidth demand of {}: {:.3} bytes/cycle",
+            self.port_name, self.bytes_per_cycle
+        )
+    }
+}
+
+/// The demand of a data port exceeding its bandwidth
+#[derive(Clone)]
+pub struct BandwidthViolationProp {
+    pub port_name: String,
+    pub bytes_per_cycle: f64,
+    pub bandwidth_bytes_per_cycle: f64,
+}
+
+impl Property for BandwidthViolationProp {
+    fn property_id(&self) -> String {
+        "BandwidthViolationProp".to_string()
+    }
+}
+
+impl fmt::Display for BandwidthViolationProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Bandwidth violation of {}: {:.3} bytes/cycle for a bandwidth of {:.3} bytes/cycle",
+            self.port_name, self.bytes_per_cycle, self.bandwidth_bytes_per_cycle
+        )
+    }
+}
+
+/// Estimates the bytes per cycle going through each data port of a PE.
+/// The spatially mapped iterations run in parallel on the PEs, each PE executing one iteration
+/// of the body per cycle, or one per SIMD lane for the vectorized iterators.
+/// - a memory port reads (or writes) its share of the footprint of the arrays loaded (or stored)
+/// - a NoC port forwards the elements loaded by the neighbouring PE along its direction which are
+///   reused, i.e. whose indices do not depend on the iterator mapped on that direction
+///
+/// The ports whose demand exceeds their bandwidth are reported as `BandwidthViolationProp`.
+pub struct BandwidthDemandPass;
+
+impl BandwidthDemandPass {
+    /// Bytes per cycle going through each data port of the architecture, in the order of the ports
+    pub fn demands(
+        loop_nest: &LoopNest,
+        arch: &Arch,
+        mapping: &Mapping,
+        footprints: &HashMap<String, AffineExpr>,
+    ) -> Result<Vec<BandwidthDemandProp>, &'static str> {
+        let bytes_per_element = arch.pe_arch.data_width as f64;
+
+        // iterator mapped on each dimension of the architecture
+        let spatial_iters: Vec<Option<&String>> = arch
+            .dimensions
+            .iter()
+            .map(|dim| {
+                mapping.types.iter().find_map(|(iter, mapping_type)| {
+                    match mapping_type {
+                        MappingType::Spatial(name) if *name == dim.name => Some(iter),
+                        _ => None,
+                    }
+                    .filter(|iter| loop_nest.get_iter(iter).is_some())
+                })
+            })
+            .collect();
+        let parallel_iterations = spatial_iters
+            .iter()
+            .flatten()
+            .map(|iter| {
+                loop_nest
+                    .get_iter(iter)
+                    .and_then(|iter| iter.trip_count(&Default::default()))
+            })
+            .product::<Option<i64>>()
+            .ok_or("Cannot compute the trip count of a spatial iterator")?;
+        // width of the SIMD lanes of each vectorized iterator, the lanes beyond the trip count
+        // of the iterator are idle
+        let simd_widths: Vec<(&String, i64)> = mapping
+            .types
+            .iter()
+            .filter_map(|(iter, mapping_type)| match mapping_type {
+                MappingType::Vectorized(_, width) => {
+                    let trip_count = loop_nest
+                        .get_iter(iter)?
+                        .trip_count(&Default::default())
+                        .unwrap_or(i64::MAX);
+                    Some((iter, (*width as i64).min(trip_count)))
+                }
+                _ => None,
+            })
+            .collect();
+        let simd_lanes: i64 = simd_widths.iter().map(|(_, width)| width).product();
+        // elements accessed at once by a vector access, the accesses not depending on a
+        // vectorized iterator are broadcast to its lanes
+        let access_lanes = |addr: &[AffineExpr]| -> f64 {
+            simd_widths
+                .iter()
+                .filter(|(iter, _)| addr.iter().any(|expr| expr.contains_var(iter)))
+                .map(|(_, width)| *width as f64)
+                .product()
+        };
+        let total_iterations = loop_nest
+            .total_iteration_count(&Default::default())
+            .ok_or("Cannot compute the iteration count of the loop nest")?;
+        if parallel_iterations == 0 || total_iterations == 0 || simd_lanes == 0 {
+            return Err("The loop nest has no iteration");
+        }
+        // a partially filled vector still takes a cycle
+        let lanes = parallel_iterations * simd_lanes;
+        let cycles = ((total_iterations + lanes - 1) / lanes) as f64;
+
+        let loaded: HashSet<&String> = loop_nest
+            .all_instructions()
+            .into_iter()
+            .filter_map(|inst| match inst {
+                Instruction::DataLoad(access) => Some(&access.array_name),
+                _ => None,
+            })
+            .collect();
+        let stored: HashSet<&String> = loop_nest
+            .all_instructions()
+            .into_iter()
+            .filter_map(|inst| match inst {
+                Instruction::DataStore(access) => Some(&access.array_name),
+                _ => None,
+            })
+            .collect();
+        let footprint_bytes = |arrays: &HashSet<&String>| -> Result<f64, &'static str> {
+            arrays
+                .iter()
+                .filter_map(|array| footprints.get(*array))
+                .map(|footprint| match footprint {
+                    AffineExpr::Const(elements) => Ok(*elements as f64 * bytes_per_element),
+                    _ => Err("The memory footprint is not constant"),
+                })
+                .sum()
+        };
+        let pe_cycles = parallel_iterations as f64 * cycles;
+        let read_ports = arch.memory_read_ports().len() as f64;
+        let write_ports = arch.memory_write_ports().len() as f64;
+        let read_demand = footprint_bytes(&loaded)? / pe_cycles / read_ports;
+        let write_demand = footprint_bytes(&stored)? / pe_cycles / write_ports;
+
+        let mut demands = Vec::new();
+        for port in arch.data_ports() {
+            let (port_name, bytes_per_cycle) = match port {
+                DataPort::MemoryReadPort(port) => (&port.name, read_demand),
+                DataPort::MemoryWritePort(port) => (&port.name, write_demand),
+                DataPort::NocPort(port) => {
+                    let forwarded_elements = port
+                        .topology
+                        .iter()
+                        .zip(spatial_iters.iter())
+                        .filter(|(step, _)| **step != 0)
+                        .filter_map(|(_, iter)| *iter)
+                        .map(|iter| {
+                            loop_nest
+                                .all_instructions()
+                                .into_iter()
+                                .filter_map(|inst| match inst {
+                                    Instruction::DataLoad(access)
+                                        if !access
+                                            .addr
+                                            .iter()
+                                            .any(|expr| expr.contains_var(iter)) =>
+                                    {
+                                        Some(access_lanes(&access.addr))
+                                    }
+                                    _ => None,
+                                })
+                                .sum::<f64>()
+                        })
+                        .sum::<f64>();
+                    (&port.name, forwarded_elements * bytes_per_element)
+                }
+            };
+            demands.push(BandwidthDemandProp {
+                port_name: port_name.clone(),
+                bytes_per_cycle,
+            });
+        }
+        Ok(demands)
+    }
+
+    /// The ports whose demand exceeds their bandwidth
+    pub fn violations(arch: &Arch, demands: &[BandwidthDemandProp]) -> Vec<BandwidthViolationProp> {
+        arch.data_ports()
+            .iter()
+            .zip(demands)
+            .filter(|(port, demand)| demand.bytes_per_cycle > port.bandwidth_bytes_per_cycle())
+            .map(|(port, demand)| BandwidthViolationProp {
+                port_name: demand.port_name.clone(),
+                bytes_per_cycle: demand.bytes_per_cycle,
+                bandwidth_bytes_per_cycle: port.bandwidth_bytes_per_cycle(),
+            })
+            .collect()
+    }
+}
+
+impl PassRun for BandwidthDemandPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let arch = workspace
+            .get_typed_property::<ArchInfo>(&*workspace)
+            .first()
+            .ok_or("The architecture is missing")?
+            .arch
+            .clone();
+        let mapping = workspace
+            .get_typed_property::<MappingInfo>(&*workspace)
+            .first()
+            .ok_or("The mapping is missing")?
+            .mapping
+            .clone();
+        let footprints = workspace
+            .get_typed_property::<MemoryFootprintProp>(&*workspace)
+            .first()
+            .ok_or("The memory footprint is missing")?
+            .footprints
+            .clone();
+        let demands =
+            BandwidthDemandPass::demands(&workspace.loop_nest, &arch, &mapping, &footprints)?;
+        let violations = BandwidthDemandPass::violations(&arch, &demands);
+        for demand in demands {
+            workspace.add_global_property(Box::new(demand));
+        }
+        for violation in violations {
+            workspace.add_global_property(Box::new(violation));
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for BandwidthDemandPass {
+    fn name(&self) -> &str {
+        "Bandwidth Demand"
+    }
+
+    fn description(&self) -> &str {
+        "Estimates the bytes per cycle going through each data port"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![
+            Feature::ArchInfo,
+            Feature::MemoryFootprint,
+            Feature::MappingInfo,
+        ]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::BandwidthDemand]
+    }
+}
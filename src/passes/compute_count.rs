@@ -0,0 +1,93 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use crate::representations::instruction::{Compute, Instruction};
+use crate::representations::loops::iteration_count;
+
+use super::feature::Feature;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// Number of arithmetic operations over the whole execution of the loop nest
+#[derive(Clone)]
+pub struct ComputeCountProp {
+    /// Weighted operations of a single execution of the body, including the complete execution
+    /// of the nested loop nests
+    pub flops_per_iteration: i64,
+    pub total_flops: i64,
+}
+
+impl Property for ComputeCountProp {
+    fn property_id(&self) -> String {
+        "ComputeCountProp".to_string()
+    }
+}
+
+impl fmt::Display for ComputeCountProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Flops: {} ({} per iteration)",
+            self.total_flops, self.flops_per_iteration
+        )
+    }
+}
+
+/// Number of operations performed by a compute instruction, fused multiply-adds count twice
+pub fn op_weight(compute: &Compute) -> i64 {
+    if compute.is_fma() {
+        2
+    } else {
+        1
+    }
+}
+
+pub struct ComputeCountPass;
+
+impl PassRun for ComputeCountPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let outer_depth = workspace.loop_nest.iters.len();
+        let mut flops_per_iteration = 0;
+        for (iters, inst) in workspace.loop_nest.scoped_instructions() {
+            if let Instruction::Compute(compute) = inst {
+                // the instructions of a nested loop nest are executed at each iteration of it
+                let executions =
+                    iteration_count(iters[outer_depth..].iter().copied(), &HashMap::new())
+                        .ok_or("Cannot compute the iteration count of a nested loop nest")?;
+                flops_per_iteration += op_weight(compute) * executions;
+            }
+        }
+        let iterations = workspace
+            .loop_nest
+            .total_iteration_count(&HashMap::new())
+            .ok_or("Cannot compute the iteration count of the loop nest")?;
+        workspace.add_global_property(Box::new(ComputeCountProp {
+            flops_per_iteration,
+            total_flops: flops_per_iteration * iterations,
+        }));
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for ComputeCountPass {
+    fn name(&self) -> &str {
+        "Compute Count"
+    }
+
+    fn description(&self) -> &str {
+        "Counts the arithmetic operations of the loop nest, weighted by operation type"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::ComputeCount]
+    }
+}
@@ -0,0 +1,122 @@
+use core::fmt;
+
+use crate::representations::instruction::{DataAccess, Instruction};
+
+use super::feature::Feature;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// How an access reuses data across the iterations of a loop iterator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReuseType {
+    /// The iterator does not appear in the indices, the same element is accessed again
+    TemporalReuse,
+    /// The iterator only appears in the last (contiguous) dimension, adjacent elements are accessed
+    SpatialReuse,
+    NoReuse,
+}
+
+impl fmt::Display for ReuseType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReuseType::TemporalReuse => write!(f, "Temporal"),
+            ReuseType::SpatialReuse => write!(f, "Spatial"),
+            ReuseType::NoReuse => write!(f, "None"),
+        }
+    }
+}
+
+/// The reuse type of a memory access along each iterator of the loop nest, in loop order
+#[derive(Clone)]
+pub struct DataReuseProp {
+    pub reuse: Vec<(String, ReuseType)>,
+}
+
+impl DataReuseProp {
+    pub fn reuse_of(&self, iter_name: &str) -> Option<ReuseType> {
+        self.reuse
+            .iter()
+            .find(|(name, _)| name == iter_name)
+            .map(|(_, reuse)| *reuse)
+    }
+}
+
+impl Property for DataReuseProp {
+    fn property_id(&self) -> String {
+        "DataReuseProp".to_string()
+    }
+}
+
+impl fmt::Display for DataReuseProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reuse: Vec<String> = self
+            .reuse
+            .iter()
+            .map(|(name, reuse)| format!("{}: {}", name, reuse))
+            .collect();
+        write!(f, "Reuse: {{{}}}", reuse.join(", "))
+    }
+}
+
+fn reuse_type(mem_access: &DataAccess, iter_name: &str) -> ReuseType {
+    let dims: Vec<usize> = mem_access
+        .addr
+        .iter()
+        .enumerate()
+        .filter(|(_, expr)| expr.contains_var(iter_name))
+        .map(|(dim, _)| dim)
+        .collect();
+    match dims.as_slice() {
+        [] => ReuseType::TemporalReuse,
+        [dim] if *dim == mem_access.addr.len() - 1 => ReuseType::SpatialReuse,
+        _ => ReuseType::NoReuse,
+    }
+}
+
+pub struct DataReuseAnalysis;
+
+impl PassRun for DataReuseAnalysis {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let loop_nest = workspace.loop_nest.clone();
+        // the reuse is classified along the iterators enclosing the instruction
+        for (iters, inst) in loop_nest.scoped_instructions() {
+            let reuse = match inst {
+                Instruction::DataLoad(mem_access) | Instruction::DataStore(mem_access) => iters
+                    .iter()
+                    .map(|iter| {
+                        (
+                            iter.iter_name.clone(),
+                            reuse_type(mem_access, &iter.iter_name),
+                        )
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            workspace.add_property(inst, Box::new(DataReuseProp { reuse }));
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for DataReuseAnalysis {
+    fn name(&self) -> &str {
+        "Data Reuse Analysis"
+    }
+
+    fn description(&self) -> &str {
+        "Classifies the reuse of each memory access along each loop iterator"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::DataReuse]
+    }
+}
@@ -1,5 +1,101 @@
-#[derive(Debug, Clone, PartialEq)]
-pub struct Feature {
-    pub name: String,
-    pub description: String,
+use std::fmt::Display;
+
+/// A piece of information made available in the workspace by a pass
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Feature {
+    ArchInfo,
+    MappingInfo,
+    MemAccess,
+    FreeDims,
+    DependenceInfo,
+    Parallelizable,
+    Vectorization,
+    Stride,
+    OperationCount,
+    ComputeCount,
+    MemoryFootprint,
+    BandwidthDemand,
+    ArithmeticIntensity,
+    DataReuse,
+    SyncBarriers,
+    /// A feature of a pass defined outside of the crate
+    Custom(String),
+}
+
+impl Feature {
+    const KNOWN: [Feature; 15] = [
+        Feature::ArchInfo,
+        Feature::MappingInfo,
+        Feature::MemAccess,
+        Feature::FreeDims,
+        Feature::DependenceInfo,
+        Feature::Parallelizable,
+        Feature::Vectorization,
+        Feature::Stride,
+        Feature::OperationCount,
+        Feature::ComputeCount,
+        Feature::MemoryFootprint,
+        Feature::BandwidthDemand,
+        Feature::ArithmeticIntensity,
+        Feature::DataReuse,
+        Feature::SyncBarriers,
+    ];
+
+    /// The name used for the feature before it was typed, e.g. "Dependence" for `DependenceInfo`
+    pub fn name(&self) -> &str {
+        match self {
+            Feature::ArchInfo => "ArchInfo",
+            Feature::MappingInfo => "MappingInfo",
+            Feature::MemAccess => "MemAccess",
+            Feature::FreeDims => "FreeDims",
+            Feature::DependenceInfo => "Dependence",
+            Feature::Parallelizable => "Parallelizable",
+            Feature::Vectorization => "Vectorization",
+            Feature::Stride => "Stride",
+            Feature::OperationCount => "OperationCount",
+            Feature::ComputeCount => "ComputeCount",
+            Feature::MemoryFootprint => "MemoryFootprint",
+            Feature::BandwidthDemand => "BandwidthDemand",
+            Feature::ArithmeticIntensity => "ArithmeticIntensity",
+            Feature::DataReuse => "DataReuse",
+            Feature::SyncBarriers => "SyncBarriers",
+            Feature::Custom(name) => name,
+        }
+    }
+
+    /// Inverse of `name`, unknown names give a `Custom` feature
+    pub fn from_name(name: &str) -> Feature {
+        Feature::KNOWN
+            .iter()
+            .find(|feature| feature.name() == name)
+            .cloned()
+            .unwrap_or_else(|| Feature::Custom(name.to_string()))
+    }
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name() {
+        for feature in Feature::KNOWN {
+            assert_eq!(Feature::from_name(feature.name()), feature);
+        }
+        assert_eq!(Feature::from_name("Dependence"), Feature::DependenceInfo);
+        assert_eq!(
+            Feature::from_name("TripCount"),
+            Feature::Custom("TripCount".to_string())
+        );
+        assert_eq!(
+            Feature::Custom("TripCount".to_string()).to_string(),
+            "TripCount"
+        );
+    }
 }
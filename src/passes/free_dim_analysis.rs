@@ -1,10 +1,12 @@
 use core::fmt;
 
-use crate::representations::{affine_expr::AffineExpr, instruction::Instruction};
-use super::property::Property;
+use super::feature::Feature;
 use super::passes::{PassInfo, PassRun};
+use super::property::Property;
 use super::workspace::Workspace;
+use crate::representations::instruction::Instruction;
 
+#[derive(Clone)]
 pub struct FreeDimProp {
     pub free_dims: Vec<String>,
 }
@@ -21,62 +23,48 @@ impl fmt::Display for FreeDimProp {
     }
 }
 
-pub struct FreeDimAnalysis;
-
-impl FreeDimAnalysis {
-    fn get_accesses_from_affine_expr(expr: &AffineExpr) -> Vec<String> {
-        match expr {
-            AffineExpr::Var(var) => {
-                vec![var.clone()]
-            }
-            AffineExpr::Const(_) => {
-                vec![]
-            }
-            AffineExpr::Add(expr1, expr2) => {
-                let mut accesses = Self::get_accesses_from_affine_expr(expr1);
-                accesses.extend(Self::get_accesses_from_affine_expr(expr2));
-                accesses
-            }
-            AffineExpr::Sub(expr1, expr2) => {
-                let mut accesses = Self::get_accesses_from_affine_expr(expr1);
-                accesses.extend(Self::get_accesses_from_affine_expr(expr2));
-                accesses
-            }
-            AffineExpr::Mul(_, expr) => Self::get_accesses_from_affine_expr(expr),
-            AffineExpr::Div(expr, _) => Self::get_accesses_from_affine_expr(expr),
-            AffineExpr::Mod(expr, _) => Self::get_accesses_from_affine_expr(expr),
-        }
-    }
+#[derive(Default)]
+pub struct FreeDimAnalysis {
+    /// Only analyze the instructions of the outermost body, not the ones of the nested loop nests
+    pub top_level_only: bool,
 }
 
 impl PassRun for FreeDimAnalysis {
     fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
-        let iter_names: Vec<String> = workspace
-            .loop_nest
-            .iters
-            .iter()
-            .map(|iter| iter.iter_name.clone())
-            .collect();
-
-        for inst in workspace.loop_nest.body.clone().iter() {
+        let loop_nest = workspace.loop_nest.clone();
+        let outer_depth = loop_nest.iters.len();
+        for (iters, inst) in loop_nest.scoped_instructions() {
+            if self.top_level_only && iters.len() > outer_depth {
+                continue;
+            }
+            if let Instruction::SyncBarrier(_) = inst {
+                continue;
+            }
+            // the free dims of an instruction are among its enclosing iterators
+            let iter_names: Vec<String> = iters.iter().map(|iter| iter.iter_name.clone()).collect();
             let mut accessed_dims = Vec::new();
 
             match inst {
-                Instruction::DataLoad(mem_access) | Instruction::DataStore(mem_access) => {
+                Instruction::DataLoad(mem_access)
+                | Instruction::DataStore(mem_access)
+                | Instruction::Prefetch(mem_access) => {
                     for expr in &mem_access.addr {
-                        accessed_dims.extend(Self::get_accesses_from_affine_expr(expr));
+                        for var in expr.vars() {
+                            if !accessed_dims.contains(&var) {
+                                accessed_dims.push(var);
+                            }
+                        }
                     }
                 }
                 _ => {}
             }
 
-            // accessed_dims.dedup();
             let free_dims: Vec<String> = iter_names
                 .clone()
                 .into_iter()
                 .filter(|dim| !accessed_dims.contains(dim))
                 .collect();
-            
+
             workspace.add_property(inst, Box::new(FreeDimProp { free_dims }));
         }
         Ok(())
@@ -96,12 +84,11 @@ impl PassInfo for FreeDimAnalysis {
         "Identifies dimensions in the loop nest that are not accessed in memory operations"
     }
 
-    fn required_features(&self) -> Vec<String> {
+    fn required_features(&self) -> Vec<Feature> {
         vec![]
     }
 
-    fn produced_features(&self) -> Vec<String> {
-        vec!["FreeDims".to_string()]
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::FreeDims]
     }
 }
-
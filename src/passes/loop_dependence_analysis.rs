@@ -0,0 +1,330 @@
+use core::fmt;
+
+use crate::representations::instruction::{DataAccess, Instruction};
+use crate::representations::loops::LoopIter;
+
+use super::feature::Feature;
+use super::passes::{PassInfo, PassRun};
+use super::property::{Property, PropertyHook};
+use super::workspace::Workspace;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependenceKind {
+    /// Read after write, true dependence
+    RAW,
+    /// Write after read, anti dependence
+    WAR,
+    /// Write after write, output dependence
+    WAW,
+    None,
+}
+
+impl fmt::Display for DependenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DependenceKind::RAW => write!(f, "RAW"),
+            DependenceKind::WAR => write!(f, "WAR"),
+            DependenceKind::WAW => write!(f, "WAW"),
+            DependenceKind::None => write!(f, "None"),
+        }
+    }
+}
+
+/// Sign of the dependence distance along a loop, from the source iteration to the sink one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The sink runs in a later iteration
+    Lt,
+    /// The source and the sink run in the same iteration
+    Eq,
+    /// The sink runs in an earlier iteration
+    Gt,
+    /// Unknown distance
+    Any,
+}
+
+impl Direction {
+    fn reversed(self) -> Direction {
+        match self {
+            Direction::Lt => Direction::Gt,
+            Direction::Gt => Direction::Lt,
+            direction => direction,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::Lt => write!(f, "<"),
+            Direction::Eq => write!(f, "="),
+            Direction::Gt => write!(f, ">"),
+            Direction::Any => write!(f, "*"),
+        }
+    }
+}
+
+/// A possible dependence between two accesses to the same array, attached to the source instruction
+#[derive(Clone)]
+pub struct DependenceProp {
+    pub array: String,
+    pub source_inst_id: String,
+    pub sink_inst_id: String,
+    pub kind: DependenceKind,
+    /// Iterators which may carry the dependence, i.e. along which the dependence distance may be
+    /// non zero. Empty for a loop-independent dependence or no dependence.
+    pub carriers: Vec<String>,
+    /// Direction of the dependence along each loop enclosing both accesses, from the outermost
+    /// to the innermost. Empty for no dependence.
+    pub directions: Vec<(String, Direction)>,
+}
+
+impl DependenceProp {
+    /// Whether interchanging the loops of `iter1` and `iter2` may reverse the dependence, i.e.
+    /// whether a direction vector of the dependence whose leading non `=` entry is `<` may have a
+    /// leading `>` once its entries for `iter1` and `iter2` are swapped.
+    /// The direction vectors which are not lexicographically positive are the ones of the
+    /// dependence in the opposite direction, which is reported separately.
+    pub fn reversed_by_interchange(&self, iter1: &str, iter2: &str) -> bool {
+        let position = |iter: &str| self.directions.iter().position(|(name, _)| name == iter);
+        let (Some(pos1), Some(pos2)) = (position(iter1), position(iter2)) else {
+            return false;
+        };
+        // each `*` stands for any of `<`, `=` and `>`
+        let mut vectors: Vec<Vec<Direction>> = vec![vec![]];
+        for (_, direction) in &self.directions {
+            let choices = match direction {
+                Direction::Any => vec![Direction::Lt, Direction::Eq, Direction::Gt],
+                direction => vec![*direction],
+            };
+            vectors = vectors
+                .iter()
+                .flat_map(|vector| {
+                    choices.iter().map(move |choice| {
+                        let mut vector = vector.clone();
+                        vector.push(*choice);
+                        vector
+                    })
+                })
+                .collect();
+        }
+        let leading = |vector: &[Direction]| vector.iter().find(|d| **d != Direction::Eq).copied();
+        vectors.into_iter().any(|mut vector| {
+            let positive = leading(&vector) == Some(Direction::Lt);
+            vector.swap(pos1, pos2);
+            positive && leading(&vector) == Some(Direction::Gt)
+        })
+    }
+}
+
+impl Property for DependenceProp {
+    fn property_id(&self) -> String {
+        "DependenceProp".to_string()
+    }
+}
+
+impl fmt::Display for DependenceProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Dependence {} on {}: {} -> {}, carried by {{{}}}",
+            self.kind,
+            self.array,
+            self.source_inst_id,
+            self.sink_inst_id,
+            self.carriers.join(", ")
+        )
+    }
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// GCD test on every dimension of the two accesses.
+/// Returns `None` if the accesses are independent, otherwise the direction of the dependence
+/// along each iterator. The direction is known along an iterator indexing a dimension alone with
+/// the same coefficient in both accesses, e.g. `A[i + 1]` and `A[i]`, and `*` otherwise.
+/// Non-linear indices, e.g. polynomial ones like `A[i^2]`, are conservatively assumed to be
+/// dependent.
+fn dependence_directions(
+    source: &DataAccess,
+    sink: &DataAccess,
+    iter_names: &[String],
+) -> Option<Vec<(String, Direction)>> {
+    // dependence distance from the source to the sink along an iterator
+    let mut distances: Vec<(String, i32)> = Vec::new();
+    for (source_idx, sink_idx) in source.addr.iter().zip(sink.addr.iter()) {
+        let (Some((source_coeffs, source_const)), Some((sink_coeffs, sink_const))) =
+            (source_idx.to_linear_form(), sink_idx.to_linear_form())
+        else {
+            continue;
+        };
+        // sum(source_coeffs * i) - sum(sink_coeffs * i') = sink_const - source_const
+        let divisor = source_coeffs
+            .values()
+            .chain(sink_coeffs.values())
+            .fold(0, |acc, coeff| gcd(acc, *coeff));
+        let diff = sink_const - source_const;
+        if (divisor == 0 && diff != 0) || (divisor != 0 && diff % divisor != 0) {
+            return None;
+        }
+        if source_coeffs == sink_coeffs && source_coeffs.len() == 1 {
+            // c * i + a = c * i' + b, i' - i = -diff / c
+            let (iter, coeff) = source_coeffs.into_iter().next().unwrap();
+            let distance = -diff / coeff;
+            match distances.iter().find(|(name, _)| *name == iter) {
+                Some((_, other)) if *other != distance => return None,
+                Some(_) => {}
+                None => distances.push((iter, distance)),
+            }
+        }
+    }
+    Some(
+        iter_names
+            .iter()
+            .map(|name| {
+                let direction = match distances.iter().find(|(iter, _)| iter == name) {
+                    Some((_, distance)) if *distance > 0 => Direction::Lt,
+                    Some((_, 0)) => Direction::Eq,
+                    Some(_) => Direction::Gt,
+                    None => Direction::Any,
+                };
+                (name.clone(), direction)
+            })
+            .collect(),
+    )
+}
+
+fn dependence_kind(source_is_store: bool, sink_is_store: bool) -> DependenceKind {
+    match (source_is_store, sink_is_store) {
+        (true, false) => DependenceKind::RAW,
+        (false, true) => DependenceKind::WAR,
+        (true, true) => DependenceKind::WAW,
+        (false, false) => DependenceKind::None,
+    }
+}
+
+/// Tests every pair of accesses to the same array involving a store with the GCD test.
+/// A dependence between two instructions in body order is reported from the first one to the
+/// second one; when it may be loop-carried, the dependence from the second one to the first one
+/// in a later iteration is reported as well.
+pub struct LoopDependenceAnalysis;
+
+impl PassRun for LoopDependenceAnalysis {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let accesses: Vec<(Vec<&LoopIter>, &Instruction, &DataAccess, bool)> = workspace
+            .loop_nest
+            .scoped_instructions()
+            .into_iter()
+            .filter_map(|(iters, inst)| match inst {
+                Instruction::DataLoad(access) => Some((iters, inst, access, false)),
+                Instruction::DataStore(access) => Some((iters, inst, access, true)),
+                _ => None,
+            })
+            .collect();
+
+        let mut dependences = Vec::new();
+        for (idx, (first_iters, first, first_access, first_is_store)) in accesses.iter().enumerate()
+        {
+            let (first, first_access, first_is_store) = (*first, *first_access, *first_is_store);
+            for (second_iters, second, second_access, second_is_store) in &accesses[idx..] {
+                let (second, second_access, second_is_store) =
+                    (*second, *second_access, *second_is_store);
+                // only the loops enclosing both accesses can carry a dependence between them
+                let iter_names: Vec<String> = first_iters
+                    .iter()
+                    .zip(second_iters.iter())
+                    .take_while(|(a, b)| std::ptr::eq(**a, **b))
+                    .map(|(iter, _)| iter.iter_name.clone())
+                    .collect();
+                let same_inst = std::ptr::eq(first, second);
+                if !(first_is_store || second_is_store)
+                    || first_access.array_name != second_access.array_name
+                    || first_access.addr.len() != second_access.addr.len()
+                {
+                    continue;
+                }
+                let dependence =
+                    |source: &Instruction,
+                     sink: &Instruction,
+                     kind,
+                     directions: Vec<(String, Direction)>| {
+                        (
+                            source.clone(),
+                            DependenceProp {
+                                array: first_access.array_name.clone(),
+                                source_inst_id: source.property_hook_id(),
+                                sink_inst_id: sink.property_hook_id(),
+                                kind,
+                                carriers: directions
+                                    .iter()
+                                    .filter(|(_, direction)| *direction != Direction::Eq)
+                                    .map(|(iter, _)| iter.clone())
+                                    .collect(),
+                                directions,
+                            },
+                        )
+                    };
+                match dependence_directions(first_access, second_access, &iter_names) {
+                    None => {
+                        dependences.push(dependence(first, second, DependenceKind::None, vec![]))
+                    }
+                    Some(directions) => {
+                        let carried = directions
+                            .iter()
+                            .any(|(_, direction)| *direction != Direction::Eq);
+                        if !same_inst && carried {
+                            dependences.push(dependence(
+                                second,
+                                first,
+                                dependence_kind(second_is_store, first_is_store),
+                                directions
+                                    .iter()
+                                    .map(|(iter, direction)| (iter.clone(), direction.reversed()))
+                                    .collect(),
+                            ));
+                        }
+                        dependences.push(dependence(
+                            first,
+                            second,
+                            dependence_kind(first_is_store, second_is_store),
+                            directions,
+                        ));
+                    }
+                }
+            }
+        }
+        for (source, dependence) in dependences {
+            workspace.add_property(&source, Box::new(dependence));
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for LoopDependenceAnalysis {
+    fn name(&self) -> &str {
+        "Loop Dependence Analysis"
+    }
+
+    fn description(&self) -> &str {
+        "Finds the dependences between the memory accesses using the GCD test"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![Feature::MemAccess]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::DependenceInfo]
+    }
+}
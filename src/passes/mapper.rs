@@ -1,11 +1,15 @@
 use crate::representations::arch::*;
 use crate::representations::loops::*;
+use crate::representations::mapping::{Mapping, MappingType};
 
-struct Mapper {
+// WIP: the mapper is not wired into the pass pipeline yet
+#[allow(dead_code)]
+pub struct Mapper {
     arch: Arch,
     loop_prob: LoopNest,
 }
 
+#[allow(dead_code)]
 impl Mapper {
     pub fn new(arch: Arch, loop_prob: LoopNest) -> Self {
         Self { arch, loop_prob }
@@ -19,23 +23,11 @@ impl Mapper {
         topo1.iter().zip(topo2.iter()).all(|(a, b)| a + b == 0)
     }
 
-    fn get_noc_ports(&self) -> Vec<&NocPort> {
-        self.arch
-            .pe_arch
-            .data_ports
-            .iter()
-            .filter_map(|port| match port {
-                DataPort::NocPort(noc) => Some(noc),
-                _ => None,
-            })
-            .collect()
-    }
-
     /// For each NOC port, there needs be a corresponding NOC in the opposite direction.
     /// E.g. for [0,1] (NORTH), there needs to be a [0,-1] (SOUTH).
     fn check_noc_symetry(&self) -> bool {
         // Get all NOC ports from all data ports
-        let noc_ports = self.get_noc_ports();
+        let noc_ports = self.arch.noc_ports();
 
         // For each port, check if it has a symmetric opposite
         noc_ports.iter().all(|port1| {
@@ -50,7 +42,7 @@ impl Mapper {
 
     /// Find the NOC port that is symmetric opposite to the given NOC port
     fn find_symmetric_noc(&self, noc: &NocPort) -> Option<&NocPort> {
-        let noc_ports = self.get_noc_ports();
+        let noc_ports = self.arch.noc_ports();
         noc_ports
             .iter()
             .find(|&noc2| Mapper::is_symmetric_opposites(&noc.topology, &noc2.topology))
@@ -61,7 +53,7 @@ impl Mapper {
     /// For instance, a 2D mesh has a rank of 2.
     /// If a 2D mesh has 2 ports in each direction (total of 8 ports), its rank is 4
     fn noc_rank(&self) -> usize {
-        let noc_ports = self.get_noc_ports();
+        let noc_ports = self.arch.noc_ports();
         assert!(
             self.check_noc_symetry(),
             "You can only get the rank if the NOC is symmetrical"
@@ -69,6 +61,63 @@ impl Mapper {
         noc_ports.len() / 2
     }
 
-    /// For all data accesses in the loop body, generate all possible mappings to the data ports
-    pub fn generate_all_mappings(&self) {}
+    /// Generate all the mappings of the loop iterators: each iterator is either mapped
+    /// on a NoC dimension, or is temporal, inter-tile or intra-tile.
+    /// A NoC dimension has at most one iterator mapped on it, and the iterators are only
+    /// mapped spatially if the NoC is symmetric.
+    /// The number of mappings is exponential in the number of iterators.
+    pub fn generate_all_mappings(&self) -> Vec<Mapping> {
+        let mut iter_names: Vec<&str> = Vec::new();
+        for iter in self.loop_prob.all_iters() {
+            if !iter_names.contains(&iter.iter_name.as_str()) {
+                iter_names.push(&iter.iter_name);
+            }
+        }
+        let spatial_dims = if self.check_noc_symetry() {
+            self.arch
+                .spatial_dimensions()
+                .into_iter()
+                .map(|dim| &dim.name)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let mut mappings = Vec::new();
+        Mapper::assign_mappings(&iter_names, &spatial_dims, &mut Vec::new(), &mut mappings);
+        mappings
+    }
+
+    /// Map the first of `iters` in every possible way, and recurse on the others
+    fn assign_mappings(
+        iters: &[&str],
+        spatial_dims: &[&String],
+        assigned: &mut Vec<(String, MappingType)>,
+        mappings: &mut Vec<Mapping>,
+    ) {
+        let Some((iter, rest)) = iters.split_first() else {
+            mappings.push(Mapping {
+                loop_nest: None,
+                types: assigned.iter().cloned().collect(),
+            });
+            return;
+        };
+        let free_dims = spatial_dims.iter().filter(|dim| {
+            !assigned
+                .iter()
+                .any(|(_, mapping_type)| matches!(mapping_type, MappingType::Spatial(name) if name == **dim))
+        });
+        let candidates: Vec<MappingType> = free_dims
+            .map(|dim| MappingType::Spatial(dim.to_string()))
+            .chain([
+                MappingType::TemporalTODO,
+                MappingType::InterTile,
+                MappingType::IntraTile,
+            ])
+            .collect();
+        for mapping_type in candidates {
+            assigned.push((iter.to_string(), mapping_type));
+            Mapper::assign_mappings(rest, spatial_dims, assigned, mappings);
+            assigned.pop();
+        }
+    }
 }
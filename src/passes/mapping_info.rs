@@ -0,0 +1,65 @@
+use core::fmt;
+
+use super::feature::Feature;
+use super::passes::*;
+use super::workspace::Workspace;
+use crate::representations::mapping::Mapping;
+
+use crate::passes::property::*;
+
+#[derive(Clone)]
+pub struct MappingInfo {
+    pub mapping: Mapping,
+}
+
+impl Property for MappingInfo {
+    fn property_id(&self) -> String {
+        "MappingInfo".to_string()
+    }
+}
+
+impl fmt::Display for MappingInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mapping)
+    }
+}
+
+pub struct MappingInfoBuilder {
+    pub mapping_info: MappingInfo,
+}
+
+impl MappingInfoBuilder {
+    pub fn from_mapping(mapping: Mapping) -> Self {
+        let mapping_info = MappingInfo { mapping };
+        Self { mapping_info }
+    }
+}
+
+impl PassInfo for MappingInfoBuilder {
+    fn name(&self) -> &str {
+        "MappingInfoBuilder"
+    }
+
+    fn description(&self) -> &str {
+        "Builds the mapping information"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::MappingInfo]
+    }
+}
+
+impl PassRun for MappingInfoBuilder {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        workspace.add_global_property(Box::new(self.mapping_info.clone()));
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
@@ -1,12 +1,14 @@
 use core::fmt;
 
-use crate::representations::{affine_expr::AffineExpr, instruction::Instruction};
+use crate::representations::instruction::Instruction;
 
 use super::property::Property;
 
+use super::feature::Feature;
 use super::passes::{PassInfo, PassRun};
 use super::workspace::Workspace;
 
+#[derive(Clone)]
 pub struct MemAccessProp {
     pub accessed_dims: Vec<String>,
 }
@@ -22,43 +24,32 @@ impl fmt::Display for MemAccessProp {
         write!(f, "Accessed Dims: {{{}}}", self.accessed_dims.join(", "))
     }
 }
-pub struct MemAccessAnalysis;
 
-impl MemAccessAnalysis {
-    fn get_accesses_from_affine_expr(expr: &AffineExpr) -> Vec<String> {
-        match expr {
-            AffineExpr::Var(var) => {
-                vec![var.clone()]
-            }
-            AffineExpr::Const(_) => {
-                vec![]
-            }
-            AffineExpr::Add(expr1, expr2) => {
-                let mut accesses = Self::get_accesses_from_affine_expr(expr1);
-                accesses.extend(Self::get_accesses_from_affine_expr(expr2));
-                accesses
-            }
-            AffineExpr::Sub(expr1, expr2) => {
-                let mut accesses = Self::get_accesses_from_affine_expr(expr1);
-                accesses.extend(Self::get_accesses_from_affine_expr(expr2));
-                accesses
-            }
-            AffineExpr::Mul(_, expr) => Self::get_accesses_from_affine_expr(expr),
-            AffineExpr::Div(expr, _) => Self::get_accesses_from_affine_expr(expr),
-            AffineExpr::Mod(expr, _) => Self::get_accesses_from_affine_expr(expr),
-        }
-    }
+#[derive(Default)]
+pub struct MemAccessAnalysis {
+    /// Only analyze the instructions of the outermost body, not the ones of the nested loop nests
+    pub top_level_only: bool,
 }
 
 impl PassRun for MemAccessAnalysis {
     fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
-        for inst in workspace.loop_nest.body.clone().iter() {
+        let loop_nest = workspace.loop_nest.clone();
+        let outer_depth = loop_nest.iters.len();
+        for (iters, inst) in loop_nest.scoped_instructions() {
+            if self.top_level_only && iters.len() > outer_depth {
+                continue;
+            }
+            if let Instruction::SyncBarrier(_) = inst {
+                continue;
+            }
             let properties = match inst {
-                Instruction::DataLoad(mem_access) | Instruction::DataStore(mem_access) => {
+                Instruction::DataLoad(mem_access)
+                | Instruction::DataStore(mem_access)
+                | Instruction::Prefetch(mem_access) => {
                     let accessed_dims = mem_access
                         .addr
                         .iter()
-                        .flat_map(|expr| Self::get_accesses_from_affine_expr(expr))
+                        .flat_map(|expr| expr.vars())
                         .collect();
                     vec![Box::new(MemAccessProp { accessed_dims })]
                 }
@@ -72,7 +63,6 @@ impl PassRun for MemAccessAnalysis {
         }
         Ok(())
     }
-    
 
     fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
         Ok(())
@@ -88,11 +78,11 @@ impl PassInfo for MemAccessAnalysis {
         "Memory Access Analysis"
     }
 
-    fn required_features(&self) -> Vec<String> {
+    fn required_features(&self) -> Vec<Feature> {
         vec![]
     }
 
-    fn produced_features(&self) -> Vec<String> {
-        vec!["MemAccess".to_string()]
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::MemAccess]
     }
 }
@@ -0,0 +1,261 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use log::warn;
+
+use crate::representations::affine_expr::{AffineExpr, Coeff};
+use crate::representations::instruction::Instruction;
+use crate::representations::loops::{LoopIter, LoopNest};
+
+use super::feature::Feature;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// Number of elements accessed in each array over the whole execution of the loop nest
+#[derive(Clone)]
+pub struct MemoryFootprintProp {
+    pub footprints: HashMap<String, AffineExpr>,
+}
+
+impl Property for MemoryFootprintProp {
+    fn property_id(&self) -> String {
+        "MemoryFootprintProp".to_string()
+    }
+}
+
+impl fmt::Display for MemoryFootprintProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut footprints: Vec<String> = self
+            .footprints
+            .iter()
+            .map(|(array, footprint)| format!("{}: {}", array, footprint))
+            .collect();
+        footprints.sort();
+        write!(f, "Memory Footprint: {{{}}}", footprints.join(", "))
+    }
+}
+
+/// Range `(min, max)` of the values taken by an index expression over the enclosing iterators,
+/// symbolic if the bounds of the iterators are symbolic.
+/// Returns `None` if the expression is not linear, uses a variable which is not an iterator,
+/// or an iterator with a symbolic step or no iteration.
+fn index_range(expr: &AffineExpr, iters: &[&LoopIter]) -> Option<(AffineExpr, AffineExpr)> {
+    let (coeffs, constant) = expr.to_linear_form()?;
+    let (mut min, mut max) = (
+        vec![AffineExpr::Const(constant)],
+        vec![AffineExpr::Const(constant)],
+    );
+    for (var, coeff) in coeffs {
+        let iter = iters.iter().find(|iter| iter.iter_name == var)?;
+        let step = iter.concrete_step(&HashMap::new())?;
+        if step <= 0 {
+            return None;
+        }
+        let (first, last) = match iter.concrete_bounds(&HashMap::new()) {
+            Some((lower, upper)) if upper <= lower => return None,
+            Some((lower, upper)) => (
+                AffineExpr::Const(lower),
+                AffineExpr::Const(lower + (upper - lower - 1) / step * step),
+            ),
+            None if step == 1 => (iter.bounds.0.clone(), iter.bounds.1.offset(-1)),
+            // lower + (upper - lower - 1) / step * step
+            None => {
+                let span = AffineExpr::Sub(
+                    Box::new(iter.bounds.1.offset(-1)),
+                    Box::new(iter.bounds.0.clone()),
+                );
+                let last = AffineExpr::Div(Box::new(span), Coeff::Const(step)).scale(step);
+                (
+                    iter.bounds.0.clone(),
+                    AffineExpr::Add(Box::new(iter.bounds.0.clone()), Box::new(last)),
+                )
+            }
+        };
+        let (a, b) = (first.scale(coeff), last.scale(coeff));
+        if coeff > 0 {
+            min.push(a);
+            max.push(b);
+        } else {
+            min.push(b);
+            max.push(a);
+        }
+    }
+    Some((
+        AffineExpr::sum(min).simplify(),
+        AffineExpr::sum(max).simplify(),
+    ))
+}
+
+/// The expression as a coefficient, its variables being symbolic parameters.
+/// Returns `None` if the expression is not a sum of products of parameters.
+fn to_coeff(expr: &AffineExpr) -> Option<Coeff> {
+    match expr {
+        AffineExpr::Const(c) => Some(Coeff::Const(*c)),
+        AffineExpr::Var(var) => Some(Coeff::ConstVar(var.clone())),
+        AffineExpr::Add(e1, e2) => {
+            Some(Coeff::Add(Box::new(to_coeff(e1)?), Box::new(to_coeff(e2)?)))
+        }
+        AffineExpr::Sub(e1, e2) => Some(Coeff::Add(
+            Box::new(to_coeff(e1)?),
+            Box::new(Coeff::Neg(Box::new(to_coeff(e2)?))),
+        )),
+        AffineExpr::Mul(coeff, e) => {
+            Some(Coeff::Mul(Box::new(coeff.clone()), Box::new(to_coeff(e)?)))
+        }
+        AffineExpr::Neg(e) => Some(Coeff::Neg(Box::new(to_coeff(e)?))),
+        _ => None,
+    }
+}
+
+/// Number of elements of the bounding box, `None` if it cannot be represented
+fn box_size(bounding_box: &[(AffineExpr, AffineExpr)]) -> Result<Option<AffineExpr>, String> {
+    let extents: Vec<AffineExpr> = bounding_box
+        .iter()
+        .map(|(min, max)| {
+            AffineExpr::Sub(Box::new(max.offset(1)), Box::new(min.clone())).simplify()
+        })
+        .collect();
+    let concrete: Option<Vec<i64>> = extents
+        .iter()
+        .map(|extent| match extent {
+            AffineExpr::Const(c) => Some(*c as i64),
+            _ => None,
+        })
+        .collect();
+    if let Some(concrete) = concrete {
+        let size = concrete
+            .into_iter()
+            .try_fold(1i64, |size, extent| size.checked_mul(extent))
+            .and_then(|size| i32::try_from(size).ok())
+            .ok_or_else(|| {
+                format!(
+                    "{} elements",
+                    extents
+                        .iter()
+                        .map(|e| e.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" * ")
+                )
+            })?;
+        return Ok(Some(AffineExpr::Const(size)));
+    }
+    // c * N * ..., the constant extents are folded into the coefficient
+    let mut size = AffineExpr::Const(1);
+    for extent in extents {
+        size = match (size, extent) {
+            (AffineExpr::Const(c), extent) | (extent, AffineExpr::Const(c)) => extent.scale(c),
+            (size, extent) => match to_coeff(&extent) {
+                Some(coeff) => AffineExpr::Mul(coeff, Box::new(size)),
+                None => return Ok(None),
+            },
+        };
+    }
+    Ok(Some(size))
+}
+
+/// Computes the number of elements accessed in each array.
+/// The footprint is the size of the bounding box of the accessed indices, which is exact for
+/// dense accesses and an upper bound otherwise, e.g. `A[c * i + d]` with `i in (0..N)` spans
+/// `c * (N - 1) + 1` elements.
+pub struct MemoryFootprintPass;
+
+impl MemoryFootprintPass {
+    /// Returns an error if a constant footprint does not fit in an `i32`
+    pub fn footprints(loop_nest: &LoopNest) -> Result<HashMap<String, AffineExpr>, String> {
+        let mut boxes: HashMap<String, Option<Vec<(AffineExpr, AffineExpr)>>> = HashMap::new();
+        for (iters, inst) in loop_nest.scoped_instructions() {
+            let mem_access = match inst {
+                Instruction::DataLoad(mem_access) | Instruction::DataStore(mem_access) => {
+                    mem_access
+                }
+                _ => continue,
+            };
+            let ranges: Option<Vec<(AffineExpr, AffineExpr)>> = mem_access
+                .addr
+                .iter()
+                .map(|expr| index_range(expr, &iters))
+                .collect();
+            let bounding_box = boxes
+                .entry(mem_access.array_name.clone())
+                .or_insert_with(|| ranges.clone());
+            let merge =
+                |a: &AffineExpr,
+                 b: &AffineExpr,
+                 extremum: fn(Box<AffineExpr>, Box<AffineExpr>) -> AffineExpr| {
+                    if a == b {
+                        a.clone()
+                    } else {
+                        extremum(Box::new(a.clone()), Box::new(b.clone())).simplify()
+                    }
+                };
+            *bounding_box = match (bounding_box.take(), ranges) {
+                (Some(bounding_box), Some(ranges)) if bounding_box.len() == ranges.len() => Some(
+                    bounding_box
+                        .iter()
+                        .zip(ranges.iter())
+                        .map(|(a, b)| {
+                            (
+                                merge(&a.0, &b.0, AffineExpr::Min),
+                                merge(&a.1, &b.1, AffineExpr::Max),
+                            )
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            };
+        }
+        let mut footprints = HashMap::new();
+        for (array, bounding_box) in boxes {
+            let size = match bounding_box {
+                Some(bounding_box) => box_size(&bounding_box).map_err(|size| {
+                    format!(
+                        "The memory footprint of the array {} does not fit in an i32: {}",
+                        array, size
+                    )
+                })?,
+                None => None,
+            };
+            match size {
+                Some(size) => {
+                    footprints.insert(array, size);
+                }
+                None => warn!("Cannot compute the memory footprint of the array {}", array),
+            }
+        }
+        Ok(footprints)
+    }
+}
+
+impl PassRun for MemoryFootprintPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let footprints = MemoryFootprintPass::footprints(&workspace.loop_nest).map_err(|e| {
+            warn!("{}", e);
+            "The memory footprint does not fit in an i32"
+        })?;
+        workspace.add_global_property(Box::new(MemoryFootprintProp { footprints }));
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for MemoryFootprintPass {
+    fn name(&self) -> &str {
+        "Memory Footprint"
+    }
+
+    fn description(&self) -> &str {
+        "Computes the number of elements accessed in each array"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::MemoryFootprint]
+    }
+}
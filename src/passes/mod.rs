@@ -1,11 +1,25 @@
 pub mod arch_info;
+pub mod arithmetic_intensity;
+pub mod bandwidth_demand;
+pub mod compute_count;
+pub mod data_reuse_analysis;
 pub mod feature;
 pub mod free_dim_analysis;
+pub mod loop_dependence_analysis;
 pub mod mapper;
+pub mod mapping_info;
 pub mod mem_access_analysis;
+pub mod memory_footprint;
 pub mod noc_analysis;
+pub mod operation_count;
+pub mod parallelizability;
 pub mod pass_pipeline;
+#[allow(clippy::module_inception)]
 pub mod passes;
 pub mod property;
+pub mod registry;
+pub mod stride_detection;
+pub mod sync_barrier;
 pub mod transform_pass;
+pub mod vectorization_analysis;
 pub mod workspace;
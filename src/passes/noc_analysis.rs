@@ -1,15 +1,59 @@
-use crate::representations::{arch::DataPort, loops::LoopNest};
+use std::collections::HashMap;
+
+use log::warn;
+
+use crate::representations::{
+    arch::{Arch, DataPort},
+    loops::LoopNest,
+    mapping::Mapping,
+};
+
+use super::bandwidth_demand::{BandwidthDemandPass, BandwidthViolationProp};
+use super::memory_footprint::MemoryFootprintPass;
 
 pub struct NoCAnalysis {
     pub loop_nest: LoopNest,
+    pub arch: Arch,
     pub concretized_noc: Vec<DataPort>,
+    /// The ports overflowed by the traffic of the last `compute_traffic`
+    pub violations: Vec<BandwidthViolationProp>,
 }
 
 impl NoCAnalysis {
-    pub fn from_loop_nest(loop_nest: LoopNest) -> NoCAnalysis {
+    pub fn new(loop_nest: LoopNest, arch: Arch) -> NoCAnalysis {
         NoCAnalysis {
             loop_nest,
-            concretized_noc: Vec::new(),
+            concretized_noc: arch.data_ports().clone(),
+            arch,
+            violations: Vec::new(),
         }
     }
+
+    /// Estimate the bytes per cycle going through each data port for the mapping,
+    /// see `BandwidthDemandPass`. The ports whose bandwidth is exceeded are kept in `violations`.
+    /// Returns an empty map if the traffic cannot be estimated, e.g. for symbolic bounds.
+    pub fn compute_traffic(&mut self, mapping: &Mapping) -> HashMap<String, f64> {
+        let footprints = match MemoryFootprintPass::footprints(&self.loop_nest) {
+            Ok(footprints) => footprints,
+            Err(e) => {
+                warn!("Cannot estimate the NoC traffic: {}", e);
+                self.violations.clear();
+                return HashMap::new();
+            }
+        };
+        let demands =
+            match BandwidthDemandPass::demands(&self.loop_nest, &self.arch, mapping, &footprints) {
+                Ok(demands) => demands,
+                Err(e) => {
+                    warn!("Cannot estimate the NoC traffic: {}", e);
+                    self.violations.clear();
+                    return HashMap::new();
+                }
+            };
+        self.violations = BandwidthDemandPass::violations(&self.arch, &demands);
+        demands
+            .into_iter()
+            .map(|demand| (demand.port_name, demand.bytes_per_cycle))
+            .collect()
+    }
 }
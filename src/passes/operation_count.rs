@@ -0,0 +1,97 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use crate::representations::instruction::Instruction;
+
+use super::feature::Feature;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// Number of instructions of each type in the loop body
+#[derive(Clone, Default)]
+pub struct OperationCountProp {
+    pub loads: u32,
+    pub stores: u32,
+    /// Compute instructions grouped by operation
+    pub compute_ops: HashMap<String, u32>,
+}
+
+impl OperationCountProp {
+    pub fn total_memory_ops(&self) -> u32 {
+        self.loads + self.stores
+    }
+
+    pub fn total_compute_ops(&self) -> u32 {
+        self.compute_ops.values().sum()
+    }
+}
+
+impl Property for OperationCountProp {
+    fn property_id(&self) -> String {
+        "OperationCountProp".to_string()
+    }
+}
+
+impl fmt::Display for OperationCountProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut compute_ops: Vec<String> = self
+            .compute_ops
+            .iter()
+            .map(|(op, count)| format!("{}: {}", op, count))
+            .collect();
+        compute_ops.sort();
+        write!(
+            f,
+            "Loads: {}, Stores: {}, Compute: {{{}}}",
+            self.loads,
+            self.stores,
+            compute_ops.join(", ")
+        )
+    }
+}
+
+pub struct OperationCountPass;
+
+impl PassRun for OperationCountPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let mut counts = OperationCountProp::default();
+        for inst in workspace.loop_nest.all_instructions() {
+            match inst {
+                Instruction::DataLoad(_) => counts.loads += 1,
+                Instruction::DataStore(_) => counts.stores += 1,
+                Instruction::Compute(compute) => {
+                    *counts.compute_ops.entry(compute.op.clone()).or_default() += 1
+                }
+                // prefetches are hints, not memory operations of the program
+                Instruction::Prefetch(_)
+                | Instruction::SyncBarrier(_)
+                | Instruction::LoopBody(_) => {}
+            }
+        }
+        workspace.add_global_property(Box::new(counts));
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for OperationCountPass {
+    fn name(&self) -> &str {
+        "Operation Count"
+    }
+
+    fn description(&self) -> &str {
+        "Counts the instructions of the loop body by type"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::OperationCount]
+    }
+}
@@ -0,0 +1,68 @@
+use core::fmt;
+
+use super::feature::Feature;
+use super::loop_dependence_analysis::{DependenceKind, DependenceProp};
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+#[derive(Clone)]
+pub struct ParallelizableProp {
+    pub is_parallel: bool,
+}
+
+impl Property for ParallelizableProp {
+    fn property_id(&self) -> String {
+        "ParallelizableProp".to_string()
+    }
+}
+
+impl fmt::Display for ParallelizableProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Parallel: {}", self.is_parallel)
+    }
+}
+
+/// An iterator is parallelizable if it carries none of the dependences of the loop body
+pub struct ParallelizabilityPass;
+
+impl PassRun for ParallelizabilityPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let carriers: Vec<String> = workspace
+            .loop_nest
+            .all_instructions()
+            .into_iter()
+            .flat_map(|inst| workspace.get_typed_property::<DependenceProp>(inst))
+            .filter(|dependence| dependence.kind != DependenceKind::None)
+            .flat_map(|dependence| dependence.carriers.clone())
+            .collect();
+        let loop_nest = workspace.loop_nest.clone();
+        for iter in loop_nest.all_iters() {
+            let is_parallel = !carriers.contains(&iter.iter_name);
+            workspace.add_property(iter, Box::new(ParallelizableProp { is_parallel }));
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for ParallelizabilityPass {
+    fn name(&self) -> &str {
+        "Parallelizability"
+    }
+
+    fn description(&self) -> &str {
+        "Checks whether each loop iterator carries no dependence and can be parallelized"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![Feature::DependenceInfo]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::Parallelizable]
+    }
+}
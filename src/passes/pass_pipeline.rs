@@ -1,32 +1,300 @@
-use log::{debug, info};
+use std::time::{Duration, Instant};
 
-use super::{passes::*, workspace::Workspace};
+use log::{debug, error, info};
 
+use super::{feature::Feature, passes::*, registry::PassRegistry, workspace::Workspace};
+
+/// Evaluated just before running a conditional pass, the pass is skipped if false
+type PassCondition = Box<dyn Fn(&Workspace) -> bool>;
+
+/// Notified of the progress of a pipeline run, see `PassPipeline::with_callback`
+pub trait PassProgressCallback: Send {
+    fn on_pass_start(&self, name: &str);
+    fn on_pass_end(&self, name: &str);
+    fn on_pass_error(&self, name: &str, err: &str);
+}
+
+/// Reports the progress with the `log` crate
+pub struct LoggingCallback;
+
+impl PassProgressCallback for LoggingCallback {
+    fn on_pass_start(&self, name: &str) {
+        info!("Pass {} started", name);
+    }
+
+    fn on_pass_end(&self, name: &str) {
+        info!("Pass {} finished", name);
+    }
+
+    fn on_pass_error(&self, name: &str, err: &str) {
+        error!("Pass {} failed: {}", name, err);
+    }
+}
+
+struct PassEntry {
+    pass: Box<dyn Pass>,
+    condition: Option<PassCondition>,
+}
+
+#[derive(Default)]
 pub struct PassPipeline {
-    passes: Vec<Box<dyn Pass>>,
+    passes: Vec<PassEntry>,
+    profiling: bool,
+    timings: Vec<(String, Duration)>,
+    /// No progress is reported without a callback
+    callback: Option<Box<dyn PassProgressCallback>>,
 }
 
 impl PassPipeline {
     pub fn new() -> Self {
-        PassPipeline { passes: Vec::new() }
+        PassPipeline {
+            passes: Vec::new(),
+            profiling: false,
+            timings: Vec::new(),
+            callback: None,
+        }
+    }
+
+    /// Build a pipeline of registered passes producing the `required` features, see `PassRegistry`.
+    /// Each feature, including the ones required by the selected passes, is produced by a single
+    /// pass, and the passes are ordered with `add_pass_ordered`.
+    pub fn from_feature_requirements(required: Vec<Feature>) -> Result<PassPipeline, String> {
+        // the factories run once the registry is released
+        let registry = PassRegistry::global().clone();
+        let mut pipeline = PassPipeline::new();
+        let mut produced: Vec<Feature> = Vec::new();
+        let mut pending = required;
+        while let Some(feature) = pending.pop() {
+            if produced.contains(&feature) {
+                continue;
+            }
+            let pass = match registry.producer(&feature) {
+                Some(factory) => factory(),
+                None => {
+                    return Err(format!(
+                        "Feature {} is not produced by any known pass",
+                        feature
+                    ))
+                }
+            };
+            produced.extend(pass.produced_features());
+            pending.extend(pass.required_features());
+            pipeline.add_pass_ordered(pass)?;
+        }
+        Ok(pipeline)
+    }
+
+    /// Enable the measurement of the wall-clock time of each pass, see `timing_report`
+    pub fn with_profiling(mut self) -> Self {
+        self.profiling = true;
+        self
+    }
+
+    /// Report the start, the end and the failure of each pass run to `cb`
+    pub fn with_callback(mut self, cb: Box<dyn PassProgressCallback>) -> Self {
+        self.callback = Some(cb);
+        self
+    }
+
+    /// Execution time of each pass during the last `run`, in execution order.
+    /// Empty if profiling is not enabled.
+    pub fn timing_report(&self) -> Vec<(String, Duration)> {
+        self.timings.clone()
     }
 
     pub fn register_pass(&mut self, pass: Box<dyn Pass>) {
-        self.passes.push(pass);
+        self.passes.push(PassEntry {
+            pass,
+            condition: None,
+        });
+    }
+
+    /// Register a pass only run when `cond` holds on the workspace at the time the pass would execute.
+    /// A skipped pass does not make its produced features available.
+    pub fn register_pass_conditional(
+        &mut self,
+        pass: Box<dyn Pass>,
+        cond: impl Fn(&Workspace) -> bool + 'static,
+    ) {
+        self.passes.push(PassEntry {
+            pass,
+            condition: Some(Box::new(cond)),
+        });
+    }
+
+    /// Registered passes producing `feature`, in pipeline order
+    pub fn passes_for_feature(&self, feature: Feature) -> Vec<&dyn PassInfo> {
+        self.passes
+            .iter()
+            .filter(|entry| entry.pass.produced_features().contains(&feature))
+            .map(|entry| entry.pass.as_ref() as &dyn PassInfo)
+            .collect()
+    }
+
+    /// Registered passes requiring `feature`, in pipeline order
+    pub fn passes_requiring_feature(&self, feature: Feature) -> Vec<&dyn PassInfo> {
+        self.passes
+            .iter()
+            .filter(|entry| entry.pass.required_features().contains(&feature))
+            .map(|entry| entry.pass.as_ref() as &dyn PassInfo)
+            .collect()
+    }
+
+    /// Register a pass and reorder the pipeline so that every pass runs after
+    /// the passes producing its required features.
+    /// Passes without dependencies between them keep their registration order.
+    /// If the new pass introduces a dependency cycle, it is not registered and an error is returned.
+    pub fn add_pass_ordered(&mut self, pass: Box<dyn Pass>) -> Result<(), String> {
+        self.passes.push(PassEntry {
+            pass,
+            condition: None,
+        });
+        let order = match self.topological_order() {
+            Ok(order) => order,
+            Err(e) => {
+                self.passes.pop();
+                return Err(e);
+            }
+        };
+        let mut passes: Vec<Option<PassEntry>> = self.passes.drain(..).map(Some).collect();
+        self.passes = order
+            .into_iter()
+            .map(|idx| passes[idx].take().unwrap())
+            .collect();
+        Ok(())
+    }
+
+    /// Kahn's algorithm on the producer -> consumer graph of the registered passes,
+    /// always picking the earliest registered pass among the ready ones.
+    fn topological_order(&self) -> Result<Vec<usize>, String> {
+        let n = self.passes.len();
+        let mut successors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut in_degree = vec![0; n];
+        for (producer, producer_pass) in self.passes.iter().enumerate() {
+            let produced = producer_pass.pass.produced_features();
+            for (consumer, consumer_pass) in self.passes.iter().enumerate() {
+                if producer != consumer
+                    && consumer_pass
+                        .pass
+                        .required_features()
+                        .iter()
+                        .any(|feature| produced.contains(feature))
+                {
+                    successors[producer].push(consumer);
+                    in_degree[consumer] += 1;
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(n);
+        let mut done = vec![false; n];
+        while order.len() < n {
+            let next = (0..n).find(|&idx| !done[idx] && in_degree[idx] == 0);
+            match next {
+                Some(idx) => {
+                    done[idx] = true;
+                    order.push(idx);
+                    for &succ in &successors[idx] {
+                        in_degree[succ] -= 1;
+                    }
+                }
+                None => {
+                    let cycle: Vec<&str> = (0..n)
+                        .filter(|&idx| !done[idx])
+                        .map(|idx| self.passes[idx].pass.name())
+                        .collect();
+                    return Err(format!(
+                        "Cyclic feature dependencies between passes: {}",
+                        cycle.join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Check the feature dependency chain without running the passes:
+    /// each required feature must be available in the workspace or produced by a preceding pass.
+    /// Returns warnings for required features that no pass of the pipeline produces
+    /// (they are only satisfied by the initial workspace).
+    pub fn run_dry(&self, workspace: &Workspace) -> Result<Vec<String>, String> {
+        let mut warnings = Vec::new();
+        let mut produced: Vec<Feature> = Vec::new();
+        for (idx, entry) in self.passes.iter().enumerate() {
+            let pass = &entry.pass;
+            for required_feature in pass.required_features() {
+                if produced.contains(&required_feature) {
+                    continue;
+                }
+                if workspace.feature_available(&required_feature) {
+                    warnings.push(format!(
+                        "Required feature {} for pass {} is not produced by the pipeline, using the one available in the workspace",
+                        required_feature,
+                        pass.name()
+                    ));
+                    continue;
+                }
+                let later_producer = self.passes[idx + 1..]
+                    .iter()
+                    .find(|later| later.pass.produced_features().contains(&required_feature));
+                return Err(match later_producer {
+                    Some(later) => format!(
+                        "Required feature {} for pass {} is produced by pass {} which runs after it",
+                        required_feature,
+                        pass.name(),
+                        later.pass.name()
+                    ),
+                    None => format!(
+                        "Required feature {} for pass {} is never produced",
+                        required_feature,
+                        pass.name()
+                    ),
+                });
+            }
+            produced.extend(pass.produced_features());
+        }
+        Ok(warnings)
     }
 
     pub fn run(&mut self, workspace: &mut Workspace) -> Result<(), String> {
-        for pass in self.passes.iter_mut() {
-            pass.setup(workspace)?;
+        self.run_passes(workspace, None).map(|_| ())
+    }
+
+    /// Run the passes in order, stopping after the first pass producing `feature`.
+    /// If no pass produces it, all the passes are run and an error is returned.
+    pub fn run_until(&mut self, workspace: &mut Workspace, feature: Feature) -> Result<(), String> {
+        if self.run_passes(workspace, Some(&feature))? {
+            Ok(())
+        } else {
+            Err(format!("Feature {} is not produced by any pass", feature))
+        }
+    }
+
+    /// Returns whether the pipeline stopped on a pass producing `until`
+    fn run_passes(
+        &mut self,
+        workspace: &mut Workspace,
+        until: Option<&Feature>,
+    ) -> Result<bool, String> {
+        self.timings.clear();
+        for entry in self.passes.iter_mut() {
+            entry.pass.setup(workspace)?;
         }
 
-        for pass in self.passes.iter() {
+        for PassEntry { pass, condition } in self.passes.iter() {
+            if let Some(condition) = condition {
+                if !condition(workspace) {
+                    info!("Skipping pass: {}", pass.name());
+                    continue;
+                }
+            }
+
             // checking if the required properties are present
             for required_feature in pass.required_features() {
-                if !workspace.feature_available_str(&required_feature) {
+                if !workspace.feature_available(&required_feature) {
                     return Err(format!(
                         "Required property {} for pass {} not found",
-                        required_feature.clone(),
+                        required_feature,
                         pass.name()
                     ));
                 }
@@ -34,8 +302,34 @@ impl PassPipeline {
 
             info!("Running pass: {}", pass.name());
             debug!("Required features: {:?}", pass.required_features());
-            pass.run(workspace).map_err(|e| e.to_string())?;
+            if let Some(callback) = &self.callback {
+                callback.on_pass_start(pass.name());
+            }
+            let start = Instant::now();
+            if let Err(e) = pass.run(workspace) {
+                if let Some(callback) = &self.callback {
+                    callback.on_pass_error(pass.name(), e);
+                }
+                return Err(e.to_string());
+            }
+            if self.profiling {
+                self.timings
+                    .push((pass.name().to_string(), start.elapsed()));
+            }
+            if let Some(callback) = &self.callback {
+                callback.on_pass_end(pass.name());
+            }
+            let produced_features = pass.produced_features();
+            let stop = until.is_some_and(|feature| produced_features.contains(feature));
+            for produced_feature in produced_features {
+                if !workspace.feature_available(&produced_feature) {
+                    workspace.available_features.push(produced_feature);
+                }
+            }
+            if stop {
+                return Ok(true);
+            }
         }
-        Ok(())
+        Ok(false)
     }
 }
@@ -1,12 +1,15 @@
-use crate::representations::{instruction::Instruction, loops::LoopIter};
+use crate::representations::{
+    instruction::Instruction,
+    loops::{LoopIter, LoopNest},
+};
 
-use super::{property::Property, workspace::Workspace};
+use super::{feature::Feature, property::Property, workspace::Workspace};
 
 pub trait PassInfo {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
-    fn required_features(&self) -> Vec<String>;
-    fn produced_features(&self) -> Vec<String>;
+    fn required_features(&self) -> Vec<Feature>;
+    fn produced_features(&self) -> Vec<Feature>;
 }
 
 pub trait PassRun {
@@ -20,7 +23,8 @@ impl<T> Pass for T where T: PassInfo + PassRun {}
 pub trait InstPass: PassRun {
     fn pass_inst(&self, inst: &Instruction) -> Vec<Box<dyn Property>>;
     fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
-        for inst in workspace.loop_nest.body.clone().iter() {
+        let loop_nest = workspace.loop_nest.clone();
+        for inst in loop_nest.all_instructions() {
             let properties = self.pass_inst(inst);
             for property in properties {
                 workspace.add_property(inst, property);
@@ -33,7 +37,8 @@ pub trait InstPass: PassRun {
 pub trait IterPass: PassRun {
     fn pass_iter(&self, iter: &LoopIter) -> Vec<Box<dyn Property>>;
     fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
-        for iter in workspace.loop_nest.iters.clone().iter() {
+        let loop_nest = workspace.loop_nest.clone();
+        for iter in loop_nest.all_iters() {
             let properties = self.pass_iter(iter);
             for property in properties {
                 workspace.add_property(iter, property);
@@ -43,16 +48,16 @@ pub trait IterPass: PassRun {
     }
 }
 
-// pub trait LoopAnalysis {
-//     fn analyze_loop(&self, loop_nest: &LoopNest) -> Vec<Box<dyn Property>>;
-//     fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
-//         let properties = self.analyze_loop(workspace.loop_nest);
-//         for property in properties {
-//             workspace.add_property(loop_nest, property);
-//         }
-//         Ok(())
-//     }
-// }
+pub trait LoopPass: PassRun {
+    fn pass_loop(&self, loop_nest: &LoopNest) -> Vec<Box<dyn Property>>;
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let properties = self.pass_loop(&workspace.loop_nest);
+        for property in properties {
+            workspace.add_loop_property(property);
+        }
+        Ok(())
+    }
+}
 
 pub trait WorkspacePass: PassRun {
     fn pass_workspace(&self, workspace: &mut Workspace) -> Vec<Box<dyn Property>>;
@@ -1,10 +1,29 @@
 use core::fmt;
+use std::any::Any;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-pub trait Property: fmt::Display {
+pub trait Property: Any + Send + Sync + PropertyClone + fmt::Display {
     fn property_id(&self) -> String;
 }
 
+/// Clones a boxed property, implemented for all the properties implementing `Clone`
+pub trait PropertyClone {
+    fn clone_box(&self) -> Box<dyn Property>;
+}
+
+impl<T: Property + Clone> PropertyClone for T {
+    fn clone_box(&self) -> Box<dyn Property> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Property> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
 pub trait PropertyHook {
     fn property_hook_id(&self) -> String;
 }
@@ -15,6 +34,19 @@ impl<T: PropertyHook> PropertyHook for &T {
     }
 }
 
+impl<T: PropertyHook> PropertyHook for Box<T> {
+    fn property_hook_id(&self) -> String {
+        self.as_ref().property_hook_id()
+    }
+}
+
+impl<T: PropertyHook> PropertyHook for Arc<T> {
+    fn property_hook_id(&self) -> String {
+        self.as_ref().property_hook_id()
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct PropertyManager {
     properties: HashMap<String, Vec<Box<dyn Property>>>,
 }
@@ -38,7 +70,12 @@ impl PropertyManager {
 
     /// add entry
     pub fn add_entry(&mut self, entry: String) {
-        self.properties.entry(entry).or_insert(Vec::new());
+        self.properties.entry(entry).or_default();
+    }
+
+    /// Unregister the hook and drop its properties
+    pub fn remove_entry(&mut self, entry: &str) {
+        self.properties.remove(entry);
     }
 
     pub fn add_property_to_hook(
@@ -52,7 +89,7 @@ impl PropertyManager {
         }
         self.properties
             .entry(property_hook_id)
-            .or_insert(Vec::new())
+            .or_default()
             .push(property);
     }
 
@@ -64,10 +101,30 @@ impl PropertyManager {
         self.properties.get(&property_hook_id)
     }
 
+    /// Remove all the properties attached to the hook, the hook itself stays registered
+    pub fn remove_properties_for(&mut self, property_hook: impl PropertyHook) {
+        if let Some(properties) = self.properties.get_mut(&property_hook.property_hook_id()) {
+            properties.clear();
+        }
+    }
+
+    /// Remove the properties of the concrete type `T` attached to the hook
+    pub fn remove_property_by_type<T: Property + 'static>(
+        &mut self,
+        property_hook: impl PropertyHook,
+    ) {
+        if let Some(properties) = self.properties.get_mut(&property_hook.property_hook_id()) {
+            properties.retain(|property| {
+                let property: &dyn Any = property.as_ref();
+                !property.is::<T>()
+            });
+        }
+    }
+
     pub fn add_property_by_id(&mut self, property_hook_id: String, property: Box<dyn Property>) {
         self.properties
             .entry(property_hook_id)
-            .or_insert(Vec::new())
+            .or_default()
             .push(property);
     }
 
@@ -77,4 +134,105 @@ impl PropertyManager {
     ) -> Option<&Vec<Box<dyn Property>>> {
         self.properties.get(&property_hook_id)
     }
+
+    /// Append the properties of `other` to the ones of the same hook, duplicates are kept.
+    /// The hooks only registered in `other` are registered.
+    pub fn merge(&mut self, other: PropertyManager) {
+        for (hook_id, properties) in other.properties {
+            self.properties
+                .entry(hook_id)
+                .or_default()
+                .extend(properties);
+        }
+    }
+
+    /// All the `(hook_id, properties)` entries, in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[Box<dyn Property>])> {
+        self.properties
+            .iter()
+            .map(|(hook_id, properties)| (hook_id.as_str(), properties.as_slice()))
+    }
+
+    /// The registered hook ids, sorted
+    pub fn hook_ids(&self) -> Vec<&str> {
+        let mut hook_ids: Vec<&str> = self.properties.keys().map(String::as_str).collect();
+        hook_ids.sort();
+        hook_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::representations::instruction::Instruction;
+
+    #[derive(Clone)]
+    struct CountProp(usize);
+
+    impl Property for CountProp {
+        fn property_id(&self) -> String {
+            "Count".to_string()
+        }
+    }
+
+    impl fmt::Display for CountProp {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Count: {}", self.0)
+        }
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut manager = PropertyManager::from_entries(vec!["b".to_string(), "a".to_string()]);
+        manager.add_property_by_id("a".to_string(), Box::new(CountProp(1)));
+        manager.add_property_by_id("a".to_string(), Box::new(CountProp(2)));
+        assert_eq!(manager.hook_ids(), vec!["a", "b"]);
+
+        let mut entries: Vec<(&str, Vec<String>)> = manager
+            .iter()
+            .map(|(hook_id, properties)| {
+                (hook_id, properties.iter().map(|p| p.to_string()).collect())
+            })
+            .collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("a", vec!["Count: 1".to_string(), "Count: 2".to_string()]),
+                ("b", vec![])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_smart_pointer_hooks() {
+        let inst: Instruction = serde_yaml::from_str("Ra <= A[i]").unwrap();
+        let mut manager = PropertyManager::from_entries(vec![inst.property_hook_id()]);
+        manager.add_property_to_hook(Box::new(inst.clone()), Box::new(CountProp(1)));
+        manager.add_property_to_hook(Arc::new(inst.clone()), Box::new(CountProp(2)));
+        manager.add_property_to_hook(inst.clone(), Box::new(CountProp(3)));
+        assert_eq!(manager.get_properties_by_hook(&inst).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut manager = PropertyManager::from_entries(vec!["a".to_string()]);
+        manager.add_property_by_id("a".to_string(), Box::new(CountProp(1)));
+        let mut other = PropertyManager::from_entries(vec!["a".to_string(), "c".to_string()]);
+        other.add_property_by_id("a".to_string(), Box::new(CountProp(1)));
+        other.add_property_by_id("c".to_string(), Box::new(CountProp(3)));
+        manager.merge(other);
+
+        assert_eq!(manager.hook_ids(), vec!["a", "c"]);
+        let counts = |hook_id: &str| -> Vec<String> {
+            manager
+                .get_properties_by_id(hook_id.to_string())
+                .unwrap()
+                .iter()
+                .map(|p| p.to_string())
+                .collect()
+        };
+        assert_eq!(counts("a"), vec!["Count: 1", "Count: 1"]);
+        assert_eq!(counts("c"), vec!["Count: 3"]);
+    }
 }
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use super::arithmetic_intensity::ArithmeticIntensityPass;
+use super::bandwidth_demand::BandwidthDemandPass;
+use super::compute_count::ComputeCountPass;
+use super::data_reuse_analysis::DataReuseAnalysis;
+use super::feature::Feature;
+use super::free_dim_analysis::FreeDimAnalysis;
+use super::loop_dependence_analysis::LoopDependenceAnalysis;
+use super::mem_access_analysis::MemAccessAnalysis;
+use super::memory_footprint::MemoryFootprintPass;
+use super::operation_count::OperationCountPass;
+use super::parallelizability::ParallelizabilityPass;
+use super::passes::Pass;
+use super::stride_detection::StrideDetectionPass;
+use super::sync_barrier::SyncBarrierPass;
+use super::vectorization_analysis::VectorizationAnalysisPass;
+
+/// Builds a new instance of a pass
+pub type PassFactory = fn() -> Box<dyn Pass>;
+
+/// The registry of the built-in passes, extended by `PassRegistry::register`
+static REGISTRY: LazyLock<Mutex<PassRegistry>> =
+    LazyLock::new(|| Mutex::new(PassRegistry::builtin()));
+
+/// The producers of each feature among the passes which can be built without any input.
+/// The factories may use the global registry themselves, so they are never called while it is
+/// locked: copy them out with `factories` or `producer` and release the guard first.
+/// `ArchInfoBuilder` and `MappingInfoBuilder` need an architecture and a mapping, so the
+/// `ArchInfo` and `MappingInfo` features have no built-in producer.
+#[derive(Clone)]
+pub struct PassRegistry {
+    factories: Vec<PassFactory>,
+    producers: HashMap<Feature, Vec<PassFactory>>,
+}
+
+impl PassRegistry {
+    pub fn new() -> Self {
+        PassRegistry {
+            factories: Vec::new(),
+            producers: HashMap::new(),
+        }
+    }
+
+    /// A registry of all the built-in passes
+    pub fn builtin() -> Self {
+        let mut registry = PassRegistry::new();
+        let factories: [PassFactory; 13] = [
+            || Box::new(MemAccessAnalysis::default()),
+            || Box::new(FreeDimAnalysis::default()),
+            || Box::new(LoopDependenceAnalysis),
+            || Box::new(ParallelizabilityPass),
+            || Box::new(VectorizationAnalysisPass),
+            || Box::new(StrideDetectionPass),
+            || Box::new(OperationCountPass),
+            || Box::new(ComputeCountPass),
+            || Box::new(MemoryFootprintPass),
+            || Box::new(BandwidthDemandPass),
+            || Box::new(ArithmeticIntensityPass),
+            || Box::new(DataReuseAnalysis),
+            || Box::new(SyncBarrierPass),
+        ];
+        for factory in factories {
+            registry.add(factory);
+        }
+        registry
+    }
+
+    /// The global registry, with the built-in passes and the ones added by `register`.
+    /// No pass factory may be called while the guard is held.
+    pub fn global() -> MutexGuard<'static, PassRegistry> {
+        REGISTRY
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Add a custom pass to the global registry, e.g. from another crate.
+    /// The built-in producers of a feature are preferred over the custom ones.
+    pub fn register(factory: PassFactory) {
+        let produced = factory().produced_features();
+        PassRegistry::global().insert(factory, produced);
+    }
+
+    /// Register the pass built by `factory` as a producer of each of its produced features
+    pub fn add(&mut self, factory: PassFactory) {
+        let produced = factory().produced_features();
+        self.insert(factory, produced);
+    }
+
+    fn insert(&mut self, factory: PassFactory, produced: Vec<Feature>) {
+        self.factories.push(factory);
+        for feature in produced {
+            self.producers.entry(feature).or_default().push(factory);
+        }
+    }
+
+    /// The factories of the registered passes, in registration order
+    pub fn factories(&self) -> Vec<PassFactory> {
+        self.factories.clone()
+    }
+
+    /// A new instance of each registered pass, in registration order
+    pub fn passes(&self) -> Vec<Box<dyn Pass>> {
+        self.factories.iter().map(|factory| factory()).collect()
+    }
+
+    /// The first registered producer of `feature`
+    pub fn producer(&self, feature: &Feature) -> Option<PassFactory> {
+        self.producers
+            .get(feature)
+            .and_then(|factories| factories.first().copied())
+    }
+}
+
+impl Default for PassRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
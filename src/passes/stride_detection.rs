@@ -0,0 +1,97 @@
+use core::fmt;
+
+use crate::representations::instruction::{DataAccess, Instruction};
+
+use super::feature::Feature;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// Stride of a memory access along the innermost iterator, in elements
+#[derive(Clone)]
+pub struct StrideProp {
+    /// `None` if the stride is unknown
+    pub stride: Option<i32>,
+    pub iterator: String,
+}
+
+impl Property for StrideProp {
+    fn property_id(&self) -> String {
+        "StrideProp".to_string()
+    }
+}
+
+impl fmt::Display for StrideProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.stride {
+            Some(stride) => write!(f, "Stride along {}: {}", self.iterator, stride),
+            None => write!(f, "Stride along {}: Unknown", self.iterator),
+        }
+    }
+}
+
+/// `c` for an access whose last index is `c * iter + d` and whose other indices do not use
+/// `iter`, 0 if `iter` is not used at all.
+/// The stride is unknown if `iter` is used in another dimension (it depends on the shape of the
+/// array) or if the last index is not linear.
+pub fn access_stride(access: &DataAccess, iter: &str) -> Option<i32> {
+    let others = match access.addr.split_last() {
+        Some((_, others)) => others,
+        None => return Some(0),
+    };
+    if others.iter().any(|expr| expr.contains_var(iter)) {
+        return None;
+    }
+    access.stride_in_dim(iter)
+}
+
+pub struct StrideDetectionPass;
+
+impl PassRun for StrideDetectionPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let loop_nest = workspace.loop_nest.clone();
+        for (iters, inst) in loop_nest.scoped_instructions() {
+            // the innermost iterator enclosing the instruction
+            let innermost = match iters.last() {
+                Some(iter) => iter.iter_name.clone(),
+                None => continue,
+            };
+            if let Instruction::DataLoad(access)
+            | Instruction::DataStore(access)
+            | Instruction::Prefetch(access) = inst
+            {
+                let stride = access_stride(access, &innermost);
+                workspace.add_property(
+                    inst,
+                    Box::new(StrideProp {
+                        stride,
+                        iterator: innermost.clone(),
+                    }),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for StrideDetectionPass {
+    fn name(&self) -> &str {
+        "Stride Detection"
+    }
+
+    fn description(&self) -> &str {
+        "Computes the stride of each memory access along the innermost iterator"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::Stride]
+    }
+}
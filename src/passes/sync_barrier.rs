@@ -0,0 +1,106 @@
+use core::fmt;
+
+use crate::representations::instruction::Instruction;
+use crate::representations::loops::LoopNest;
+
+use super::feature::Feature;
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// A sync barrier placed where it cannot separate two pipeline stages
+#[derive(Clone)]
+pub struct SyncBarrierViolationProp {
+    pub label: String,
+    pub reason: String,
+}
+
+impl Property for SyncBarrierViolationProp {
+    fn property_id(&self) -> String {
+        "SyncBarrierViolationProp".to_string()
+    }
+}
+
+impl fmt::Display for SyncBarrierViolationProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Illegal sync barrier {}: {}", self.label, self.reason)
+    }
+}
+
+/// Checks that the sync barriers split the bodies in non-empty stages:
+/// a barrier cannot start a body nor directly follow another barrier,
+/// and the labels are unique in the loop nest.
+/// The illegal barriers are reported as `SyncBarrierViolationProp`.
+pub struct SyncBarrierPass;
+
+impl SyncBarrierPass {
+    pub fn violations(loop_nest: &LoopNest) -> Vec<SyncBarrierViolationProp> {
+        let mut labels = Vec::new();
+        let mut violations = Vec::new();
+        Self::check_body(&loop_nest.body, &mut labels, &mut violations);
+        violations
+    }
+
+    fn check_body<'a>(
+        body: &'a [Instruction],
+        labels: &mut Vec<&'a str>,
+        violations: &mut Vec<SyncBarrierViolationProp>,
+    ) {
+        for (idx, inst) in body.iter().enumerate() {
+            let label = match inst {
+                Instruction::SyncBarrier(label) => label,
+                Instruction::LoopBody(nest) => {
+                    Self::check_body(&nest.body, labels, violations);
+                    continue;
+                }
+                _ => continue,
+            };
+            let mut violation = |reason: &str| {
+                violations.push(SyncBarrierViolationProp {
+                    label: label.clone(),
+                    reason: reason.to_string(),
+                })
+            };
+            if idx == 0 {
+                violation("no instruction before it in its body");
+            } else if let Instruction::SyncBarrier(_) = body[idx - 1] {
+                violation("directly follows another sync barrier");
+            }
+            if labels.contains(&label.as_str()) {
+                violation("the label is used by another sync barrier");
+            }
+            labels.push(label);
+        }
+    }
+}
+
+impl PassRun for SyncBarrierPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        for violation in Self::violations(&workspace.loop_nest) {
+            workspace.add_global_property(Box::new(violation));
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for SyncBarrierPass {
+    fn name(&self) -> &str {
+        "Sync Barrier Check"
+    }
+
+    fn description(&self) -> &str {
+        "Checks that the sync barriers appear at legal positions in the body"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::SyncBarriers]
+    }
+}
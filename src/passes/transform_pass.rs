@@ -3,6 +3,7 @@ use crate::representations::affine_expr::Coeff;
 use crate::representations::instruction::*;
 use crate::representations::loops::*;
 use crate::representations::transforms::{Transform, Transforms};
+use std::collections::{HashMap, HashSet};
 
 pub trait Transforming {
     fn apply(&self, transform: &Transform) -> Self;
@@ -12,7 +13,7 @@ pub trait Transforming {
     {
         let mut new_self = self.clone();
         for transform in &transforms.transforms {
-            new_self = new_self.apply(&transform);
+            new_self = new_self.apply(transform);
         }
         new_self
     }
@@ -39,7 +40,13 @@ impl Transforming for Coeff {
                     self.clone()
                 }
             }
-            (Coeff::ConstVar(_), Transform::Reorder(_)) => self.clone(),
+            (Coeff::ConstVar(_), Transform::Interchange(_)) => self.clone(),
+            (Coeff::ConstVar(_), Transform::Unroll(_, _)) => self.clone(),
+            (Coeff::ConstVar(_), Transform::Skew(_, _, _)) => self.clone(),
+            (Coeff::ConstVar(_), Transform::Shift(_, _)) => self.clone(),
+            (Coeff::ConstVar(_), Transform::Scale(_, _)) => self.clone(),
+            (Coeff::ConstVar(_), Transform::Fusion(_, _)) => self.clone(),
+            (Coeff::ConstVar(_), Transform::Distribution(_, _)) => self.clone(),
 
             // Coeff::Mul
             (Coeff::Mul(lhs, rhs), _) => {
@@ -47,6 +54,16 @@ impl Transforming for Coeff {
                 let new_rhs = rhs.apply(transform);
                 Coeff::Mul(Box::new(new_lhs), Box::new(new_rhs))
             }
+
+            // Coeff::Add
+            (Coeff::Add(lhs, rhs), _) => {
+                let new_lhs = lhs.apply(transform);
+                let new_rhs = rhs.apply(transform);
+                Coeff::Add(Box::new(new_lhs), Box::new(new_rhs))
+            }
+
+            // Coeff::Neg
+            (Coeff::Neg(e), _) => Coeff::Neg(Box::new(e.apply(transform))),
         }
     }
 }
@@ -57,25 +74,72 @@ impl Transforming for AffineExpr {
             // AffineExpr::Var
             (AffineExpr::Var(var), Transform::Tiling((old, new, factor))) => {
                 if var == old {
-                    AffineExpr::Add(
+                    AffineExpr::linear_combination(vec![
+                        (*factor, AffineExpr::Var(new.clone())),
+                        (1, AffineExpr::Var(var.clone())),
+                    ])
+                } else {
+                    self.clone()
+                }
+            }
+            (AffineExpr::Var(var_name), Transform::Renaming((old_iter, new_iter))) => {
+                if var_name == old_iter {
+                    AffineExpr::Var(new_iter.clone())
+                } else {
+                    self.clone()
+                }
+            }
+            (AffineExpr::Var(_), Transform::Interchange(_)) => self.clone(),
+            // The copies of the body are created by LoopNest
+            (AffineExpr::Var(_), Transform::Unroll(_, _)) => self.clone(),
+            // i' = i + factor * j, so i is replaced by i' - factor * j
+            (AffineExpr::Var(var), Transform::Skew(outer, inner, factor)) => {
+                if var == outer {
+                    AffineExpr::Sub(
+                        Box::new(AffineExpr::Var(var.clone())),
                         Box::new(AffineExpr::Mul(
                             Coeff::Const(*factor),
-                            Box::new(AffineExpr::Var(new.clone())),
+                            Box::new(AffineExpr::Var(inner.clone())),
                         )),
+                    )
+                } else {
+                    self.clone()
+                }
+            }
+            // i' = i + offset, so i is replaced by i' - offset
+            (AffineExpr::Var(var), Transform::Shift(iter, offset)) => {
+                if var == iter && *offset != 0 {
+                    let var = Box::new(AffineExpr::Var(var.clone()));
+                    match offset.checked_neg() {
+                        Some(opposite) if opposite > 0 => {
+                            AffineExpr::Add(var, Box::new(AffineExpr::Const(opposite)))
+                        }
+                        _ => AffineExpr::Sub(var, Box::new(AffineExpr::Const(*offset))),
+                    }
+                } else {
+                    self.clone()
+                }
+            }
+            // i' = factor * i, so i is replaced by i' / factor
+            (AffineExpr::Var(var), Transform::Scale(iter, factor)) => {
+                if var == iter {
+                    AffineExpr::Div(
                         Box::new(AffineExpr::Var(var.clone())),
+                        Coeff::Const(*factor),
                     )
                 } else {
                     self.clone()
                 }
             }
-            (AffineExpr::Var(var_name), Transform::Renaming((old_iter, new_iter))) => {
-                if var_name == old_iter {
-                    AffineExpr::Var(new_iter.clone())
+            // The instructions of the second loop now use the iterator of the first one
+            (AffineExpr::Var(var), Transform::Fusion(first, second)) => {
+                if var == second {
+                    AffineExpr::Var(first.clone())
                 } else {
                     self.clone()
                 }
             }
-            (AffineExpr::Var(_), Transform::Reorder(_)) => self.clone(),
+            (AffineExpr::Var(_), Transform::Distribution(_, _)) => self.clone(),
 
             // AffineExpr::Const
             (AffineExpr::Const(_), _) => self.clone(),
@@ -108,12 +172,37 @@ impl Transforming for AffineExpr {
                 AffineExpr::Div(Box::new(new_expr), new_divisor)
             }
 
+            // AffineExpr::CeilDiv
+            (AffineExpr::CeilDiv(expr, divisor), _) => {
+                AffineExpr::CeilDiv(Box::new(expr.apply(transform)), divisor.apply(transform))
+            }
+
             // AffineExpr::Mod
             (AffineExpr::Mod(expr, modulus), _) => {
                 let new_expr = expr.apply(transform);
                 let new_modulus = modulus.apply(transform);
                 AffineExpr::Mod(Box::new(new_expr), new_modulus)
             }
+
+            // AffineExpr::Neg
+            (AffineExpr::Neg(expr), _) => AffineExpr::Neg(Box::new(expr.apply(transform))),
+
+            // AffineExpr::Max
+            (AffineExpr::Max(lhs, rhs), _) => AffineExpr::Max(
+                Box::new(lhs.apply(transform)),
+                Box::new(rhs.apply(transform)),
+            ),
+
+            // AffineExpr::Min
+            (AffineExpr::Min(lhs, rhs), _) => AffineExpr::Min(
+                Box::new(lhs.apply(transform)),
+                Box::new(rhs.apply(transform)),
+            ),
+
+            // AffineExpr::Pow
+            (AffineExpr::Pow(expr, exponent), _) => {
+                AffineExpr::Pow(Box::new(expr.apply(transform)), *exponent)
+            }
         }
     }
 }
@@ -173,10 +262,18 @@ impl Transforming for Instruction {
                 let new_data_access = data_access.apply(transform);
                 Instruction::DataStore(new_data_access)
             }
+            Instruction::Prefetch(data_access) => {
+                Instruction::Prefetch(data_access.apply(transform))
+            }
+            Instruction::SyncBarrier(_) => self.clone(),
             Instruction::Compute(compute) => {
                 let new_compute = compute.apply(transform);
                 Instruction::Compute(new_compute)
             }
+            Instruction::LoopBody(nest) => Instruction::LoopBody(Box::new(
+                nest.apply_in_scope(transform)
+                    .unwrap_or_else(|error| panic!("{}", error)),
+            )),
         }
     }
 }
@@ -188,16 +285,22 @@ impl Transforming for LoopIter {
             // The extra loop (with the new iterator) is created by LoopNest
             Transform::Tiling((old, _, factor)) => {
                 if self.iter_name == *old {
-                    if self.bounds.1 % factor != 0 {
-                        panic!(
-                        "The upper bound: {} of the iterator {} is not divisible by the factor: {}",
-                        self.bounds.1, self.iter_name, factor
-                    );
-                    }
+                    let upper = match self.bounds.1.exact_div(*factor) {
+                        Some(upper) => upper,
+                        None if self.bounds.1.is_constant() => panic!(
+                            "The upper bound: {} of the iterator {} is not divisible by the factor: {}",
+                            self.bounds.1, self.iter_name, factor
+                        ),
+                        // the tiles of a symbolic bound are rounded up, the last one may be partial
+                        None => AffineExpr::CeilDiv(
+                            Box::new(self.bounds.1.clone()),
+                            Coeff::Const(*factor),
+                        ),
+                    };
                     LoopIter {
                         iter_name: old.clone(),
-                        bounds: (self.bounds.0, self.bounds.1 / factor),
-                        step: self.step,
+                        bounds: (self.bounds.0.clone(), upper),
+                        step: self.step.clone(),
                     }
                 } else {
                     self.clone()
@@ -207,54 +310,191 @@ impl Transforming for LoopIter {
                 if self.iter_name == *old_iter {
                     LoopIter {
                         iter_name: new_iter.clone(),
-                        bounds: self.bounds,
-                        step: self.step,
+                        bounds: self.bounds.clone(),
+                        step: self.step.clone(),
+                    }
+                } else {
+                    self.clone()
+                }
+            }
+            Transform::Interchange(_) => self.clone(),
+            // The copies of the body and the residual loop are created by LoopNest
+            Transform::Unroll(_, _) => self.clone(),
+            // The new bounds depend on the other iterator, they are computed by LoopNest
+            Transform::Skew(_, _, _) => self.clone(),
+            Transform::Shift(iter, offset) => {
+                if self.iter_name == *iter {
+                    LoopIter {
+                        iter_name: self.iter_name.clone(),
+                        bounds: (self.bounds.0.offset(*offset), self.bounds.1.offset(*offset)),
+                        step: self.step.clone(),
+                    }
+                } else {
+                    self.clone()
+                }
+            }
+            // (lo..hi).step(s) becomes (factor * lo..factor * hi).step(factor * s)
+            Transform::Scale(iter, factor) => {
+                if self.iter_name == *iter {
+                    if *factor <= 0 {
+                        panic!(
+                            "The scaling factor: {} of the iterator {} should be positive",
+                            factor, self.iter_name
+                        );
+                    }
+                    LoopIter {
+                        iter_name: self.iter_name.clone(),
+                        bounds: (self.bounds.0.scale(*factor), self.bounds.1.scale(*factor)),
+                        step: Coeff::Const(*factor).mul(&self.step),
                     }
                 } else {
                     self.clone()
                 }
             }
-            Transform::Reorder(_) => self.clone(),
+            // The second loop is removed by LoopNest
+            Transform::Fusion(_, _) => self.clone(),
+            Transform::Distribution(_, _) => self.clone(),
         }
     }
 }
 
+/// Panics if a transform cannot be applied, `LoopNest::try_apply` and `LoopNest::try_apply_all`
+/// report the error instead
 impl Transforming for LoopNest {
+    fn apply_all(&self, transforms: &Transforms) -> Self {
+        self.try_apply_all(transforms)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
     fn apply(&self, transform: &Transform) -> Self {
+        self.try_apply(transform)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl LoopNest {
+    /// Apply the transforms in order, all the inapplicable transforms of the sequence are
+    /// reported before applying any of them
+    pub fn try_apply_all(&self, transforms: &Transforms) -> Result<LoopNest, String> {
+        transforms
+            .validate_sequence(self)
+            .map_err(|errors| format!("The transforms cannot be applied: {}", errors.join(", ")))?;
+        let mut loop_nest = self.clone();
+        for transform in &transforms.transforms {
+            loop_nest = loop_nest.try_apply(transform)?;
+        }
+        Ok(loop_nest)
+    }
+
+    /// Apply the transform, or return why it cannot be applied.
+    /// The resulting loop nest is validated, see `LoopNest::validate`.
+    pub fn try_apply(&self, transform: &Transform) -> Result<LoopNest, String> {
+        Transforms {
+            transforms: vec![transform.clone()],
+        }
+        .validate_sequence(self)
+        .map_err(|errors| errors.join(", "))?;
+        let loop_nest = self.apply_in_scope(transform)?;
+        // catch transforms leaving the loop nest in an invalid state
+        loop_nest.validate().map_err(|errors| {
+            format!(
+                "The loop nest is invalid after applying {}: {}",
+                transform,
+                errors.join(", ")
+            )
+        })?;
+        Ok(loop_nest)
+    }
+
+    /// Apply the transform to the instructions of the body, the nested loop nests apply it
+    /// in their own scope
+    fn apply_to_body(&self, transform: &Transform) -> Result<Vec<Instruction>, String> {
+        self.body
+            .iter()
+            .map(|instr| match instr {
+                Instruction::LoopBody(nest) => Ok(Instruction::LoopBody(Box::new(
+                    nest.apply_in_scope(transform)?,
+                ))),
+                _ => Ok(instr.apply(transform)),
+            })
+            .collect()
+    }
+
+    /// Apply the transform to this loop nest if it owns the iterators of the transform,
+    /// otherwise only to the body, where the nested loop nests may own them.
+    /// Nested loop nests are not valid on their own, so the result is not validated.
+    fn apply_in_scope(&self, transform: &Transform) -> Result<LoopNest, String> {
         match transform {
+            Transform::Fusion(first, second) => {
+                return self.fuse(first, second)?.ok_or_else(|| {
+                    format!(
+                        "Cannot fuse the loops of {} and {}, they are not consecutive loops of the same body",
+                        first, second
+                    )
+                });
+            }
+            Transform::Distribution(name, index) => {
+                return self.distribute(name, *index)?.ok_or_else(|| {
+                    format!(
+                        "The loop of {} to distribute was not found in the loop nest",
+                        name
+                    )
+                });
+            }
+            _ => {}
+        }
+        let names = transform.iter_names();
+        let owned = names
+            .iter()
+            .filter(|name| self.get_iter(name).is_some())
+            .count();
+        if owned == 0 {
+            return Ok(LoopNest {
+                iters: self.iters.clone(),
+                body: self.apply_to_body(transform)?,
+            });
+        }
+        if owned < names.len() {
+            return Err(format!(
+                "Cannot apply {}, the iterators {:?} are not in the same loop nest",
+                transform, names
+            ));
+        }
+        let loop_nest = match transform {
             Transform::Tiling((old, new, factor)) => {
+                let iter = self.get_iter(old).ok_or_else(|| {
+                    format!(
+                        "The iterator {} to tile was not found in the loop nest, current iterators: {:?}",
+                        old,
+                        self.iter_names()
+                    )
+                })?;
+                if iter.bounds.1.is_constant() && iter.bounds.1.exact_div(*factor).is_none() {
+                    return Err(format!(
+                        "The upper bound: {} of the iterator {} is not divisible by the factor: {}",
+                        iter.bounds.1, old, factor
+                    ));
+                }
                 let mut new_iters: Vec<LoopIter> = self
                     .iters
                     .iter()
                     .map(|iter| iter.apply(transform))
                     .collect();
-                let new_body = self
-                    .body
-                    .iter()
-                    .map(|instr| instr.apply(transform))
-                    .collect();
+                let new_body = self.apply_to_body(transform)?;
                 // Add a new loop with the new iterator
                 // The step is the same as the old iterator
                 // The upper bound is the factor
                 let new_iter = LoopIter {
                     iter_name: new.clone(),
-                    bounds: (0, *factor),
-                    step: self
-                        .iters
-                        .iter()
-                        .find(|iter| iter.iter_name == *old)
-                        .unwrap()
-                        .step,
+                    bounds: (AffineExpr::Const(0), AffineExpr::Const(*factor)),
+                    step: iter.step.clone(),
                 };
                 // insert the new iterator just after the old iterator
-                let idx = new_iters.iter().position(|iter| iter.iter_name == *old);
-                assert!(
-                    idx.is_some(),
-                    "The iterator {} to tile was not found in the loop nest, current iterators: {:?}",
-                    old,
-                    new_iters
-                );
-                let idx = idx.unwrap();
+                let idx = self
+                    .iters
+                    .iter()
+                    .position(|iter| iter.iter_name == *old)
+                    .unwrap();
                 new_iters.insert(idx + 1, new_iter);
 
                 LoopNest {
@@ -263,36 +503,32 @@ impl Transforming for LoopNest {
                 }
             }
 
-            Transform::Reorder((iter1, iter2)) => {
+            Transform::Interchange((iter1, iter2)) => {
                 let mut new_iters: Vec<LoopIter> = self
                     .iters
                     .iter()
                     .map(|iter| iter.apply(transform))
                     .collect();
-                let new_body = self
-                    .body
-                    .iter()
-                    .map(|instr| instr.apply(transform))
-                    .collect();
-                // Reorder the iterators
+                let new_body = self.apply_to_body(transform)?;
+                // Interchange the iterators
                 let idx1 = new_iters
                     .iter()
                     .position(|iter| iter.iter_name == *iter1)
-                    .unwrap_or_else(|| {
-                        panic!(
-                            "The first iterator {} to reorder was not found in the loop nest",
+                    .ok_or_else(|| {
+                        format!(
+                            "The first iterator {} to interchange was not found in the loop nest",
                             iter1
                         )
-                    });
+                    })?;
                 let idx2 = new_iters
                     .iter()
                     .position(|iter| iter.iter_name == *iter2)
-                    .unwrap_or_else(|| {
-                        panic!(
-                            "The second iterator {} to reorder was not found in the loop nest",
+                    .ok_or_else(|| {
+                        format!(
+                            "The second iterator {} to interchange was not found in the loop nest",
                             iter2
                         )
-                    });
+                    })?;
                 new_iters.swap(idx1, idx2);
 
                 LoopNest {
@@ -300,23 +536,387 @@ impl Transforming for LoopNest {
                     body: new_body,
                 }
             }
-            // Renaming
-            Transform::Renaming(_) => {
+            Transform::Unroll(name, factor) => {
+                let pos = self
+                    .iters
+                    .iter()
+                    .position(|iter| iter.iter_name == *name)
+                    .unwrap();
+                let iter = &self.iters[pos];
+                let trip_count = iter.trip_count(&HashMap::new()).ok_or_else(|| {
+                    format!(
+                        "The iterator {} to unroll must have concrete bounds and step: {}",
+                        name, iter
+                    )
+                })?;
+                if *factor == 0 || i64::from(*factor) > trip_count {
+                    return Err(format!(
+                        "The unrolling factor {} must be between 1 and the trip count {} of the iterator {}",
+                        factor, trip_count, name
+                    ));
+                }
+                if pos == 0 {
+                    return Err(format!(
+                        "Cannot unroll the outermost loop of {}, the loop nest would have no iterator",
+                        name
+                    ));
+                }
+                let (lower, upper) = iter.concrete_bounds(&HashMap::new()).unwrap();
+                let step = iter.concrete_step(&HashMap::new()).unwrap();
+                // The loops inside the unrolled loop are copied with the body
+                let inner = Instruction::LoopBody(Box::new(LoopNest {
+                    iters: self.iters[pos + 1..].to_vec(),
+                    body: self.body.clone(),
+                }));
+                // The k-th copy executes the iteration `lower + k * step` of the unrolled loop
+                let mut new_body = Vec::new();
+                let mut next = lower;
+                for _ in 0..*factor {
+                    let value = AffineExpr::Const(next);
+                    match inner.substitute(name, &value).normalize() {
+                        Instruction::LoopBody(copy) if copy.iters.is_empty() => {
+                            new_body.extend(copy.body)
+                        }
+                        copy => new_body.push(copy),
+                    }
+                    next += step;
+                }
+                // The residual loop executes the remaining iterations
+                if next < upper {
+                    let mut residual_iters = self.iters[pos..].to_vec();
+                    residual_iters[0].bounds.0 = AffineExpr::Const(next);
+                    new_body.push(Instruction::LoopBody(Box::new(LoopNest {
+                        iters: residual_iters,
+                        body: self.body.clone(),
+                    })));
+                }
+                LoopNest {
+                    iters: self.iters[..pos].to_vec(),
+                    body: new_body,
+                }
+            }
+            Transform::Skew(outer, inner, factor) => {
+                if outer == inner {
+                    return Err(format!("Cannot skew the iterator {} by itself", outer));
+                }
+                if *factor == 0 {
+                    return Ok(self.clone());
+                }
+                let position = |name: &String| {
+                    self.iters
+                        .iter()
+                        .position(|iter| iter.iter_name == *name)
+                        .unwrap()
+                };
+                let (outer_pos, inner_pos) = (position(outer), position(inner));
+                let outer_iter = &self.iters[outer_pos];
+                let inner_iter = &self.iters[inner_pos];
+                let (lower, upper) = (&outer_iter.bounds.0, &outer_iter.bounds.1);
+                let var = |name: &String| Box::new(AffineExpr::Var(name.clone()));
+                let mut new_iters = self.iters.clone();
+                if inner_pos < outer_pos {
+                    // The loop of inner encloses the loop of outer, which is shifted by
+                    // factor * inner: (lower + factor * inner..upper + factor * inner)
+                    let shift = AffineExpr::Mul(Coeff::Const(*factor), var(inner));
+                    let shifted = |bound: &AffineExpr| {
+                        AffineExpr::Add(Box::new(bound.clone()), Box::new(shift.clone())).simplify()
+                    };
+                    new_iters[outer_pos].bounds = (shifted(lower), shifted(upper));
+                } else {
+                    // The loop of outer runs over the bounding box of outer + factor * inner, and
+                    // the bounds of inner are restricted to lower <= outer - factor * inner < upper
+                    let unit_step = |iter: &LoopIter| iter.step == Coeff::Const(1);
+                    if !unit_step(outer_iter) || !unit_step(inner_iter) {
+                        return Err(format!(
+                            "Cannot skew {} by the enclosed iterator {}, both iterators must have a unit step",
+                            outer, inner
+                        ));
+                    }
+                    if let Some(iter) = self.iters[outer_pos + 1..=inner_pos].iter().find(|iter| {
+                        iter.bounds.0.contains_var(outer) || iter.bounds.1.contains_var(outer)
+                    }) {
+                        return Err(format!(
+                            "Cannot skew {} by the enclosed iterator {}, the bounds of {} depend on {}",
+                            outer, inner, iter.iter_name, outer
+                        ));
+                    }
+                    let (inner_lower, inner_upper) =
+                        inner_iter.concrete_bounds(&HashMap::new()).ok_or_else(|| {
+                            format!(
+                                "The iterator {} to skew by must have concrete bounds: {}",
+                                inner, inner_iter
+                            )
+                        })?;
+                    if inner_lower >= inner_upper {
+                        return Ok(self.clone());
+                    }
+                    let shift_first = factor * inner_lower;
+                    let shift_last = factor * (inner_upper - 1);
+                    new_iters[outer_pos].bounds = (
+                        lower.offset(shift_first.min(shift_last)),
+                        upper.offset(shift_first.max(shift_last)),
+                    );
+                    // factor > 0: ceildiv(outer - (upper - 1), factor) <= inner
+                    //             inner <= (outer - lower) / factor
+                    // factor < 0: ceildiv(lower - outer, -factor) <= inner
+                    //             inner <= (upper - 1 - outer) / -factor
+                    let (first, last) = if *factor > 0 {
+                        (
+                            AffineExpr::Sub(var(outer), Box::new(upper.offset(-1))),
+                            AffineExpr::Sub(var(outer), Box::new(lower.clone())),
+                        )
+                    } else {
+                        (
+                            AffineExpr::Sub(Box::new(lower.clone()), var(outer)),
+                            AffineExpr::Sub(Box::new(upper.offset(-1)), var(outer)),
+                        )
+                    };
+                    let divisor = Coeff::Const(factor.abs());
+                    new_iters[inner_pos].bounds = (
+                        AffineExpr::Max(
+                            Box::new(inner_iter.bounds.0.clone()),
+                            Box::new(AffineExpr::CeilDiv(Box::new(first), divisor.clone())),
+                        )
+                        .simplify(),
+                        AffineExpr::Min(
+                            Box::new(inner_iter.bounds.1.clone()),
+                            Box::new(AffineExpr::Div(Box::new(last), divisor).offset(1)),
+                        )
+                        .simplify(),
+                    );
+                }
+                // outer is replaced by outer - factor * inner in the bounds of the enclosed loops
+                for iter in &mut new_iters[outer_pos.max(inner_pos) + 1..] {
+                    iter.bounds = (
+                        iter.bounds.0.apply(transform),
+                        iter.bounds.1.apply(transform),
+                    );
+                }
+                let new_body = self.apply_to_body(transform)?;
+                LoopNest {
+                    iters: new_iters,
+                    body: new_body,
+                }
+            }
+            Transform::Fusion(_, _) | Transform::Distribution(_, _) => {
+                unreachable!("fusion and distribution are applied by LoopNest::fuse and LoopNest::distribute")
+            }
+            // Renaming, shifting and scaling only change the iterators and the body in place
+            Transform::Renaming(_) | Transform::Shift(_, _) | Transform::Scale(_, _) => {
                 let new_iters = self
                     .iters
                     .iter()
                     .map(|iter| iter.apply(transform))
                     .collect();
-                let new_body = self
-                    .body
-                    .iter()
-                    .map(|instr| instr.apply(transform))
-                    .collect();
+                let new_body = self.apply_to_body(transform)?;
                 LoopNest {
                     iters: new_iters,
                     body: new_body,
                 }
             }
+        };
+        Ok(loop_nest)
+    }
+
+    /// Fuse the consecutive loops of `first` and `second`, the outermost loops of two consecutive
+    /// loop nests of the body, searching the nested loop nests if they are not in this body.
+    /// The iterator `second` is replaced by `first`.
+    /// Returns `None` if the two loops were not found.
+    fn fuse(&self, first: &str, second: &str) -> Result<Option<LoopNest>, String> {
+        let is_loop_of = |inst: &Instruction, name: &str| match inst {
+            Instruction::LoopBody(nest) => nest
+                .iters
+                .first()
+                .is_some_and(|iter| iter.iter_name == name),
+            _ => false,
+        };
+        let position = self
+            .body
+            .windows(2)
+            .position(|pair| is_loop_of(&pair[0], first) && is_loop_of(&pair[1], second));
+        let idx = match position {
+            Some(idx) => idx,
+            None => {
+                let mut fused = false;
+                let mut body = Vec::new();
+                for inst in &self.body {
+                    match inst {
+                        Instruction::LoopBody(nest) if !fused => match nest.fuse(first, second)? {
+                            Some(nest) => {
+                                fused = true;
+                                body.push(Instruction::LoopBody(Box::new(nest)));
+                            }
+                            None => body.push(inst.clone()),
+                        },
+                        _ => body.push(inst.clone()),
+                    }
+                }
+                return Ok(fused.then(|| LoopNest {
+                    iters: self.iters.clone(),
+                    body,
+                }));
+            }
+        };
+        let (first_nest, second_nest) = match (&self.body[idx], &self.body[idx + 1]) {
+            (Instruction::LoopBody(first_nest), Instruction::LoopBody(second_nest)) => {
+                (first_nest, second_nest)
+            }
+            _ => unreachable!(),
+        };
+        let (first_iter, second_iter) = (&first_nest.iters[0], &second_nest.iters[0]);
+        if first_iter.bounds != second_iter.bounds || first_iter.step != second_iter.step {
+            return Err(format!(
+                "Cannot fuse the loops of {} and {}, their iterators have different bounds or steps: {} and {}",
+                first, second, first_iter, second_iter
+            ));
+        }
+        // The instructions of the second loop now use the iterator of the first one
+        let second_nest = if first == second {
+            second_nest.as_ref().clone()
+        } else {
+            second_nest.apply_in_scope(&Transform::Renaming((
+                second.to_string(),
+                first.to_string(),
+            )))?
+        };
+        // The inner loops of each nest stay separated in the fused loop
+        let inner_body = |nest: &LoopNest| {
+            if nest.iters.len() == 1 {
+                nest.body.clone()
+            } else {
+                vec![Instruction::LoopBody(Box::new(LoopNest {
+                    iters: nest.iters[1..].to_vec(),
+                    body: nest.body.clone(),
+                }))]
+            }
+        };
+        let fused = LoopNest {
+            iters: vec![first_iter.clone()],
+            body: [inner_body(first_nest), inner_body(&second_nest)].concat(),
+        };
+        let mut body = self.body[..idx].to_vec();
+        body.push(Instruction::LoopBody(Box::new(fused)));
+        body.extend_from_slice(&self.body[idx + 2..]);
+        Ok(Some(
+            LoopNest {
+                iters: self.iters.clone(),
+                body,
+            }
+            .flatten(),
+        ))
+    }
+
+    /// Split the body of the loop of `name` at the instruction `index` into two consecutive loops,
+    /// the loops inside the loop of `name` are duplicated with the same iterators.
+    /// Returns `None` if the loop was not found, and an error if a register written by one
+    /// part is read by the other.
+    fn distribute(&self, name: &str, index: usize) -> Result<Option<LoopNest>, String> {
+        let split = |iters: &[LoopIter], body: &[Instruction]| {
+            if index == 0 || index >= body.len() {
+                return Err(format!(
+                    "Cannot distribute the loop of {} at {}, the index must split the {} instructions of the body in two",
+                    name,
+                    index,
+                    body.len()
+                ));
+            }
+            let (first_reads, first_writes) = register_flow(&body[..index]);
+            let (second_reads, second_writes) = register_flow(&body[index..]);
+            if let Some(reg) = first_writes
+                .intersection(&second_reads)
+                .chain(second_writes.intersection(&first_reads))
+                .min()
+            {
+                return Err(format!(
+                    "Cannot distribute the loop of {} at {}, the register {} is written on one side of the split and read on the other",
+                    name, index, reg
+                ));
+            }
+            Ok([&body[..index], &body[index..]].map(|part| {
+                Instruction::LoopBody(Box::new(LoopNest {
+                    iters: iters.to_vec(),
+                    body: part.to_vec(),
+                }))
+            }))
+        };
+        match self.iters.iter().position(|iter| iter.iter_name == name) {
+            // The enclosing loop nest replaces this loop nest by the two loops
+            Some(0) => Err(format!(
+                "Cannot distribute the outermost loop of {}, the loop nest would have no iterator",
+                name
+            )),
+            Some(pos) => Ok(Some(LoopNest {
+                iters: self.iters[..pos].to_vec(),
+                body: split(&self.iters[pos..], &self.body)?.to_vec(),
+            })),
+            None => {
+                let mut distributed = false;
+                let mut body = Vec::new();
+                for inst in &self.body {
+                    match inst {
+                        Instruction::LoopBody(nest) if !distributed => {
+                            if nest
+                                .iters
+                                .first()
+                                .is_some_and(|iter| iter.iter_name == name)
+                            {
+                                body.extend(split(&nest.iters, &nest.body)?);
+                                distributed = true;
+                            } else if let Some(nest) = nest.distribute(name, index)? {
+                                body.push(Instruction::LoopBody(Box::new(nest)));
+                                distributed = true;
+                            } else {
+                                body.push(inst.clone());
+                            }
+                        }
+                        _ => body.push(inst.clone()),
+                    }
+                }
+                Ok(distributed.then(|| LoopNest {
+                    iters: self.iters.clone(),
+                    body,
+                }))
+            }
+        }
+    }
+
+    /// Merge a body made of a single loop nest into this loop nest, making it perfectly nested
+    fn flatten(self) -> LoopNest {
+        match self.body.as_slice() {
+            [Instruction::LoopBody(nest)] => LoopNest {
+                iters: [self.iters.clone(), nest.iters.clone()].concat(),
+                body: nest.body.clone(),
+            },
+            _ => self,
+        }
+    }
+}
+
+/// `(reads, writes)`: the registers read before being written in the instructions,
+/// and the registers written by them, including the nested loop nests
+fn register_flow(body: &[Instruction]) -> (HashSet<&String>, HashSet<&String>) {
+    let (mut reads, mut writes) = (HashSet::new(), HashSet::new());
+    for inst in body {
+        match inst {
+            Instruction::LoopBody(nest) => {
+                let (nested_reads, nested_writes) = register_flow(&nest.body);
+                reads.extend(
+                    nested_reads
+                        .difference(&writes)
+                        .copied()
+                        .collect::<Vec<_>>(),
+                );
+                writes.extend(nested_writes);
+            }
+            _ => {
+                for reg in inst.read_registers() {
+                    if !writes.contains(reg) {
+                        reads.insert(reg);
+                    }
+                }
+                writes.extend(inst.written_register());
+            }
         }
     }
+    (reads, writes)
 }
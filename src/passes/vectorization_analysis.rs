@@ -0,0 +1,226 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+
+use crate::representations::instruction::{DataAccess, Instruction};
+use crate::representations::loops::LoopIter;
+
+use super::feature::Feature;
+use super::loop_dependence_analysis::{DependenceKind, DependenceProp};
+use super::passes::{PassInfo, PassRun};
+use super::property::Property;
+use super::workspace::Workspace;
+
+/// Widest vector suggested by the `VectorizationAnalysisPass`
+pub const MAX_VECTOR_WIDTH: u32 = 16;
+
+#[derive(Clone)]
+pub struct VectorizationProp {
+    pub vectorizable: bool,
+    pub blocking_reason: Option<String>,
+    pub suggested_width: Option<u32>,
+}
+
+impl Property for VectorizationProp {
+    fn property_id(&self) -> String {
+        "VectorizationProp".to_string()
+    }
+}
+
+impl fmt::Display for VectorizationProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.blocking_reason, self.suggested_width) {
+            (Some(reason), _) => write!(f, "Not vectorizable: {}", reason),
+            (None, Some(width)) => write!(f, "Vectorizable, width {}", width),
+            (None, None) => write!(f, "Vectorizable"),
+        }
+    }
+}
+
+/// Whether a compute instruction operates on SIMD vector registers
+#[derive(Clone)]
+pub struct VectorOperandProp {
+    /// Widest vector register of the instruction, `None` if all its operands are scalars
+    pub vector_width: Option<u32>,
+}
+
+impl Property for VectorOperandProp {
+    fn property_id(&self) -> String {
+        "VectorOperandProp".to_string()
+    }
+}
+
+impl fmt::Display for VectorOperandProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.vector_width {
+            Some(width) => write!(f, "Vector operands, width {}", width),
+            None => write!(f, "Scalar operands"),
+        }
+    }
+}
+
+/// Whether consecutive iterations of `iter` access consecutive elements or the same element
+fn has_unit_stride(access: &DataAccess, iter: &LoopIter) -> bool {
+    let last_dim = access.addr.len().saturating_sub(1);
+    access.addr.iter().enumerate().all(|(dim, expr)| {
+        if !expr.contains_var(&iter.iter_name) {
+            return true;
+        }
+        match expr.to_linear_form() {
+            // a symbolic step is never a unit stride
+            Some((coeffs, _)) => match iter.concrete_step(&HashMap::new()) {
+                Some(step) => {
+                    let stride = coeffs.get(&iter.iter_name).copied().unwrap_or(0) * step;
+                    stride == 0 || (dim == last_dim && stride == 1)
+                }
+                None => false,
+            },
+            None => false,
+        }
+    })
+}
+
+/// Registers holding data loaded in the same iteration, directly or through computations
+fn loaded_registers(body: &[Instruction]) -> HashSet<String> {
+    let mut loaded = HashSet::new();
+    for inst in body {
+        match inst {
+            Instruction::DataLoad(access) => {
+                loaded.insert(access.reg.clone());
+            }
+            Instruction::Compute(compute) => {
+                if compute
+                    .src
+                    .iter()
+                    .any(|src| src.register().is_some_and(|reg| loaded.contains(reg)))
+                {
+                    loaded.extend(compute.dst.register().cloned());
+                }
+            }
+            Instruction::DataStore(_)
+            | Instruction::Prefetch(_)
+            | Instruction::SyncBarrier(_)
+            | Instruction::LoopBody(_) => {}
+        }
+    }
+    loaded
+}
+
+fn condition(inst: &Instruction) -> Option<&String> {
+    match inst {
+        Instruction::DataLoad(access)
+        | Instruction::DataStore(access)
+        | Instruction::Prefetch(access) => access.cond.as_ref(),
+        Instruction::Compute(compute) => compute.cond.as_ref(),
+        Instruction::SyncBarrier(_) | Instruction::LoopBody(_) => None,
+    }
+}
+
+/// Checks whether the innermost iterator can be vectorized: unit stride accesses, no dependence
+/// carried by the iterator and no instruction predicated on data loaded in the same iteration.
+/// Each compute instruction also gets a `VectorOperandProp`.
+pub struct VectorizationAnalysisPass;
+
+impl VectorizationAnalysisPass {
+    fn blocking_reason(workspace: &Workspace, innermost: &LoopIter) -> Option<String> {
+        let body = &workspace.loop_nest.body;
+        // the innermost iterator does not enclose the whole body
+        if body
+            .iter()
+            .any(|inst| matches!(inst, Instruction::LoopBody(_)))
+        {
+            return Some("the loop nest is not perfectly nested".to_string());
+        }
+        if let Some(access) = body.iter().find_map(|inst| match inst {
+            Instruction::DataLoad(access) | Instruction::DataStore(access)
+                if !has_unit_stride(access, innermost) =>
+            {
+                Some(access)
+            }
+            _ => None,
+        }) {
+            return Some(format!(
+                "the access to {} does not have a unit stride along {}",
+                access.array_name, innermost.iter_name
+            ));
+        }
+        if let Some(dependence) = body
+            .iter()
+            .flat_map(|inst| workspace.get_typed_property::<DependenceProp>(inst))
+            .find(|dependence| {
+                dependence.kind != DependenceKind::None
+                    && dependence.carriers.contains(&innermost.iter_name)
+            })
+        {
+            return Some(format!(
+                "{} is carried by {}",
+                dependence, innermost.iter_name
+            ));
+        }
+        let loaded = loaded_registers(body);
+        if let Some(inst) = body
+            .iter()
+            .find(|inst| condition(inst).is_some_and(|cond| loaded.contains(cond)))
+        {
+            return Some(format!(
+                "the instruction {} depends on a condition loaded in the same iteration",
+                inst
+            ));
+        }
+        None
+    }
+}
+
+impl PassRun for VectorizationAnalysisPass {
+    fn run(&self, workspace: &mut Workspace) -> Result<(), &'static str> {
+        let innermost = workspace
+            .loop_nest
+            .iters
+            .last()
+            .ok_or("The loop nest has no iterator")?
+            .clone();
+        let blocking_reason = VectorizationAnalysisPass::blocking_reason(workspace, &innermost);
+        // the widest power of two dividing the trip count
+        let suggested_width = match (&blocking_reason, innermost.trip_count(&HashMap::new())) {
+            (None, Some(trip_count)) => (1..=MAX_VECTOR_WIDTH.ilog2())
+                .rev()
+                .map(|exp| 1u32 << exp)
+                .find(|width| trip_count % *width as i64 == 0),
+            _ => None,
+        };
+        workspace.add_global_property(Box::new(VectorizationProp {
+            vectorizable: blocking_reason.is_none(),
+            blocking_reason,
+            suggested_width,
+        }));
+        let loop_nest = workspace.loop_nest.clone();
+        for inst in loop_nest.all_instructions() {
+            if let Instruction::Compute(compute) = inst {
+                let vector_width = compute.vector_width();
+                workspace.add_property(inst, Box::new(VectorOperandProp { vector_width }));
+            }
+        }
+        Ok(())
+    }
+
+    fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl PassInfo for VectorizationAnalysisPass {
+    fn name(&self) -> &str {
+        "Vectorization Analysis"
+    }
+
+    fn description(&self) -> &str {
+        "Checks whether the innermost loop can be vectorized"
+    }
+
+    fn required_features(&self) -> Vec<Feature> {
+        vec![Feature::DependenceInfo]
+    }
+
+    fn produced_features(&self) -> Vec<Feature> {
+        vec![Feature::Vectorization]
+    }
+}
@@ -1,10 +1,27 @@
+use std::any::Any;
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::path::Path;
 
 use crate::representations::arch::Arch;
-use crate::representations::loops::LoopNest;
+use crate::representations::instruction::Instruction;
+use crate::representations::loops::{LoopIter, LoopNest};
+use crate::representations::transforms::{Transform, Transforms};
 
 use super::feature::Feature;
+use super::loop_dependence_analysis::{DependenceKind, DependenceProp};
+use super::parallelizability::ParallelizableProp;
+use super::pass_pipeline::PassPipeline;
 use super::property::{Property, PropertyHook, PropertyManager};
+use super::registry::PassRegistry;
+
+/// A copy of the state of a workspace, see `Workspace::snapshot` and `Workspace::restore`
+#[derive(Clone)]
+pub struct WorkspaceSnapshot {
+    pub properties: PropertyManager,
+    pub loop_nest: LoopNest,
+    pub available_features: Vec<Feature>,
+}
 
 pub struct Workspace {
     pub properties: PropertyManager,
@@ -15,21 +32,137 @@ pub struct Workspace {
 
 impl Workspace {
     pub fn new(loop_nest: LoopNest, arch: Option<Arch>) -> Self {
-        let property_manager = PropertyManager::from_entries(
-            loop_nest
-                .body
-                .iter()
-                .map(|inst| inst.property_hook_id().clone())
-                .collect(),
-        );
         Workspace {
-            properties: property_manager,
+            properties: Self::property_manager_for(&loop_nest),
             loop_nest,
             arch,
             available_features: Vec::new(),
         }
     }
 
+    /// An empty property manager with the hooks of the loop nest registered
+    fn property_manager_for(loop_nest: &LoopNest) -> PropertyManager {
+        PropertyManager::from_entries(
+            loop_nest
+                .all_instructions()
+                .into_iter()
+                .map(|inst| inst.property_hook_id())
+                .chain(
+                    loop_nest
+                        .all_iters()
+                        .into_iter()
+                        .map(|iter| iter.property_hook_id()),
+                )
+                .chain(std::iter::once(loop_nest.property_hook_id()))
+                .collect(),
+        )
+    }
+
+    /// Drop all the properties and features, e.g. stale after a transform of the loop nest,
+    /// register the hooks of the current loop nest and run the pipeline again
+    pub fn clear_and_rerun(&mut self, pipeline: &mut PassPipeline) -> Result<(), String> {
+        self.properties = Self::property_manager_for(&self.loop_nest);
+        self.available_features.clear();
+        pipeline.run(self)
+    }
+
+    /// Apply the transform to the loop nest, the properties and features are dropped as they
+    /// are stale afterwards, and the hooks of the new loop nest are registered.
+    /// The workspace is left unchanged if the transform cannot be applied.
+    pub fn apply_transform(&mut self, transform: &Transform) -> Result<(), String> {
+        self.loop_nest = self.loop_nest.try_apply(transform)?;
+        self.properties = Self::property_manager_for(&self.loop_nest);
+        self.available_features.clear();
+        Ok(())
+    }
+
+    /// Apply the transforms in order, see `apply_transform`
+    pub fn apply_transforms(&mut self, transforms: &Transforms) -> Result<(), String> {
+        self.loop_nest = self.loop_nest.try_apply_all(transforms)?;
+        self.properties = Self::property_manager_for(&self.loop_nest);
+        self.available_features.clear();
+        Ok(())
+    }
+
+    /// Same as `apply_transform`, but when the dependence information is available, an
+    /// interchange which may reverse a dependence is rejected, see
+    /// `DependenceProp::reversed_by_interchange`.
+    pub fn apply_transform_checked(&mut self, transform: &Transform) -> Result<(), String> {
+        if let Transform::Interchange((iter1, iter2)) = transform {
+            if self.feature_available(&Feature::DependenceInfo) {
+                let violated = self
+                    .loop_nest
+                    .all_instructions()
+                    .into_iter()
+                    .flat_map(|inst| self.get_typed_property::<DependenceProp>(inst))
+                    .find(|dependence| {
+                        dependence.kind != DependenceKind::None
+                            && dependence.reversed_by_interchange(iter1, iter2)
+                    });
+                if let Some(dependence) = violated {
+                    let directions: Vec<String> = dependence
+                        .directions
+                        .iter()
+                        .map(|(_, direction)| direction.to_string())
+                        .collect();
+                    return Err(format!(
+                        "Cannot interchange {} and {}: the {} dependence on {} from {} to {} with direction ({}) may be reversed",
+                        iter1,
+                        iter2,
+                        dependence.kind,
+                        dependence.array,
+                        dependence.source_inst_id,
+                        dependence.sink_inst_id,
+                        directions.join(", ")
+                    ));
+                }
+            }
+        }
+        self.apply_transform(transform)
+    }
+
+    /// Load the loop nest of a `.loop` file and the architecture of an `.arch` file,
+    /// the workspace has no architecture if the `.arch` file does not exist.
+    /// The workspace is validated before being returned.
+    pub fn from_files(loop_path: &Path, arch_path: &Path) -> Result<Self, String> {
+        let loop_nest = LoopNest::from_loop_file(loop_path)?;
+        let arch = if arch_path.exists() {
+            Some(Arch::from_file(arch_path)?)
+        } else {
+            None
+        };
+        let workspace = Workspace::new(loop_nest, arch);
+        workspace.validate().map_err(|errors| {
+            format!(
+                "Invalid loop nest {}: {}",
+                loop_path.display(),
+                errors.join(", ")
+            )
+        })?;
+        Ok(workspace)
+    }
+
+    /// Check the invariants of the loop nest, see `LoopNest::validate`
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        self.loop_nest.validate()
+    }
+
+    /// Capture the loop nest, the properties and the available features
+    pub fn snapshot(&self) -> WorkspaceSnapshot {
+        WorkspaceSnapshot {
+            properties: self.properties.clone(),
+            loop_nest: self.loop_nest.clone(),
+            available_features: self.available_features.clone(),
+        }
+    }
+
+    /// Set the workspace back to the state captured by `snapshot`
+    pub fn restore(&mut self, snapshot: WorkspaceSnapshot) {
+        self.properties = snapshot.properties;
+        self.loop_nest = snapshot.loop_nest;
+        self.available_features = snapshot.available_features;
+    }
+
     pub fn add_property(&mut self, property_hook: impl PropertyHook, property: Box<dyn Property>) {
         self.properties
             .add_property_to_hook(property_hook, property);
@@ -40,6 +173,39 @@ impl Workspace {
             .add_property_by_id(self.property_hook_id(), property);
     }
 
+    /// Append an instruction to the loop body and register its property hook
+    pub fn add_inst_entry_for_new_instruction(&mut self, inst: &Instruction) {
+        self.properties.add_entry(inst.property_hook_id());
+        self.loop_nest.body.push(inst.clone());
+    }
+
+    /// Remove the first occurrence of the instruction from the loop body.
+    /// Its hook and properties are dropped unless an identical instruction remains.
+    /// Returns false if the instruction is not in the body.
+    pub fn remove_instruction(&mut self, inst: &Instruction) -> bool {
+        let idx = match self.loop_nest.body.iter().position(|other| other == inst) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        self.loop_nest.body.remove(idx);
+        let hook_id = inst.property_hook_id();
+        if !self
+            .loop_nest
+            .all_instructions()
+            .iter()
+            .any(|other| other.property_hook_id() == hook_id)
+        {
+            self.properties.remove_entry(&hook_id);
+        }
+        true
+    }
+
+    /// Attach a property to the loop nest as a whole, see `LoopNest::property_hook_id`
+    pub fn add_loop_property(&mut self, property: Box<dyn Property>) {
+        self.properties
+            .add_property_by_id(self.loop_nest.property_hook_id(), property);
+    }
+
     pub fn get_properties(
         &self,
         property_hook: impl PropertyHook,
@@ -51,11 +217,43 @@ impl Workspace {
         &self,
         property_hook: impl PropertyHook,
         property_id: &str,
-    ) -> Option<&Box<dyn Property>> {
+    ) -> Option<&dyn Property> {
         let properties = self.get_properties(property_hook)?;
         properties
             .iter()
             .find(|property| property.property_id() == property_id)
+            .map(|property| property.as_ref())
+    }
+
+    /// Get the properties of the concrete type `T` attached to the hook
+    pub fn get_typed_property<T: Property + 'static>(
+        &self,
+        property_hook: impl PropertyHook,
+    ) -> Vec<&T> {
+        self.get_properties(property_hook)
+            .map(|properties| {
+                properties
+                    .iter()
+                    .filter_map(|property| {
+                        let property: &dyn Any = property.as_ref();
+                        property.downcast_ref::<T>()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The iterators marked as parallel by the `ParallelizabilityPass`
+    pub fn get_parallel_iters(&self) -> Vec<&LoopIter> {
+        self.loop_nest
+            .all_iters()
+            .into_iter()
+            .filter(|iter| {
+                self.get_typed_property::<ParallelizableProp>(*iter)
+                    .iter()
+                    .any(|prop| prop.is_parallel)
+            })
+            .collect()
     }
 
     // // Find LoopIter index in LoopNest
@@ -74,45 +272,69 @@ impl Workspace {
     //         .position(|x| std::ptr::eq(x, instruction))
     // }
 
+    /// The producer -> consumer graph of the features of the registered passes, see `PassRegistry`.
+    /// A pass adds an edge from each of its required features to each of its produced features.
+    /// Every feature is a key, the consumers are sorted.
+    pub fn feature_graph() -> HashMap<String, Vec<String>> {
+        let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+        // the factories run once the registry is released
+        let factories = PassRegistry::global().factories();
+        for pass in factories.into_iter().map(|factory| factory()) {
+            let produced = pass.produced_features();
+            for feature in &produced {
+                graph.entry(feature.to_string()).or_default();
+            }
+            for required in pass.required_features() {
+                let consumers = graph.entry(required.to_string()).or_default();
+                for feature in &produced {
+                    if !consumers.contains(&feature.to_string()) {
+                        consumers.push(feature.to_string());
+                    }
+                }
+            }
+        }
+        for consumers in graph.values_mut() {
+            consumers.sort();
+        }
+        graph
+    }
+
+    /// The `feature_graph` in the DOT format of Graphviz
+    pub fn feature_graph_dot() -> String {
+        let graph = Self::feature_graph();
+        let mut features: Vec<&String> = graph.keys().collect();
+        features.sort();
+        let mut dot = String::from("digraph features {\n");
+        for feature in features {
+            dot.push_str(&format!("    \"{}\";\n", feature));
+            for consumer in &graph[feature] {
+                dot.push_str(&format!("    \"{}\" -> \"{}\";\n", feature, consumer));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
     pub fn feature_available(&self, feature: &Feature) -> bool {
         self.available_features.contains(feature)
     }
 
+    /// Compatibility shim for the untyped feature names, see `Feature::name`
     pub fn feature_available_str(&self, feature_str: &str) -> bool {
-        self.available_features
-            .iter()
-            .any(|feature| feature.name == feature_str)
+        self.feature_available(&Feature::from_name(feature_str))
     }
 }
 
 impl Display for Workspace {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Iters: \n")?;
-        for iter in &self.loop_nest.iters {
-            write!(f, "\n - {}\n", iter)?;
-            let properties = self.properties.get_properties_by_hook(iter);
-            if let Some(properties) = properties {
-                for property in properties {
-                    write!(f, "\t> {}\n ", property)?;
-                }
-            } else {
-                write!(f, "\t>\n")?;
-            }
-        }
-        write!(f, "\nBody: \n")?;
-        for inst in &self.loop_nest.body {
-            write!(f, "\n - {}\n", inst)?;
-            let properties = self.properties.get_properties_by_hook(inst);
-            if let Some(properties) = properties {
-                for property in properties {
-                    write!(f, "\t> {}\n ", property)?;
-                }
-            } else {
-                write!(f, "\t>\n")?;
-            }
-        }
+        writeln!(f, "Loop nest:")?;
+        write!(
+            f,
+            "{}",
+            self.loop_nest.display_with_properties(&self.properties)
+        )?;
         if let Some(arch) = &self.arch {
-            write!(f, "Arch: \n{}\n", arch)?;
+            write!(f, "\nArch: \n{}\n", arch)?;
         }
         Ok(())
     }
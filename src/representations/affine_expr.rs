@@ -1,18 +1,23 @@
+use crate::error::ParseError;
 use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{alpha1, alphanumeric0, char, digit1, multispace0, space0},
-    combinator::{map, map_res, opt, recognize},
+    combinator::{map, map_res, not, opt, recognize},
     multi::many0,
-    sequence::{delimited, pair, preceded, tuple},
+    sequence::{delimited, pair, preceded, separated_pair, tuple},
     IResult,
 };
 use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 /// Represents an affine expression.
 /// It can be a constant, a variable, or an affine combination of variables.
-#[derive(Clone, Debug, PartialEq)]
+/// Two expressions are equal if they have the same canonical form (see `AffineExpr::canonical`),
+/// e.g. `x + 1` and `1 + x`.
+#[derive(Clone, Debug)]
 pub enum AffineExpr {
     Var(String),
     Const(i32),
@@ -20,24 +25,91 @@ pub enum AffineExpr {
     Sub(Box<AffineExpr>, Box<AffineExpr>),
     Mul(Coeff, Box<AffineExpr>),
     Div(Box<AffineExpr>, Coeff),
+    /// Division rounded up, e.g. the number of tiles `ceildiv(N, 4)`
+    CeilDiv(Box<AffineExpr>, Coeff),
     Mod(Box<AffineExpr>, Coeff),
+    Neg(Box<AffineExpr>),
+    /// Bounds of non-rectangular domains, e.g. `max(0, i - 4)`
+    Max(Box<AffineExpr>, Box<AffineExpr>),
+    Min(Box<AffineExpr>, Box<AffineExpr>),
+    /// Polynomial indices, e.g. `i^2`, which are not affine, see `is_affine`
+    Pow(Box<AffineExpr>, u32),
 }
 
 /// Represents a coefficient (constant or a variable as metaparameters)
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Coeff {
     Const(i32),
     ConstVar(String),
     Mul(Box<Coeff>, Box<Coeff>),
+    Add(Box<Coeff>, Box<Coeff>),
+    Neg(Box<Coeff>),
 }
 
 impl Coeff {
+    /// Returns all the const variable names (metaparameters) appearing in the coefficient,
+    /// in order of first appearance and without duplicates.
+    pub fn const_vars(&self) -> Vec<String> {
+        let mut const_vars = Vec::new();
+        self.collect_const_vars(&mut const_vars);
+        const_vars
+    }
+
+    fn collect_const_vars(&self, const_vars: &mut Vec<String>) {
+        match self {
+            Coeff::Const(_) => {}
+            Coeff::ConstVar(var) => {
+                if !const_vars.contains(var) {
+                    const_vars.push(var.clone());
+                }
+            }
+            Coeff::Mul(lhs, rhs) | Coeff::Add(lhs, rhs) => {
+                lhs.collect_const_vars(const_vars);
+                rhs.collect_const_vars(const_vars);
+            }
+            Coeff::Neg(e) => e.collect_const_vars(const_vars),
+        }
+    }
+
+    /// Evaluate the coefficient given concrete values for the const variables (metaparameters)
+    pub fn evaluate(&self, params: &HashMap<String, i32>) -> Result<i32, String> {
+        match self {
+            Coeff::Const(c) => Ok(*c),
+            Coeff::ConstVar(name) => params
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("Parameter {} not found", name)),
+            Coeff::Mul(lhs, rhs) => Ok(lhs.evaluate(params)? * rhs.evaluate(params)?),
+            Coeff::Add(lhs, rhs) => Ok(lhs.evaluate(params)? + rhs.evaluate(params)?),
+            Coeff::Neg(e) => Ok(-e.evaluate(params)?),
+        }
+    }
+
+    /// Returns true if the coefficient simplifies to 0
+    pub fn is_zero(&self) -> bool {
+        matches!(self.simplify(), Coeff::Const(0))
+    }
+
+    /// Returns true if the coefficient simplifies to 1
+    pub fn is_one(&self) -> bool {
+        matches!(self.simplify(), Coeff::Const(1))
+    }
+
+    /// Returns the simplified sum of the two coefficients
+    pub fn add(&self, other: &Coeff) -> Coeff {
+        Coeff::Add(Box::new(self.clone()), Box::new(other.clone())).simplify()
+    }
+
+    /// Returns the simplified product of the two coefficients
+    pub fn mul(&self, other: &Coeff) -> Coeff {
+        Coeff::Mul(Box::new(self.clone()), Box::new(other.clone())).simplify()
+    }
+
     /// make the expression canonical by always putting the constant on the left
     pub fn normalize(&self) -> Coeff {
         let e = self.simplify();
         match e {
-            Coeff::Const(_) => self.clone(),
-            Coeff::ConstVar(_) => self.clone(),
+            Coeff::Const(_) | Coeff::ConstVar(_) => e,
             Coeff::Mul(e1, e2) => {
                 let e1 = e1.normalize();
                 let e2 = e2.normalize();
@@ -48,12 +120,21 @@ impl Coeff {
                     (_, _) => Coeff::Mul(Box::new(e1), Box::new(e2)),
                 }
             }
+            Coeff::Add(e1, e2) => {
+                let e1 = e1.normalize();
+                let e2 = e2.normalize();
+                match (e1.clone(), e2.clone()) {
+                    (_, Coeff::Const(_)) => Coeff::Add(Box::new(e2), Box::new(e1)),
+                    (_, _) => Coeff::Add(Box::new(e1), Box::new(e2)),
+                }
+            }
+            Coeff::Neg(e) => Coeff::Neg(Box::new(e.normalize())),
         }
     }
 
     /// Simplify the const expression in the AST,
     /// e.g., 0 * x = 0, 1 * x = x, x * 1 = x, x * 0 = 0
-    /// and 3 * (3 * x) = 9 * x, etc.
+    /// and 3 * (3 * x) = 9 * x, 0 + x = x, 1 + (2 + x) = 3 + x, -(3) = -3, -(-x) = x, etc.
     fn simplify(&self) -> Coeff {
         match self {
             Coeff::Const(_) => self.clone(),
@@ -93,17 +174,401 @@ impl Coeff {
                     },
                     (Coeff::Mul(_, _), Coeff::Mul(_, _))
                     | (Coeff::ConstVar(_), _)
-                    | (_, Coeff::ConstVar(_)) => Coeff::Mul(Box::new(e1), Box::new(e2)),
+                    | (_, Coeff::ConstVar(_))
+                    | (Coeff::Add(_, _), _)
+                    | (_, Coeff::Add(_, _))
+                    | (Coeff::Neg(_), _)
+                    | (_, Coeff::Neg(_)) => Coeff::Mul(Box::new(e1), Box::new(e2)),
                 }
             }
+            Coeff::Add(e1, e2) => {
+                let e1 = e1.simplify();
+                let e2 = e2.simplify();
+                match (e1.clone(), e2.clone()) {
+                    (Coeff::Const(c1), Coeff::Const(c2)) => Coeff::Const(c1 + c2),
+                    (Coeff::Const(0), e) => e,
+                    (e, Coeff::Const(0)) => e,
+                    (Coeff::Const(c1), Coeff::Add(e1, e2))
+                    | (Coeff::Add(e1, e2), Coeff::Const(c1)) => match (*e1, *e2) {
+                        (Coeff::Const(c2), e) | (e, Coeff::Const(c2)) => {
+                            Coeff::Add(Box::new(Coeff::Const(c1 + c2)), Box::new(e))
+                        }
+                        (e1, e2) => Coeff::Add(
+                            Box::new(Coeff::Const(c1)),
+                            Box::new(Coeff::Add(Box::new(e1), Box::new(e2))),
+                        ),
+                    },
+                    (e, Coeff::Const(c)) => Coeff::Add(Box::new(Coeff::Const(c)), Box::new(e)),
+                    // x + x = 2 * x
+                    (e1, e2) if e1 == e2 => {
+                        Coeff::Mul(Box::new(Coeff::Const(2)), Box::new(e1)).simplify()
+                    }
+                    (e1, e2) => Coeff::Add(Box::new(e1), Box::new(e2)),
+                }
+            }
+            Coeff::Neg(e) => match e.simplify() {
+                Coeff::Const(c) => Coeff::Const(-c),
+                Coeff::Neg(e) => *e,
+                e => Coeff::Neg(Box::new(e)),
+            },
         }
     }
 }
 
 impl AffineExpr {
+    /// Returns all the variable names appearing in the expression,
+    /// in order of first appearance and without duplicates.
+    /// Const variables used as coefficients are not included, see `Coeff::const_vars`.
+    pub fn vars(&self) -> Vec<String> {
+        let mut vars = Vec::new();
+        self.collect_vars(&mut vars);
+        vars
+    }
+
+    fn collect_vars(&self, vars: &mut Vec<String>) {
+        match self {
+            AffineExpr::Var(var) => {
+                if !vars.contains(var) {
+                    vars.push(var.clone());
+                }
+            }
+            AffineExpr::Const(_) => {}
+            AffineExpr::Add(e1, e2)
+            | AffineExpr::Sub(e1, e2)
+            | AffineExpr::Max(e1, e2)
+            | AffineExpr::Min(e1, e2) => {
+                e1.collect_vars(vars);
+                e2.collect_vars(vars);
+            }
+            AffineExpr::Mul(_, e)
+            | AffineExpr::Div(e, _)
+            | AffineExpr::CeilDiv(e, _)
+            | AffineExpr::Mod(e, _)
+            | AffineExpr::Neg(e)
+            | AffineExpr::Pow(e, _) => e.collect_vars(vars),
+        }
+    }
+
+    /// Returns the variable names which are not in `bound_vars`, in order of first appearance
+    /// and without duplicates. With the iterator names as `bound_vars`, these are the symbolic
+    /// parameters of an index, e.g. `OFFSET` in `A[i + OFFSET]`.
+    pub fn free_vars<'a>(&'a self, bound_vars: &HashSet<String>) -> Vec<&'a String> {
+        let mut free_vars = Vec::new();
+        self.collect_free_vars(bound_vars, &mut free_vars);
+        free_vars
+    }
+
+    fn collect_free_vars<'a>(&'a self, bound_vars: &HashSet<String>, vars: &mut Vec<&'a String>) {
+        match self {
+            AffineExpr::Var(var) => {
+                if !bound_vars.contains(var) && !vars.contains(&var) {
+                    vars.push(var);
+                }
+            }
+            AffineExpr::Const(_) => {}
+            AffineExpr::Add(e1, e2)
+            | AffineExpr::Sub(e1, e2)
+            | AffineExpr::Max(e1, e2)
+            | AffineExpr::Min(e1, e2) => {
+                e1.collect_free_vars(bound_vars, vars);
+                e2.collect_free_vars(bound_vars, vars);
+            }
+            AffineExpr::Mul(_, e)
+            | AffineExpr::Div(e, _)
+            | AffineExpr::CeilDiv(e, _)
+            | AffineExpr::Mod(e, _)
+            | AffineExpr::Neg(e)
+            | AffineExpr::Pow(e, _) => e.collect_free_vars(bound_vars, vars),
+        }
+    }
+
+    /// Replace every occurrence of the variable `var` by `replacement`
+    pub fn substitute(&self, var: &str, replacement: &AffineExpr) -> AffineExpr {
+        match self {
+            AffineExpr::Var(name) if name == var => replacement.clone(),
+            AffineExpr::Var(_) | AffineExpr::Const(_) => self.clone(),
+            AffineExpr::Add(e1, e2) => AffineExpr::Add(
+                Box::new(e1.substitute(var, replacement)),
+                Box::new(e2.substitute(var, replacement)),
+            ),
+            AffineExpr::Sub(e1, e2) => AffineExpr::Sub(
+                Box::new(e1.substitute(var, replacement)),
+                Box::new(e2.substitute(var, replacement)),
+            ),
+            AffineExpr::Mul(coeff, e) => {
+                AffineExpr::Mul(coeff.clone(), Box::new(e.substitute(var, replacement)))
+            }
+            AffineExpr::Div(e, coeff) => {
+                AffineExpr::Div(Box::new(e.substitute(var, replacement)), coeff.clone())
+            }
+            AffineExpr::CeilDiv(e, coeff) => {
+                AffineExpr::CeilDiv(Box::new(e.substitute(var, replacement)), coeff.clone())
+            }
+            AffineExpr::Mod(e, coeff) => {
+                AffineExpr::Mod(Box::new(e.substitute(var, replacement)), coeff.clone())
+            }
+            AffineExpr::Neg(e) => AffineExpr::Neg(Box::new(e.substitute(var, replacement))),
+            AffineExpr::Max(e1, e2) => AffineExpr::Max(
+                Box::new(e1.substitute(var, replacement)),
+                Box::new(e2.substitute(var, replacement)),
+            ),
+            AffineExpr::Min(e1, e2) => AffineExpr::Min(
+                Box::new(e1.substitute(var, replacement)),
+                Box::new(e2.substitute(var, replacement)),
+            ),
+            AffineExpr::Pow(e, exponent) => {
+                AffineExpr::Pow(Box::new(e.substitute(var, replacement)), *exponent)
+            }
+        }
+    }
+
+    /// Returns true if the variable `var` appears in the expression.
+    pub fn contains_var(&self, var: &str) -> bool {
+        match self {
+            AffineExpr::Var(name) => name == var,
+            AffineExpr::Const(_) => false,
+            AffineExpr::Add(e1, e2)
+            | AffineExpr::Sub(e1, e2)
+            | AffineExpr::Max(e1, e2)
+            | AffineExpr::Min(e1, e2) => e1.contains_var(var) || e2.contains_var(var),
+            AffineExpr::Mul(_, e)
+            | AffineExpr::Div(e, _)
+            | AffineExpr::CeilDiv(e, _)
+            | AffineExpr::Mod(e, _)
+            | AffineExpr::Neg(e)
+            | AffineExpr::Pow(e, _) => e.contains_var(var),
+        }
+    }
+
+    /// The degree of the expression in the variable `var`: 0 if it does not appear, 1 if the
+    /// expression is linear in it, and more if it is also a const variable of a coefficient
+    /// multiplying it, e.g. `N * N`. A variable in a divisor or a modulus counts as a product.
+    pub fn degree_in(&self, var: &str) -> u32 {
+        let coeff_degree = |coeff: &Coeff| coeff.const_vars().iter().any(|name| name == var) as u32;
+        match self {
+            AffineExpr::Var(name) => (name == var) as u32,
+            AffineExpr::Const(_) => 0,
+            AffineExpr::Add(e1, e2)
+            | AffineExpr::Sub(e1, e2)
+            | AffineExpr::Max(e1, e2)
+            | AffineExpr::Min(e1, e2) => e1.degree_in(var).max(e2.degree_in(var)),
+            AffineExpr::Mul(coeff, e)
+            | AffineExpr::Div(e, coeff)
+            | AffineExpr::CeilDiv(e, coeff)
+            | AffineExpr::Mod(e, coeff) => coeff_degree(coeff) + e.degree_in(var),
+            AffineExpr::Neg(e) => e.degree_in(var),
+            AffineExpr::Pow(e, exponent) => e.degree_in(var) * exponent,
+        }
+    }
+
+    /// Returns false if the expression contains a power of degree 2 or more, e.g. `i^2`.
+    /// Such indices are not handled by the affine analyses, e.g. the dependence test.
+    pub fn is_affine(&self) -> bool {
+        match self {
+            AffineExpr::Var(_) | AffineExpr::Const(_) => true,
+            AffineExpr::Add(e1, e2)
+            | AffineExpr::Sub(e1, e2)
+            | AffineExpr::Max(e1, e2)
+            | AffineExpr::Min(e1, e2) => e1.is_affine() && e2.is_affine(),
+            AffineExpr::Mul(_, e)
+            | AffineExpr::Div(e, _)
+            | AffineExpr::CeilDiv(e, _)
+            | AffineExpr::Mod(e, _)
+            | AffineExpr::Neg(e) => e.is_affine(),
+            AffineExpr::Pow(e, exponent) => *exponent <= 1 && e.is_affine(),
+        }
+    }
+
+    /// Returns true if the expression does not depend on any variable once simplified,
+    /// e.g. `3`, `x - x` or `M_a * 2`.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            AffineExpr::Const(_) => true,
+            _ => self.simplify().vars().is_empty(),
+        }
+    }
+
+    /// The sum of the terms as a left fold of `Add`, 0 for no term
+    pub fn sum(terms: Vec<AffineExpr>) -> AffineExpr {
+        let mut terms = terms.into_iter();
+        match terms.next() {
+            Some(first) => terms.fold(first, |acc, term| {
+                AffineExpr::Add(Box::new(acc), Box::new(term))
+            }),
+            None => AffineExpr::Const(0),
+        }
+    }
+
+    /// The sum `c1 * e1 + c2 * e2 + ...` of the `(c, e)` terms, see `sum` and `scale`
+    pub fn linear_combination(terms: Vec<(i32, AffineExpr)>) -> AffineExpr {
+        AffineExpr::sum(
+            terms
+                .into_iter()
+                .map(|(coeff, expr)| expr.scale(coeff))
+                .collect(),
+        )
+    }
+
+    /// Add a constant to the expression, folded if the expression is a constant and the sum
+    /// fits in an `i32`
+    pub fn offset(&self, offset: i32) -> AffineExpr {
+        match self {
+            AffineExpr::Const(c) if c.checked_add(offset).is_some() => {
+                AffineExpr::Const(c + offset)
+            }
+            _ if offset == 0 => self.clone(),
+            _ => match offset.checked_neg() {
+                Some(opposite) if opposite > 0 => AffineExpr::Sub(
+                    Box::new(self.clone()),
+                    Box::new(AffineExpr::Const(opposite)),
+                ),
+                _ => AffineExpr::Add(Box::new(self.clone()), Box::new(AffineExpr::Const(offset))),
+            },
+        }
+    }
+
+    /// Multiply the expression by a constant, folded if the expression is a constant
+    pub fn scale(&self, factor: i32) -> AffineExpr {
+        match self {
+            AffineExpr::Const(c) => AffineExpr::Const(c * factor),
+            _ if factor == 1 => self.clone(),
+            _ => AffineExpr::Mul(Coeff::Const(factor), Box::new(self.clone())),
+        }
+    }
+
+    /// Divide the expression by `divisor` if it is provably divisible,
+    /// i.e. all its constants and constant coefficients are multiples of `divisor`.
+    pub fn exact_div(&self, divisor: i32) -> Option<AffineExpr> {
+        if divisor == 0 {
+            return None;
+        }
+        match self {
+            AffineExpr::Const(c) if c % divisor == 0 => Some(AffineExpr::Const(c / divisor)),
+            AffineExpr::Mul(coeff, e) => match coeff.normalize() {
+                Coeff::Const(c) if c % divisor == 0 => Some(e.scale(c / divisor)),
+                _ => None,
+            },
+            AffineExpr::Add(e1, e2) => Some(AffineExpr::Add(
+                Box::new(e1.exact_div(divisor)?),
+                Box::new(e2.exact_div(divisor)?),
+            )),
+            AffineExpr::Sub(e1, e2) => Some(AffineExpr::Sub(
+                Box::new(e1.exact_div(divisor)?),
+                Box::new(e2.exact_div(divisor)?),
+            )),
+            AffineExpr::Neg(e) => Some(AffineExpr::Neg(Box::new(e.exact_div(divisor)?))),
+            _ => None,
+        }
+    }
+
+    /// Flatten the expression into `(coefficients, constant)` such that the expression equals
+    /// `sum(coefficients[var] * var) + constant`. Variables with a zero coefficient are dropped.
+    /// Returns `None` if the expression is not linear with constant coefficients,
+    /// i.e. if it contains a `Div`, a `CeilDiv`, a `Mod`, a `Max`, a `Min`, a power of a
+    /// variable, or a coefficient that depends on a const variable.
+    pub fn to_linear_form(&self) -> Option<(HashMap<String, i32>, i32)> {
+        if self.vars().iter().any(|var| self.degree_in(var) > 1) {
+            return None;
+        }
+        let mut coeffs = HashMap::new();
+        let constant = self.collect_linear_form(1, &mut coeffs)?;
+        coeffs.retain(|_, coeff| *coeff != 0);
+        Some((coeffs, constant))
+    }
+
+    /// Accumulate `scale * self` into `coeffs`, returns the constant part
+    fn collect_linear_form(&self, scale: i32, coeffs: &mut HashMap<String, i32>) -> Option<i32> {
+        match self {
+            AffineExpr::Var(var) => {
+                *coeffs.entry(var.clone()).or_insert(0) += scale;
+                Some(0)
+            }
+            AffineExpr::Const(c) => Some(scale * c),
+            AffineExpr::Add(e1, e2) => Some(
+                e1.collect_linear_form(scale, coeffs)? + e2.collect_linear_form(scale, coeffs)?,
+            ),
+            AffineExpr::Sub(e1, e2) => Some(
+                e1.collect_linear_form(scale, coeffs)? + e2.collect_linear_form(-scale, coeffs)?,
+            ),
+            AffineExpr::Mul(coeff, e) => match coeff.normalize() {
+                Coeff::Const(c) => e.collect_linear_form(scale * c, coeffs),
+                _ => None,
+            },
+            AffineExpr::Neg(e) => e.collect_linear_form(-scale, coeffs),
+            AffineExpr::Pow(e, exponent) => match (&**e, exponent) {
+                (_, 0) => Some(scale),
+                (e, 1) => e.collect_linear_form(scale, coeffs),
+                (AffineExpr::Const(c), exponent) => Some(scale * c.checked_pow(*exponent)?),
+                _ => None,
+            },
+            AffineExpr::Div(_, _)
+            | AffineExpr::CeilDiv(_, _)
+            | AffineExpr::Mod(_, _)
+            | AffineExpr::Max(_, _)
+            | AffineExpr::Min(_, _) => None,
+        }
+    }
+
+    /// Evaluate the expression for concrete values.
+    /// `values` maps the variables (e.g. loop iterators), `params` maps the const variables of the coefficients.
+    /// Division and modulo are floor division and euclidean remainder,
+    /// `CeilDiv` rounds up.
+    pub fn evaluate(
+        &self,
+        values: &HashMap<String, i32>,
+        params: &HashMap<String, i32>,
+    ) -> Result<i32, String> {
+        match self {
+            AffineExpr::Var(name) => values
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("Variable {} not found", name)),
+            AffineExpr::Const(c) => Ok(*c),
+            AffineExpr::Add(e1, e2) => {
+                Ok(e1.evaluate(values, params)? + e2.evaluate(values, params)?)
+            }
+            AffineExpr::Sub(e1, e2) => {
+                Ok(e1.evaluate(values, params)? - e2.evaluate(values, params)?)
+            }
+            AffineExpr::Mul(coeff, e) => Ok(coeff.evaluate(params)? * e.evaluate(values, params)?),
+            AffineExpr::Div(e, coeff) => {
+                let divisor = coeff.evaluate(params)?;
+                if divisor == 0 {
+                    return Err(format!("Division by zero in {}", self));
+                }
+                Ok(e.evaluate(values, params)?.div_euclid(divisor))
+            }
+            AffineExpr::CeilDiv(e, coeff) => {
+                let divisor = coeff.evaluate(params)?;
+                if divisor == 0 {
+                    return Err(format!("Division by zero in {}", self));
+                }
+                Ok(ceil_div(e.evaluate(values, params)?, divisor))
+            }
+            AffineExpr::Mod(e, coeff) => {
+                let modulus = coeff.evaluate(params)?;
+                if modulus == 0 {
+                    return Err(format!("Modulo by zero in {}", self));
+                }
+                Ok(e.evaluate(values, params)?.rem_euclid(modulus))
+            }
+            AffineExpr::Neg(e) => Ok(-e.evaluate(values, params)?),
+            AffineExpr::Max(e1, e2) => Ok(e1
+                .evaluate(values, params)?
+                .max(e2.evaluate(values, params)?)),
+            AffineExpr::Min(e1, e2) => Ok(e1
+                .evaluate(values, params)?
+                .min(e2.evaluate(values, params)?)),
+            AffineExpr::Pow(e, exponent) => e
+                .evaluate(values, params)?
+                .checked_pow(*exponent)
+                .ok_or_else(|| format!("Overflow in {}", self)),
+        }
+    }
+
     /// Simplify the expression by grouping constatants:
     /// 1 + x + 2 = 3 + x; x + 1 + 2 = x + 3; 1 + x + 2 + y = 3 + x + y
-    fn simplify(&self) -> AffineExpr {
+    pub fn simplify(&self) -> AffineExpr {
         match self {
             AffineExpr::Const(_) => self.clone(),
             AffineExpr::Var(_) => self.clone(),
@@ -155,10 +620,10 @@ impl AffineExpr {
                     (AffineExpr::Const(c1), AffineExpr::Sub(e1, e2)) => {
                         match (*e1.clone(), *e2.clone()) {
                             (AffineExpr::Const(c2), e2) => {
-                                AffineExpr::Add(Box::new(AffineExpr::Const(c1 - c2)), Box::new(e2))
+                                AffineExpr::Sub(Box::new(AffineExpr::Const(c1 + c2)), Box::new(e2))
                             }
                             (e1, AffineExpr::Const(c2)) => {
-                                AffineExpr::Sub(Box::new(AffineExpr::Const(c1 + c2)), Box::new(e1))
+                                AffineExpr::Add(Box::new(AffineExpr::Const(c1 - c2)), Box::new(e1))
                             }
                             (e1, e2) => AffineExpr::Add(
                                 Box::new(AffineExpr::Const(c1)),
@@ -171,10 +636,10 @@ impl AffineExpr {
                     (AffineExpr::Sub(e1, e2), AffineExpr::Const(c)) => {
                         match (*e1.clone(), *e2.clone()) {
                             (AffineExpr::Const(c1), e2) => {
-                                AffineExpr::Add(Box::new(AffineExpr::Const(c - c1)), Box::new(e2))
+                                AffineExpr::Sub(Box::new(AffineExpr::Const(c1 + c)), Box::new(e2))
                             }
                             (e1, AffineExpr::Const(c1)) => {
-                                AffineExpr::Sub(Box::new(AffineExpr::Const(c1 + c)), Box::new(e1))
+                                AffineExpr::Add(Box::new(AffineExpr::Const(c - c1)), Box::new(e1))
                             }
                             (e1, e2) => AffineExpr::Add(
                                 Box::new(AffineExpr::Const(c)),
@@ -192,30 +657,264 @@ impl AffineExpr {
                     (e1, e2) => AffineExpr::Add(Box::new(e1), Box::new(e2)),
                 }
             } // End of Add
-            AffineExpr::Sub(_, _) => {
-                // TODO
-                self.clone()
-            }
-            AffineExpr::Mul(coeff, e) => {
-                let coeff = coeff.normalize();
-                let e = e.simplify();
-                // TODO, the possible optimizations are not done
-                AffineExpr::Mul(coeff, Box::new(e))
-            }
+            AffineExpr::Sub(e1, e2) => {
+                let e1 = e1.simplify();
+                let e2 = e2.simplify();
+                match (e1.clone(), e2.clone()) {
+                    // Const - Const = Const
+                    (AffineExpr::Const(c1), AffineExpr::Const(c2)) => AffineExpr::Const(c1 - c2),
+                    // e - 0 = e
+                    (e, AffineExpr::Const(0)) => e,
+                    // e - e = 0
+                    (e1, e2) if e1.structural_eq(&e2) => AffineExpr::Const(0),
+                    // Sub(Add(Const, e), Const) = Add(Const, e)
+                    // Sub(Add(e, Const), Const) = Add(Const, e)
+                    (AffineExpr::Add(e1, e2), AffineExpr::Const(c)) => {
+                        match (*e1.clone(), *e2.clone()) {
+                            (AffineExpr::Const(c1), e2) => {
+                                AffineExpr::Add(Box::new(AffineExpr::Const(c1 - c)), Box::new(e2))
+                            }
+                            (e1, AffineExpr::Const(c1)) => {
+                                AffineExpr::Add(Box::new(AffineExpr::Const(c1 - c)), Box::new(e1))
+                            }
+                            (e1, e2) => AffineExpr::Add(
+                                Box::new(AffineExpr::Const(-c)),
+                                Box::new(AffineExpr::Add(Box::new(e1), Box::new(e2))),
+                            ),
+                        }
+                    }
+                    // Sub(Sub(Const, e), Const) = Sub(Const, e)
+                    // Sub(Sub(e, Const), Const) = Add(Const, e)
+                    (AffineExpr::Sub(e1, e2), AffineExpr::Const(c)) => {
+                        match (*e1.clone(), *e2.clone()) {
+                            (AffineExpr::Const(c1), e2) => {
+                                AffineExpr::Sub(Box::new(AffineExpr::Const(c1 - c)), Box::new(e2))
+                            }
+                            (e1, AffineExpr::Const(c1)) => {
+                                AffineExpr::Add(Box::new(AffineExpr::Const(-c1 - c)), Box::new(e1))
+                            }
+                            (e1, e2) => AffineExpr::Add(
+                                Box::new(AffineExpr::Const(-c)),
+                                Box::new(AffineExpr::Sub(Box::new(e1), Box::new(e2))),
+                            ),
+                        }
+                    }
+                    // Sub(Const, Add(Const, e)) = Sub(Const, e)
+                    // Sub(Const, Add(e, Const)) = Sub(Const, e)
+                    (AffineExpr::Const(c), AffineExpr::Add(e1, e2)) => {
+                        match (*e1.clone(), *e2.clone()) {
+                            (AffineExpr::Const(c1), e2) => {
+                                AffineExpr::Sub(Box::new(AffineExpr::Const(c - c1)), Box::new(e2))
+                            }
+                            (e1, AffineExpr::Const(c1)) => {
+                                AffineExpr::Sub(Box::new(AffineExpr::Const(c - c1)), Box::new(e1))
+                            }
+                            (e1, e2) => AffineExpr::Sub(
+                                Box::new(AffineExpr::Const(c)),
+                                Box::new(AffineExpr::Add(Box::new(e1), Box::new(e2))),
+                            ),
+                        }
+                    }
+                    // Sub(Const, Sub(Const, e)) = Add(Const, e)
+                    // Sub(Const, Sub(e, Const)) = Sub(Const, e)
+                    (AffineExpr::Const(c), AffineExpr::Sub(e1, e2)) => {
+                        match (*e1.clone(), *e2.clone()) {
+                            (AffineExpr::Const(c1), e2) => {
+                                AffineExpr::Add(Box::new(AffineExpr::Const(c - c1)), Box::new(e2))
+                            }
+                            (e1, AffineExpr::Const(c1)) => {
+                                AffineExpr::Sub(Box::new(AffineExpr::Const(c + c1)), Box::new(e1))
+                            }
+                            (e1, e2) => AffineExpr::Sub(
+                                Box::new(AffineExpr::Const(c)),
+                                Box::new(AffineExpr::Sub(Box::new(e1), Box::new(e2))),
+                            ),
+                        }
+                    }
+
+                    // Sub(e, Const) = Add(-Const, e)
+                    (e, AffineExpr::Const(c)) => {
+                        AffineExpr::Add(Box::new(AffineExpr::Const(-c)), Box::new(e))
+                    }
+
+                    // Default, do nothing
+                    (e1, e2) => AffineExpr::Sub(Box::new(e1), Box::new(e2)),
+                }
+            } // End of Sub
+            AffineExpr::Mul(coeff, e) => match (coeff.normalize(), e.simplify()) {
+                // Const * Const = Const
+                (Coeff::Const(c1), AffineExpr::Const(c2)) => AffineExpr::Const(c1 * c2),
+                // 1 * e = e
+                (Coeff::Const(1), e) => e,
+                // TODO, the other possible optimizations are not done
+                (coeff, e) => AffineExpr::Mul(coeff, Box::new(e)),
+            },
             AffineExpr::Div(e, coeff) => {
                 let e = e.simplify();
                 let coeff = coeff.normalize();
                 // TODO, the possible optimizations are not done
                 AffineExpr::Div(Box::new(e), coeff)
             }
+            AffineExpr::CeilDiv(e, coeff) => match (e.simplify(), coeff.normalize()) {
+                (AffineExpr::Const(c), Coeff::Const(divisor)) if divisor != 0 => {
+                    AffineExpr::Const(ceil_div(c, divisor))
+                }
+                (e, coeff) => AffineExpr::CeilDiv(Box::new(e), coeff),
+            },
             AffineExpr::Mod(e, coeff) => {
                 let e = e.simplify();
                 let coeff = coeff.normalize();
                 // TODO, the possible optimizations are not done
                 AffineExpr::Mod(Box::new(e), coeff)
             }
+            AffineExpr::Neg(e) => match e.simplify() {
+                // -(c) = -c
+                AffineExpr::Const(c) => AffineExpr::Const(-c),
+                // -(-e) = e
+                AffineExpr::Neg(e) => *e,
+                e => AffineExpr::Neg(Box::new(e)),
+            },
+            AffineExpr::Max(e1, e2) => match (e1.simplify(), e2.simplify()) {
+                (AffineExpr::Const(c1), AffineExpr::Const(c2)) => AffineExpr::Const(c1.max(c2)),
+                (e1, e2) => AffineExpr::Max(Box::new(e1), Box::new(e2)),
+            },
+            AffineExpr::Min(e1, e2) => match (e1.simplify(), e2.simplify()) {
+                (AffineExpr::Const(c1), AffineExpr::Const(c2)) => AffineExpr::Const(c1.min(c2)),
+                (e1, e2) => AffineExpr::Min(Box::new(e1), Box::new(e2)),
+            },
+            AffineExpr::Pow(e, exponent) => match (e.simplify(), exponent) {
+                // e^0 = 1, e^1 = e
+                (_, 0) => AffineExpr::Const(1),
+                (e, 1) => e,
+                (AffineExpr::Const(c), exponent) => match c.checked_pow(*exponent) {
+                    Some(value) => AffineExpr::Const(value),
+                    None => AffineExpr::Pow(Box::new(AffineExpr::Const(c)), *exponent),
+                },
+                (e, exponent) => AffineExpr::Pow(Box::new(e), *exponent),
+            },
         }
     }
+
+    /// Canonical form of the expression: simplified, with the terms of the sums and the operands
+    /// of `max` and `min` sorted, and the constant of the sums grouped on the left,
+    /// e.g. `y + 2 + x - 1` becomes `1 + x + y`.
+    pub fn canonical(&self) -> AffineExpr {
+        match self.simplify() {
+            e @ (AffineExpr::Var(_) | AffineExpr::Const(_)) => e,
+            e @ AffineExpr::Add(_, _) => {
+                let mut terms = Vec::new();
+                e.collect_terms(&mut terms);
+                let mut constant = 0;
+                let mut terms: Vec<AffineExpr> = terms
+                    .into_iter()
+                    .map(|term| term.canonical())
+                    .filter(|term| match term {
+                        AffineExpr::Const(c) => {
+                            constant += c;
+                            false
+                        }
+                        _ => true,
+                    })
+                    .collect();
+                terms.sort_by_cached_key(|term| term.to_string());
+                let mut terms = terms.into_iter();
+                let first = match constant {
+                    0 => terms.next().unwrap_or(AffineExpr::Const(0)),
+                    c => AffineExpr::Const(c),
+                };
+                terms.fold(first, |acc, term| {
+                    AffineExpr::Add(Box::new(acc), Box::new(term))
+                })
+            }
+            AffineExpr::Sub(e1, e2) => {
+                AffineExpr::Sub(Box::new(e1.canonical()), Box::new(e2.canonical()))
+            }
+            AffineExpr::Mul(coeff, e) => {
+                AffineExpr::Mul(coeff.normalize(), Box::new(e.canonical()))
+            }
+            AffineExpr::Div(e, coeff) => {
+                AffineExpr::Div(Box::new(e.canonical()), coeff.normalize())
+            }
+            AffineExpr::CeilDiv(e, coeff) => {
+                AffineExpr::CeilDiv(Box::new(e.canonical()), coeff.normalize())
+            }
+            AffineExpr::Mod(e, coeff) => {
+                AffineExpr::Mod(Box::new(e.canonical()), coeff.normalize())
+            }
+            AffineExpr::Neg(e) => AffineExpr::Neg(Box::new(e.canonical())),
+            AffineExpr::Max(e1, e2) => {
+                let (e1, e2) = sorted_pair(e1.canonical(), e2.canonical());
+                AffineExpr::Max(Box::new(e1), Box::new(e2))
+            }
+            AffineExpr::Min(e1, e2) => {
+                let (e1, e2) = sorted_pair(e1.canonical(), e2.canonical());
+                AffineExpr::Min(Box::new(e1), Box::new(e2))
+            }
+            AffineExpr::Pow(e, exponent) => AffineExpr::Pow(Box::new(e.canonical()), exponent),
+        }
+    }
+
+    // The terms of a chain of additions
+    fn collect_terms(self, terms: &mut Vec<AffineExpr>) {
+        match self {
+            AffineExpr::Add(e1, e2) => {
+                e1.collect_terms(terms);
+                e2.collect_terms(terms);
+            }
+            e => terms.push(e),
+        }
+    }
+
+    // Equality of the trees, without normalization
+    fn structural_eq(&self, other: &AffineExpr) -> bool {
+        match (self, other) {
+            (AffineExpr::Var(v1), AffineExpr::Var(v2)) => v1 == v2,
+            (AffineExpr::Const(c1), AffineExpr::Const(c2)) => c1 == c2,
+            (AffineExpr::Add(l1, r1), AffineExpr::Add(l2, r2))
+            | (AffineExpr::Sub(l1, r1), AffineExpr::Sub(l2, r2))
+            | (AffineExpr::Max(l1, r1), AffineExpr::Max(l2, r2))
+            | (AffineExpr::Min(l1, r1), AffineExpr::Min(l2, r2)) => {
+                l1.structural_eq(l2) && r1.structural_eq(r2)
+            }
+            (AffineExpr::Mul(c1, e1), AffineExpr::Mul(c2, e2))
+            | (AffineExpr::Div(e1, c1), AffineExpr::Div(e2, c2))
+            | (AffineExpr::CeilDiv(e1, c1), AffineExpr::CeilDiv(e2, c2))
+            | (AffineExpr::Mod(e1, c1), AffineExpr::Mod(e2, c2)) => {
+                c1 == c2 && e1.structural_eq(e2)
+            }
+            (AffineExpr::Neg(e1), AffineExpr::Neg(e2)) => e1.structural_eq(e2),
+            (AffineExpr::Pow(e1, n1), AffineExpr::Pow(e2, n2)) => n1 == n2 && e1.structural_eq(e2),
+            _ => false,
+        }
+    }
+}
+
+fn sorted_pair(e1: AffineExpr, e2: AffineExpr) -> (AffineExpr, AffineExpr) {
+    if e2.to_string() < e1.to_string() {
+        (e2, e1)
+    } else {
+        (e1, e2)
+    }
+}
+
+impl PartialEq for AffineExpr {
+    fn eq(&self, other: &AffineExpr) -> bool {
+        self.canonical().structural_eq(&other.canonical())
+    }
+}
+
+impl Eq for AffineExpr {}
+
+impl Hash for AffineExpr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // structurally equal canonical forms are written the same
+        self.canonical().to_string().hash(state);
+    }
+}
+
+/// `value / divisor` rounded up, consistent with the floor division of `Div`
+fn ceil_div(value: i32, divisor: i32) -> i32 {
+    -(-value).div_euclid(divisor)
 }
 
 impl<'de> Deserialize<'de> for AffineExpr {
@@ -228,7 +927,7 @@ impl<'de> Deserialize<'de> for AffineExpr {
         // Parse the string into an AffineExpr
         parse_expr(&s)
             .map(|(_, expr)| expr)
-            .map_err(|e| serde::de::Error::custom(format!("{:?}", e)))
+            .map_err(|e| serde::de::Error::custom(ParseError::from_nom(&s, e)))
     }
 }
 
@@ -274,7 +973,7 @@ fn parse_const_var(input: &str) -> IResult<&str, Coeff> {
 }
 
 // parse multiplication expressions for Coeff
-fn parse_coeff(input: &str) -> IResult<&str, Coeff> {
+pub fn parse_coeff(input: &str) -> IResult<&str, Coeff> {
     let (input, first) = parse_factor_coeff(input)?;
     let (input, res) = many0(preceded(
         multispace0,
@@ -286,14 +985,31 @@ fn parse_coeff(input: &str) -> IResult<&str, Coeff> {
     Ok((input, expr))
 }
 
-// parse individual factors for Coeff (constants, variables, or parenthesized expressions)
+// parse addition expressions for Coeff, only allowed inside parentheses
+// to avoid ambiguities with the affine expression addition
+fn parse_coeff_sum(input: &str) -> IResult<&str, Coeff> {
+    let (input, first) = parse_coeff(input)?;
+    let (input, res) = many0(preceded(
+        multispace0,
+        preceded(char('+'), preceded(multispace0, parse_coeff)),
+    ))(input)?;
+    let expr = res
+        .into_iter()
+        .fold(first, |acc, item| Coeff::Add(Box::new(acc), Box::new(item)));
+    Ok((input, expr))
+}
+
+// parse individual factors for Coeff (constants, variables, negations or parenthesized expressions)
 fn parse_factor_coeff(input: &str) -> IResult<&str, Coeff> {
     alt((
         parse_const_var,
         map(parse_integer, Coeff::Const),
+        map(preceded(char('-'), parse_factor_coeff), |coeff| {
+            Coeff::Neg(Box::new(coeff))
+        }),
         delimited(
             preceded(multispace0, char('(')),
-            parse_coeff,
+            parse_coeff_sum,
             preceded(multispace0, char(')')),
         ),
     ))(input)
@@ -304,8 +1020,9 @@ fn parse_mul(input: &str) -> IResult<&str, AffineExpr> {
     let (input, (coeff, expr)) = tuple((
         parse_coeff,
         alt((
-            preceded(space0, parse_factor), // handles "3x"
-            preceded(space0, preceded(char('*'), preceded(space0, parse_factor))), // handles "3 * x"
+            // handles "3x", but not "3 - x" which is a subtraction
+            preceded(pair(space0, not(char('-'))), parse_power),
+            preceded(space0, preceded(char('*'), preceded(space0, parse_power))), // handles "3 * x"
         )),
     ))(input)?;
     if coeff == Coeff::Const(1) {
@@ -315,6 +1032,56 @@ fn parse_mul(input: &str) -> IResult<&str, AffineExpr> {
     }
 }
 
+// Parse negated factors, e.g. "-x" or "-(2 * y + 1)", negative constants are parsed as constants
+fn parse_neg(input: &str) -> IResult<&str, AffineExpr> {
+    map(preceded(char('-'), parse_factor), |expr| {
+        AffineExpr::Neg(Box::new(expr))
+    })(input)
+}
+
+// Parse "max(e1, e2)" and "min(e1, e2)"
+fn parse_min_max(input: &str) -> IResult<&str, AffineExpr> {
+    let (input, (op, _, (e1, e2))) = tuple((
+        alt((tag("max"), tag("min"))),
+        multispace0,
+        delimited(
+            char('('),
+            separated_pair(
+                parse_expr,
+                delimited(multispace0, char(','), multispace0),
+                parse_expr,
+            ),
+            preceded(multispace0, char(')')),
+        ),
+    ))(input)?;
+    let (e1, e2) = (Box::new(e1), Box::new(e2));
+    Ok((
+        input,
+        match op {
+            "max" => AffineExpr::Max(e1, e2),
+            "min" => AffineExpr::Min(e1, e2),
+            _ => unreachable!(),
+        },
+    ))
+}
+
+// Parse "ceildiv(e, c)"
+fn parse_ceil_div(input: &str) -> IResult<&str, AffineExpr> {
+    let (input, (expr, divisor)) = preceded(
+        pair(tag("ceildiv"), multispace0),
+        delimited(
+            char('('),
+            separated_pair(
+                parse_expr,
+                delimited(multispace0, char(','), multispace0),
+                parse_coeff,
+            ),
+            preceded(multispace0, char(')')),
+        ),
+    )(input)?;
+    Ok((input, AffineExpr::CeilDiv(Box::new(expr), divisor)))
+}
+
 // Parse parenthesized expressions
 fn parse_parens(input: &str) -> IResult<&str, AffineExpr> {
     delimited(
@@ -328,14 +1095,37 @@ fn parse_parens(input: &str) -> IResult<&str, AffineExpr> {
 fn parse_factor(input: &str) -> IResult<&str, AffineExpr> {
     preceded(
         multispace0,
-        alt((parse_mul, parse_const, parse_var, parse_parens)),
+        alt((
+            parse_mul,
+            parse_const,
+            parse_neg,
+            parse_min_max,
+            parse_ceil_div,
+            parse_var,
+            parse_parens,
+        )),
     )(input)
 }
 
+// Parse a factor with an optional exponent, e.g. "i^2"
+fn parse_power(input: &str) -> IResult<&str, AffineExpr> {
+    let (input, (expr, exponent)) = pair(
+        parse_factor,
+        opt(preceded(
+            delimited(multispace0, char('^'), multispace0),
+            map_res(digit1, str::parse::<u32>),
+        )),
+    )(input)?;
+    match exponent {
+        Some(exponent) => Ok((input, AffineExpr::Pow(Box::new(expr), exponent))),
+        None => Ok((input, expr)),
+    }
+}
+
 // Parse term (including optional division and modulo)
 fn parse_term(input: &str) -> IResult<&str, AffineExpr> {
     let (input, (expr, op_div)) = tuple((
-        parse_factor,
+        parse_power,
         preceded(
             multispace0,
             opt(tuple((
@@ -379,13 +1169,62 @@ impl fmt::Display for AffineExpr {
             AffineExpr::Const(c) => write!(f, "{}", c),
             AffineExpr::Var(name) => write!(f, "{}", name),
             AffineExpr::Add(lhs, rhs) => write!(f, "{} + {}", lhs, rhs),
-            AffineExpr::Sub(lhs, rhs) => write!(f, "{} - {}", lhs, rhs),
-            AffineExpr::Mul(coeff, expr) => match **expr {
-                AffineExpr::Var(_) => write!(f, "{} * {}", coeff, expr),
-                _ => write!(f, "{} * ({})", coeff, expr),
+            AffineExpr::Sub(lhs, rhs) => match **rhs {
+                AffineExpr::Add(_, _) | AffineExpr::Sub(_, _) => write!(f, "{} - ({})", lhs, rhs),
+                _ => write!(f, "{} - {}", lhs, rhs),
+            },
+            AffineExpr::Mul(coeff, expr) => {
+                match coeff {
+                    Coeff::Add(_, _) => write!(f, "({})", coeff)?,
+                    _ => write!(f, "{}", coeff)?,
+                }
+                match **expr {
+                    AffineExpr::Var(_) => write!(f, " * {}", expr),
+                    _ => write!(f, " * ({})", expr),
+                }
+            }
+            AffineExpr::Div(expr, divisor) => {
+                match **expr {
+                    AffineExpr::Var(_)
+                    | AffineExpr::Const(_)
+                    | AffineExpr::CeilDiv(_, _)
+                    | AffineExpr::Max(_, _)
+                    | AffineExpr::Min(_, _) => write!(f, "{}", expr)?,
+                    _ => write!(f, "({})", expr)?,
+                }
+                match divisor {
+                    Coeff::Add(_, _) => write!(f, " / ({})", divisor),
+                    _ => write!(f, " / {}", divisor),
+                }
+            }
+            AffineExpr::Mod(expr, modulus) => {
+                match **expr {
+                    AffineExpr::Var(_) | AffineExpr::Const(_) => write!(f, "{}", expr)?,
+                    _ => write!(f, "({})", expr)?,
+                }
+                match modulus {
+                    Coeff::Add(_, _) => write!(f, " % ({})", modulus),
+                    _ => write!(f, " % {}", modulus),
+                }
+            }
+            // "-3" would be parsed back as a constant
+            AffineExpr::Neg(expr) => match **expr {
+                AffineExpr::Var(_)
+                | AffineExpr::CeilDiv(_, _)
+                | AffineExpr::Max(_, _)
+                | AffineExpr::Min(_, _) => write!(f, "-{}", expr),
+                _ => write!(f, "-({})", expr),
+            },
+            AffineExpr::CeilDiv(expr, divisor) => match divisor {
+                Coeff::Add(_, _) => write!(f, "ceildiv({}, ({}))", expr, divisor),
+                _ => write!(f, "ceildiv({}, {})", expr, divisor),
+            },
+            AffineExpr::Max(e1, e2) => write!(f, "max({}, {})", e1, e2),
+            AffineExpr::Min(e1, e2) => write!(f, "min({}, {})", e1, e2),
+            AffineExpr::Pow(expr, exponent) => match **expr {
+                AffineExpr::Var(_) => write!(f, "{}^{}", expr, exponent),
+                _ => write!(f, "({})^{}", expr, exponent),
             },
-            AffineExpr::Div(expr, divisor) => write!(f, "{} / {}", expr, divisor),
-            AffineExpr::Mod(expr, modulus) => write!(f, "{} % {}", expr, modulus),
         }
     }
 }
@@ -395,7 +1234,21 @@ impl fmt::Display for Coeff {
         match self {
             Coeff::Const(c) => write!(f, "{}", c),
             Coeff::ConstVar(name) => write!(f, "{}", name),
-            Coeff::Mul(lhs, rhs) => write!(f, "{} * {}", lhs, rhs),
+            Coeff::Mul(lhs, rhs) => {
+                match **lhs {
+                    Coeff::Add(_, _) => write!(f, "({})", lhs)?,
+                    _ => write!(f, "{}", lhs)?,
+                }
+                match **rhs {
+                    Coeff::Add(_, _) => write!(f, " * ({})", rhs),
+                    _ => write!(f, " * {}", rhs),
+                }
+            }
+            Coeff::Add(lhs, rhs) => write!(f, "{} + {}", lhs, rhs),
+            Coeff::Neg(e) => match **e {
+                Coeff::Const(_) | Coeff::ConstVar(_) => write!(f, "-{}", e),
+                _ => write!(f, "-({})", e),
+            },
         }
     }
 }
@@ -404,6 +1257,7 @@ impl fmt::Display for Coeff {
 mod tests {
     use super::AffineExpr;
     use super::Coeff;
+    use std::collections::{HashMap, HashSet};
 
     #[test]
     fn test_serde() {
@@ -469,6 +1323,415 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_vars() {
+        let (_, expr) = super::parse_expr("(x + M_a * y) / 3 - 3 * x % 5 + 2").unwrap();
+        assert_eq!(expr.vars(), vec!["x".to_string(), "y".to_string()]);
+
+        let (_, expr) = super::parse_expr("7").unwrap();
+        assert!(expr.vars().is_empty());
+
+        let coeff = Coeff::Mul(
+            Box::new(Coeff::ConstVar("M_a".to_string())),
+            Box::new(Coeff::Mul(
+                Box::new(Coeff::Const(2)),
+                Box::new(Coeff::ConstVar("M_a".to_string())),
+            )),
+        );
+        assert_eq!(coeff.const_vars(), vec!["M_a".to_string()]);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let (_, expr) = super::parse_expr("(x + M_a * y) / 3 - 3 * z % 5").unwrap();
+        assert!(expr.contains_var("x"));
+        assert!(expr.contains_var("z"));
+        assert!(!expr.contains_var("M_a"));
+        assert!(!expr.is_constant());
+
+        assert!(AffineExpr::Const(3).is_constant());
+        assert!(super::parse_expr("y - y").unwrap().1.is_constant());
+        assert!(super::parse_expr("2 + 3 - 1").unwrap().1.is_constant());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let (_, expr) = super::parse_expr("2 * i + (i + j) / 4").unwrap();
+        let (_, replacement) = super::parse_expr("i + 3").unwrap();
+        let substituted = expr.substitute("i", &replacement);
+        assert_eq!(substituted.to_string(), "2 * (i + 3) + (i + 3 + j) / 4");
+        let (_, reparsed) = super::parse_expr(&substituted.to_string()).unwrap();
+        assert_eq!(reparsed, substituted);
+        assert!(!expr
+            .substitute("i", &AffineExpr::Const(1))
+            .contains_var("i"));
+    }
+
+    #[test]
+    fn test_linear_form() {
+        let (_, expr) = super::parse_expr("3 * x + 2 - (y - 4 * x) + 1").unwrap();
+        let (coeffs, constant) = expr.to_linear_form().unwrap();
+        let expected: HashMap<String, i32> = [("x".to_string(), 7), ("y".to_string(), -1)]
+            .into_iter()
+            .collect();
+        assert_eq!(coeffs, expected);
+        assert_eq!(constant, 3);
+
+        let (_, expr) = super::parse_expr("x - x + 5").unwrap();
+        assert_eq!(expr.to_linear_form(), Some((HashMap::new(), 5)));
+
+        assert_eq!(super::parse_expr("x / 2").unwrap().1.to_linear_form(), None);
+        assert_eq!(
+            super::parse_expr("1 + x % 2").unwrap().1.to_linear_form(),
+            None
+        );
+        assert_eq!(
+            super::parse_expr("M_a * x").unwrap().1.to_linear_form(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_coeff_evaluate() {
+        let coeff = Coeff::Mul(
+            Box::new(Coeff::Const(3)),
+            Box::new(Coeff::Mul(
+                Box::new(Coeff::ConstVar("M_a".to_string())),
+                Box::new(Coeff::ConstVar("M_b".to_string())),
+            )),
+        );
+        let params: HashMap<String, i32> = [("M_a".to_string(), 2), ("M_b".to_string(), 5)]
+            .into_iter()
+            .collect();
+        assert_eq!(coeff.evaluate(&params), Ok(30));
+        assert_eq!(Coeff::Const(7).evaluate(&HashMap::new()), Ok(7));
+        assert!(coeff.evaluate(&HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_coeff_arithmetic() {
+        let m_a = Coeff::ConstVar("M_a".to_string());
+        assert!(Coeff::Mul(Box::new(Coeff::Const(0)), Box::new(m_a.clone())).is_zero());
+        assert!(Coeff::Mul(Box::new(Coeff::Const(1)), Box::new(Coeff::Const(1))).is_one());
+        assert!(!m_a.is_zero());
+        assert!(!m_a.is_one());
+
+        assert_eq!(Coeff::Const(2).add(&Coeff::Const(3)), Coeff::Const(5));
+        assert_eq!(Coeff::Const(0).add(&m_a), m_a);
+        assert_eq!(
+            m_a.add(&m_a),
+            Coeff::Mul(Box::new(Coeff::Const(2)), Box::new(m_a.clone()))
+        );
+        let sum = m_a.add(&Coeff::Const(1)).add(&Coeff::Const(2));
+        assert_eq!(
+            sum,
+            Coeff::Add(Box::new(Coeff::Const(3)), Box::new(m_a.clone()))
+        );
+
+        // sums of coefficients are written in parentheses
+        let expr = AffineExpr::Mul(sum, Box::new(AffineExpr::Var("x".to_string())));
+        assert_eq!(expr.to_string(), "(3 + M_a) * x");
+        let (_, parsed) = super::parse_expr(&expr.to_string()).unwrap();
+        assert_eq!(parsed, expr);
+    }
+
+    #[test]
+    fn test_coeff_neg() {
+        let m_a = Coeff::ConstVar("M_a".to_string());
+        let neg_m_a = Coeff::Neg(Box::new(m_a.clone()));
+        assert_eq!(
+            Coeff::Neg(Box::new(Coeff::Const(3))).normalize(),
+            Coeff::Const(-3)
+        );
+        assert_eq!(Coeff::Neg(Box::new(neg_m_a.clone())).normalize(), m_a);
+        let params: HashMap<String, i32> = [("M_a".to_string(), 4)].into_iter().collect();
+        assert_eq!(neg_m_a.evaluate(&params), Ok(-4));
+
+        let (rest, expr) = super::parse_expr("-M_a * x + 1").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            expr,
+            AffineExpr::Add(
+                Box::new(AffineExpr::Mul(
+                    neg_m_a,
+                    Box::new(AffineExpr::Var("x".to_string()))
+                )),
+                Box::new(AffineExpr::Const(1))
+            )
+        );
+        assert_eq!(expr.to_string(), "-M_a * x + 1");
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let (_, expr) = super::parse_expr("(x + M_a * y) / 3 - 3 * z % 5").unwrap();
+        let values: HashMap<String, i32> = [("x", 4), ("y", 2), ("z", 3)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let params: HashMap<String, i32> = [("M_a".to_string(), 4)].into_iter().collect();
+        // (4 + 4 * 2) / 3 - (3 * 3) % 5 = 4 - 4
+        assert_eq!(expr.evaluate(&values, &params), Ok(0));
+        assert!(expr.evaluate(&values, &HashMap::new()).is_err());
+        assert!(expr.evaluate(&HashMap::new(), &params).is_err());
+    }
+
+    #[test]
+    fn test_simplify_sub() {
+        let cases = [
+            ("x + 2 - 1", "1 + x"),
+            ("5 - 3", "2"),
+            ("x - 0", "x"),
+            ("y - y", "0"),
+            ("x - 2 - 3", "-5 + x"),
+            ("7 - (2 + x)", "5 - x"),
+            ("7 - (x - 2)", "9 - x"),
+            ("1 + (4 - x)", "5 - x"),
+            ("x - y", "x - y"),
+        ];
+        let values: HashMap<String, i32> = [("x".to_string(), 11), ("y".to_string(), -4)]
+            .into_iter()
+            .collect();
+        for (input, expected) in cases {
+            let (_, expr) = super::parse_expr(input).unwrap();
+            let simplified = expr.simplify();
+            assert_eq!(simplified.to_string(), expected, "simplifying {}", input);
+            assert_eq!(
+                expr.evaluate(&values, &HashMap::new()),
+                simplified.evaluate(&values, &HashMap::new())
+            );
+        }
+    }
+
+    #[test]
+    fn test_simplify_mul() {
+        let x = Box::new(AffineExpr::Var("x".to_string()));
+        let mul = |c: i32, e: AffineExpr| AffineExpr::Mul(Coeff::Const(c), Box::new(e));
+        assert_eq!(
+            mul(2, AffineExpr::Const(3)).simplify(),
+            AffineExpr::Const(6)
+        );
+        assert_eq!(mul(1, *x.clone()).simplify(), *x);
+        assert_eq!(mul(2, *x.clone()).simplify(), mul(2, *x));
+    }
+
     #[test]
     fn test_normalization() {}
+
+    #[test]
+    fn test_canonical() {
+        let expr = |s: &str| super::parse_expr(s).unwrap().1;
+        assert_eq!(expr("y + 2 + x - 1").canonical().to_string(), "1 + x + y");
+        assert_eq!(expr("x - 1 + 1").canonical().to_string(), "x");
+        assert_eq!(expr("max(j, i)").canonical().to_string(), "max(i, j)");
+        assert_eq!(expr("x + 1"), expr("1 + x"));
+        assert_eq!(expr("2 * (y + x) / 4"), expr("2 * (x + y) / 4"));
+        assert_eq!(expr("min(N, i + 1)"), expr("min(1 + i, N)"));
+        assert_ne!(expr("x + 1"), expr("x + 2"));
+        assert_ne!(expr("x - y"), expr("y - x"));
+
+        let mut counts: HashMap<AffineExpr, i32> = HashMap::new();
+        for input in ["i + 1", "1 + i", "i - 1 + 2", "i"] {
+            *counts.entry(expr(input)).or_insert(0) += 1;
+        }
+        assert_eq!(counts[&expr("i + 1")], 3);
+        assert_eq!(counts[&expr("i")], 1);
+    }
+
+    #[test]
+    fn test_ceil_div() {
+        let expr = |s: &str| super::parse_expr(s).unwrap().1;
+        let ceil_div = expr("ceildiv(N, 4)");
+        assert_eq!(
+            ceil_div,
+            AffineExpr::CeilDiv(Box::new(AffineExpr::Var("N".to_string())), Coeff::Const(4))
+        );
+        for input in [
+            "ceildiv(N, 4)",
+            "ceildiv(2 * N + 1, (1 + T_a)) - 1",
+            "ceildiv(N, M_a) / 2",
+        ] {
+            assert_eq!(expr(&expr(input).to_string()), expr(input), "{}", input);
+        }
+        assert_eq!(expr("ceildiv(N, 4)").to_string(), "ceildiv(N, 4)");
+
+        assert_eq!(expr("ceildiv(7, 4)").simplify(), AffineExpr::Const(2));
+        assert_eq!(expr("ceildiv(8, 4)").simplify(), AffineExpr::Const(2));
+        assert_eq!(expr("ceildiv(-7, 4)").simplify(), AffineExpr::Const(-1));
+        assert_eq!(expr("ceildiv(7, 0)").simplify(), expr("ceildiv(7, 0)"));
+
+        let values: HashMap<String, i32> = [("N".to_string(), 9)].into_iter().collect();
+        assert_eq!(ceil_div.evaluate(&values, &HashMap::new()), Ok(3));
+        assert_eq!(expr("N / 4").evaluate(&values, &HashMap::new()), Ok(2));
+        assert_eq!(ceil_div.to_linear_form(), None);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let expr = |s: &str| super::parse_expr(s).unwrap().1;
+        let max = expr("max(0, i - 4)");
+        assert_eq!(
+            max,
+            AffineExpr::Max(Box::new(AffineExpr::Const(0)), Box::new(expr("i - 4")))
+        );
+        assert_eq!(max.to_string(), "max(0, i - 4)");
+        for input in ["max(0, i - 4) + 1", "min(N, 4 * (i + 1)) / 2", "-min(i, j)"] {
+            assert_eq!(expr(&expr(input).to_string()), expr(input), "{}", input);
+        }
+        // identifiers starting with max or min are still variables
+        assert_eq!(expr("maxi"), AffineExpr::Var("maxi".to_string()));
+
+        assert_eq!(expr("max(3, 1 + 1)").simplify(), AffineExpr::Const(3));
+        assert_eq!(expr("min(3, -2)").simplify(), AffineExpr::Const(-2));
+        assert_eq!(
+            expr("max(0, i - 4)").simplify().to_string(),
+            "max(0, -4 + i)"
+        );
+        assert_eq!(max.vars(), vec!["i".to_string()]);
+        assert_eq!(max.to_linear_form(), None);
+
+        let values: HashMap<String, i32> = [("i".to_string(), 2)].into_iter().collect();
+        assert_eq!(max.evaluate(&values, &HashMap::new()), Ok(0));
+        assert_eq!(
+            expr("min(i, 1) + max(i, 1)").evaluate(&values, &HashMap::new()),
+            Ok(3)
+        );
+    }
+
+    #[test]
+    fn test_neg() {
+        let expr = |s: &str| super::parse_expr(s).unwrap().1;
+        let x = Box::new(AffineExpr::Var("x".to_string()));
+        assert_eq!(expr("-x"), AffineExpr::Neg(x.clone()));
+        assert_eq!(expr("-3"), AffineExpr::Const(-3));
+        assert_eq!(expr("y - -x"), expr("y - (-x)"));
+        assert_eq!(expr("-(2 * y + 1)").to_string(), "-(2 * y + 1)");
+        for input in ["-x", "-(2 * y + 1)", "-(3)", "y + -x / 2", "-(-x)"] {
+            assert_eq!(expr(&expr(input).to_string()), expr(input), "{}", input);
+        }
+
+        assert_eq!(expr("-(3)").simplify(), AffineExpr::Const(-3));
+        assert_eq!(expr("-(-x)").simplify(), *x);
+        assert_eq!(expr("-(1 + 2)").simplify(), AffineExpr::Const(-3));
+
+        let values: HashMap<String, i32> = [("x".to_string(), 5), ("y".to_string(), 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            expr("-(2 * y + 1) - -x").evaluate(&values, &HashMap::new()),
+            Ok(0)
+        );
+        let (coeffs, constant) = expr("-(2 * y - x + 1)").to_linear_form().unwrap();
+        assert_eq!(coeffs["y"], -2);
+        assert_eq!(coeffs["x"], 1);
+        assert_eq!(constant, -1);
+    }
+
+    #[test]
+    fn test_bound_arithmetic() {
+        let expr = |s: &str| super::parse_expr(s).unwrap().1;
+        assert_eq!(expr("8").offset(-1), expr("7"));
+        assert_eq!(expr("N").offset(-1).to_string(), "N - 1");
+        assert_eq!(expr("N").offset(2).to_string(), "N + 2");
+        assert_eq!(expr("8").scale(4), expr("32"));
+        assert_eq!(expr("N + 1").scale(4).to_string(), "4 * (N + 1)");
+        assert_eq!(expr("16").exact_div(4), Some(expr("4")));
+        assert_eq!(expr("18").exact_div(4), None);
+        assert_eq!(
+            expr("8 * N + 4").exact_div(4).unwrap().to_string(),
+            "2 * N + 1"
+        );
+        assert_eq!(expr("4 * N").exact_div(4), Some(expr("N")));
+        assert_eq!(expr("N").exact_div(4), None);
+    }
+
+    #[test]
+    fn test_degree_in() {
+        let (_, expr) = super::parse_expr("3 * x + y - 2").unwrap();
+        assert_eq!(expr.degree_in("x"), 1);
+        assert_eq!(expr.degree_in("y"), 1);
+        assert_eq!(expr.degree_in("z"), 0);
+
+        // N * N, the coefficient is the const variable N
+        let expr = AffineExpr::Mul(
+            Coeff::ConstVar("N".to_string()),
+            Box::new(AffineExpr::Var("N".to_string())),
+        );
+        assert_eq!(expr.degree_in("N"), 2);
+        assert_eq!(expr.to_linear_form(), None);
+
+        // a const variable alone is not a variable of the expression
+        let (_, expr) = super::parse_expr("M_a * x").unwrap();
+        assert_eq!(expr.degree_in("M_a"), 1);
+        assert_eq!(expr.degree_in("x"), 1);
+    }
+
+    #[test]
+    fn test_free_vars() {
+        let (_, expr) = super::parse_expr("i + OFFSET - 2 * (j + OFFSET) + K").unwrap();
+        let bound_vars: HashSet<String> = ["i".to_string(), "j".to_string()].into_iter().collect();
+        assert_eq!(expr.free_vars(&bound_vars), vec!["OFFSET", "K"]);
+        assert_eq!(expr.free_vars(&HashSet::new()).len(), 4);
+    }
+
+    #[test]
+    fn test_sum() {
+        let var = |name: &str| AffineExpr::Var(name.to_string());
+        assert_eq!(AffineExpr::sum(vec![]), AffineExpr::Const(0));
+        assert_eq!(AffineExpr::sum(vec![var("x")]), var("x"));
+        assert_eq!(
+            AffineExpr::sum(vec![var("x"), var("y"), AffineExpr::Const(1)]).to_string(),
+            "x + y + 1"
+        );
+        let expr =
+            AffineExpr::linear_combination(vec![(4, var("x")), (1, var("y")), (-2, var("z"))]);
+        assert_eq!(expr.to_string(), "4 * x + y + -2 * z");
+        assert_eq!(
+            expr.to_linear_form().unwrap().0,
+            [
+                ("x".to_string(), 4),
+                ("y".to_string(), 1),
+                ("z".to_string(), -2)
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn test_pow() {
+        let expr: AffineExpr = serde_yaml::from_str("i^2 + 2 * j").unwrap();
+        assert_eq!(
+            expr,
+            AffineExpr::Add(
+                Box::new(AffineExpr::Pow(
+                    Box::new(AffineExpr::Var("i".to_string())),
+                    2
+                )),
+                Box::new(AffineExpr::Mul(
+                    Coeff::Const(2),
+                    Box::new(AffineExpr::Var("j".to_string()))
+                )),
+            )
+        );
+        assert_eq!(expr.to_string(), "i^2 + 2 * j");
+        assert!(!expr.is_affine());
+        assert_eq!(expr.degree_in("i"), 2);
+        assert_eq!(expr.degree_in("j"), 1);
+        assert_eq!(expr.to_linear_form(), None);
+        assert_eq!(expr.vars(), vec!["i", "j"]);
+
+        let values = HashMap::from([("i".to_string(), 3), ("j".to_string(), 1)]);
+        assert_eq!(expr.evaluate(&values, &HashMap::new()), Ok(11));
+
+        // (i + 1)^2 with i = 2
+        let expr: AffineExpr = serde_yaml::from_str("(i + 1)^2").unwrap();
+        assert_eq!(expr.to_string(), "(i + 1)^2");
+        let two = AffineExpr::Const(2);
+        assert_eq!(expr.substitute("i", &two).simplify(), AffineExpr::Const(9));
+
+        let expr: AffineExpr = serde_yaml::from_str("3 * i^1").unwrap();
+        assert!(expr.is_affine());
+        assert_eq!(expr.simplify().to_string(), "3 * i");
+    }
 }
@@ -1,4 +1,6 @@
 use std::fmt::Display;
+use std::fs;
+use std::path::Path;
 
 use crate::passes::property::PropertyHook;
 use property_hood_id_derive::PropertyHook;
@@ -33,12 +35,14 @@ pub enum DataPort {
 pub struct NocPort {
     pub name: String,
     pub topology: Vec<i32>,
+    pub bandwidth_bytes_per_cycle: f64,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct MemoryPort {
     pub name: String,
     pub mem_name: String,
+    pub bandwidth_bytes_per_cycle: f64,
 }
 
 pub enum ControlType {
@@ -63,15 +67,125 @@ impl Display for PEArch {
     }
 }
 
+impl DataPort {
+    pub fn name(&self) -> &str {
+        match self {
+            DataPort::NocPort(port) => &port.name,
+            DataPort::MemoryWritePort(port) | DataPort::MemoryReadPort(port) => &port.name,
+        }
+    }
+
+    /// Bytes the port can transfer per cycle
+    pub fn bandwidth_bytes_per_cycle(&self) -> f64 {
+        match self {
+            DataPort::NocPort(port) => port.bandwidth_bytes_per_cycle,
+            DataPort::MemoryWritePort(port) | DataPort::MemoryReadPort(port) => {
+                port.bandwidth_bytes_per_cycle
+            }
+        }
+    }
+}
+
 impl Arch {
+    /// Read an architecture from a YAML `.arch` file
+    pub fn from_file(path: &Path) -> Result<Arch, String> {
+        let yaml_str = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read the architecture {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&yaml_str)
+            .map_err(|e| format!("Cannot parse the architecture {}: {}", path.display(), e))
+    }
+
+    /// Encode the architecture with bincode, faster to read back than YAML
+    pub fn to_binary(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("An architecture can always be encoded")
+    }
+
+    /// Decode an architecture encoded by `to_binary`
+    pub fn from_binary(bytes: &[u8]) -> Result<Arch, String> {
+        bincode::deserialize(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Write the architecture to a YAML `.arch` file
+    pub fn to_file(&self, path: &Path) -> Result<(), String> {
+        let yaml_str = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, yaml_str)
+            .map_err(|e| format!("Cannot write the architecture {}: {}", path.display(), e))
+    }
+
     pub fn data_ports(&self) -> &Vec<DataPort> {
         &self.pe_arch.data_ports
     }
+
+    pub fn noc_ports(&self) -> Vec<&NocPort> {
+        self.data_ports()
+            .iter()
+            .filter_map(|port| match port {
+                DataPort::NocPort(port) => Some(port),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn memory_read_ports(&self) -> Vec<&MemoryPort> {
+        self.data_ports()
+            .iter()
+            .filter_map(|port| match port {
+                DataPort::MemoryReadPort(port) => Some(port),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn memory_write_ports(&self) -> Vec<&MemoryPort> {
+        self.data_ports()
+            .iter()
+            .filter_map(|port| match port {
+                DataPort::MemoryWritePort(port) => Some(port),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The dimensions of the PE array, i.e. along which a NoC port moves data,
+    /// e.g. `x` and `y` for a 2D mesh
+    pub fn spatial_dimensions(&self) -> Vec<&Dimension> {
+        let noc_ports = self.noc_ports();
+        self.dimensions
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| {
+                noc_ports
+                    .iter()
+                    .any(|port| port.topology.get(*idx).is_some_and(|step| *step != 0))
+            })
+            .map(|(_, dim)| dim)
+            .collect()
+    }
+
+    /// The dimensions not connected by the NoC, e.g. the SIMD lanes of a PE
+    pub fn temporal_dimensions(&self) -> Vec<&Dimension> {
+        let spatial_dimensions = self.spatial_dimensions();
+        self.dimensions
+            .iter()
+            .filter(|dim| {
+                !spatial_dimensions
+                    .iter()
+                    .any(|spatial| std::ptr::eq(*spatial, *dim))
+            })
+            .collect()
+    }
+
+    /// Number of PEs, the product of the shapes of the spatial dimensions
+    pub fn total_pe_count(&self) -> i32 {
+        self.spatial_dimensions()
+            .iter()
+            .map(|dim| dim.shape)
+            .product()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use serde_yaml;
     use std::{fs, path::Path};
 
     use super::*;
@@ -79,9 +193,39 @@ mod tests {
     #[test]
     fn test_deserialize() {
         let manifest_dir = env!("CARGO_MANIFEST_DIR");
-        // Construct the file path to loopprob.yaml
         let file_path = Path::new(manifest_dir).join("example/mesh_distributed-mem.arch");
-        let yaml_str = fs::read_to_string(file_path).expect("Failed to read YAML file");
-        let _arch: Arch = serde_yaml::from_str(&yaml_str).expect("Failed to deserialize YAML");
+        let arch = Arch::from_file(&file_path).unwrap();
+
+        // write -> read round-trip
+        let out_path = std::env::temp_dir().join("loopana_test_arch.arch");
+        arch.to_file(&out_path).unwrap();
+        assert_eq!(Arch::from_file(&out_path).unwrap(), arch);
+        fs::remove_file(&out_path).unwrap();
+
+        let missing = Path::new(manifest_dir).join("example/missing.arch");
+        assert!(Arch::from_file(&missing)
+            .unwrap_err()
+            .starts_with("Cannot read the architecture"));
+        let not_arch = Path::new(manifest_dir).join("example/prob.loop");
+        assert!(Arch::from_file(&not_arch)
+            .unwrap_err()
+            .starts_with("Cannot parse the architecture"));
+    }
+
+    #[test]
+    fn test_dimensions_and_ports() {
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+        let file_path = Path::new(manifest_dir).join("example/mesh_distributed-mem.arch");
+        let arch = Arch::from_file(&file_path).unwrap();
+
+        let names = |dims: Vec<&Dimension>| -> Vec<String> {
+            dims.into_iter().map(|dim| dim.name.clone()).collect()
+        };
+        assert_eq!(names(arch.spatial_dimensions()), vec!["x", "y"]);
+        assert_eq!(names(arch.temporal_dimensions()), vec!["SIMD"]);
+        assert_eq!(arch.total_pe_count(), 64);
+        assert_eq!(arch.noc_ports().len(), 4);
+        assert_eq!(arch.memory_read_ports()[0].name, "RP1");
+        assert!(arch.memory_write_ports().is_empty());
     }
 }
@@ -1,18 +1,21 @@
+use crate::error::ParseError;
 use crate::passes::property::PropertyHook;
 
 use super::affine_expr::{self, AffineExpr};
+use super::loops::{LoopIter, LoopNest};
 use nom::{
     branch::alt,
-    bytes::complete::tag,
+    bytes::complete::{is_not, tag},
     character::complete::{
         alpha1, alphanumeric0, alphanumeric1, char, digit1, multispace0, multispace1,
     },
-    combinator::{opt, recognize},
+    combinator::{all_consuming, cut, map_parser, opt, recognize},
     multi::{separated_list0, separated_list1},
     sequence::{delimited, pair, preceded, terminated, tuple},
     IResult,
 };
 use property_hood_id_derive::PropertyHook;
+use serde::de::{self, value::MapAccessDeserializer, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt;
 
@@ -21,6 +24,13 @@ pub enum Instruction {
     DataLoad(DataAccess),
     DataStore(DataAccess),
     Compute(Compute),
+    /// A hint to fetch data ahead of its load, e.g. `PREFETCH A[i + 1]`.
+    /// No register is produced, the `reg` of the access is empty.
+    Prefetch(DataAccess),
+    /// A synchronization point between pipeline stages, e.g. `SYNC stage1`
+    SyncBarrier(String),
+    /// A loop nest nested in the body, e.g. the point loops of a two-level tiling
+    LoopBody(Box<LoopNest>),
 }
 
 type Register = String;
@@ -28,7 +38,11 @@ type Register = String;
 #[derive(Debug, Clone, PartialEq)]
 pub enum Operand {
     Reg(Register),
-    Imm(i32),
+    ImmInt(i32),
+    /// Written with a decimal point, e.g. `$0.5` or `$1.0`
+    ImmFloat(f64),
+    /// A SIMD vector register and its number of lanes, e.g. `V0:4`
+    VecReg(Register, u32),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -46,7 +60,8 @@ pub struct DataAccess {
 pub struct Compute {
     pub op: String,
     pub src: Vec<Operand>,
-    pub dst: Register,
+    /// A register or a vector register
+    pub dst: Operand,
     /// optional condition to execute the instruction, String is the condition register
     pub cond_suffix: Option<ConditionSuffix>,
     pub cond: Option<Register>,
@@ -60,6 +75,29 @@ pub enum ConditionSuffix {
     LE,
     GT,
     GE,
+    /// Unordered floating-point comparisons, also true if an operand is NaN
+    UEQ,
+    UNE,
+    ULT,
+    ULE,
+    UGT,
+    UGE,
+    /// True if no operand is NaN
+    ORD,
+    /// True if an operand is NaN
+    UNORD,
+}
+
+/// Class of the operation of a compute instruction, see `Compute::op_class`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpClass {
+    MAC,
+    MUL,
+    ADD,
+    SUB,
+    CMP,
+    MOV,
+    OTHER,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -68,6 +106,198 @@ pub struct Conditional {
     pub prob: f64,
 }
 
+impl Compute {
+    pub fn op_class(&self) -> OpClass {
+        match self.op.as_str() {
+            "mac" | "fma" => OpClass::MAC,
+            "mul" => OpClass::MUL,
+            "add" => OpClass::ADD,
+            "sub" => OpClass::SUB,
+            "cmp" => OpClass::CMP,
+            "mov" => OpClass::MOV,
+            _ => OpClass::OTHER,
+        }
+    }
+
+    /// Whether the operation is a fused multiply-add
+    pub fn is_fma(&self) -> bool {
+        self.op_class() == OpClass::MAC
+    }
+
+    /// Whether the destination is also a source, i.e. `acc = acc op val`
+    pub fn is_reduction_candidate(&self) -> bool {
+        self.src.contains(&self.dst)
+    }
+
+    /// Widest vector register among the destination and the sources,
+    /// `None` if the instruction only operates on scalars
+    pub fn vector_width(&self) -> Option<u32> {
+        self.src
+            .iter()
+            .chain(std::iter::once(&self.dst))
+            .filter_map(|operand| match operand {
+                Operand::VecReg(_, width) => Some(*width),
+                _ => None,
+            })
+            .max()
+    }
+}
+
+impl DataAccess {
+    /// Replace the variable `var` by `replacement` in the address
+    pub fn substitute(&self, var: &str, replacement: &AffineExpr) -> DataAccess {
+        DataAccess {
+            addr: self
+                .addr
+                .iter()
+                .map(|idx| idx.substitute(var, replacement))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// `(array_name, dim)` for each index of the address which is not a constant
+    pub fn accessed_arrays_and_dims(&self) -> Vec<(String, usize)> {
+        self.addr
+            .iter()
+            .enumerate()
+            .filter(|(_, expr)| !expr.is_constant())
+            .map(|(dim, _)| (self.array_name.clone(), dim))
+            .collect()
+    }
+
+    /// `c` if a single index of the address uses `iter`, as `c * iter + offset` with a
+    /// constant `c`, 0 if no index uses `iter`.
+    /// `None` if the index is not linear or if several indices use `iter`.
+    pub fn stride_in_dim(&self, iter: &str) -> Option<i32> {
+        let mut indices = self.addr.iter().filter(|expr| expr.contains_var(iter));
+        let index = match indices.next() {
+            Some(index) => index,
+            None => return Some(0),
+        };
+        if indices.next().is_some() {
+            return None;
+        }
+        let (coeffs, _) = index.to_linear_form()?;
+        Some(coeffs.get(iter).copied().unwrap_or(0))
+    }
+}
+
+impl Instruction {
+    /// Names of the arrays read or written, including the ones of a nested loop nest
+    pub fn accessed_arrays(&self) -> Vec<&str> {
+        match self {
+            Instruction::DataLoad(access)
+            | Instruction::DataStore(access)
+            | Instruction::Prefetch(access) => vec![access.array_name.as_str()],
+            Instruction::Compute(_) | Instruction::SyncBarrier(_) => vec![],
+            Instruction::LoopBody(nest) => nest
+                .body
+                .iter()
+                .flat_map(|inst| inst.accessed_arrays())
+                .collect(),
+        }
+    }
+
+    /// Whether the instruction is only executed under a condition
+    pub fn is_conditional(&self) -> bool {
+        match self {
+            Instruction::DataLoad(access)
+            | Instruction::DataStore(access)
+            | Instruction::Prefetch(access) => access.cond.is_some(),
+            Instruction::Compute(compute) => compute.cond.is_some(),
+            Instruction::SyncBarrier(_) | Instruction::LoopBody(_) => false,
+        }
+    }
+
+    /// The register written by the instruction, not including a nested loop nest
+    pub fn written_register(&self) -> Option<&String> {
+        match self {
+            Instruction::DataLoad(access) => Some(&access.reg),
+            Instruction::Compute(compute) => compute.dst.register(),
+            Instruction::DataStore(_)
+            | Instruction::Prefetch(_)
+            | Instruction::SyncBarrier(_)
+            | Instruction::LoopBody(_) => None,
+        }
+    }
+
+    /// The registers read by the instruction, including its condition register,
+    /// not including a nested loop nest
+    pub fn read_registers(&self) -> Vec<&String> {
+        let mut regs = Vec::new();
+        match self {
+            Instruction::DataLoad(access) | Instruction::Prefetch(access) => {
+                regs.extend(&access.cond);
+            }
+            Instruction::DataStore(access) => {
+                regs.push(&access.reg);
+                regs.extend(&access.cond);
+            }
+            Instruction::Compute(compute) => {
+                regs.extend(compute.src.iter().filter_map(Operand::register));
+                regs.extend(&compute.cond);
+            }
+            Instruction::SyncBarrier(_) | Instruction::LoopBody(_) => {}
+        }
+        regs
+    }
+
+    /// The instruction with its index expressions in canonical form, see `AffineExpr::canonical`,
+    /// e.g. `R1 <= A[1 + x]` and `R1 <= A[x + 1]` have the same normalized form
+    pub fn normalize(&self) -> Instruction {
+        let normalize_access = |access: &DataAccess| DataAccess {
+            addr: access.addr.iter().map(|idx| idx.canonical()).collect(),
+            ..access.clone()
+        };
+        match self {
+            Instruction::DataLoad(access) => Instruction::DataLoad(normalize_access(access)),
+            Instruction::DataStore(access) => Instruction::DataStore(normalize_access(access)),
+            Instruction::Prefetch(access) => Instruction::Prefetch(normalize_access(access)),
+            Instruction::Compute(_) | Instruction::SyncBarrier(_) => self.clone(),
+            Instruction::LoopBody(nest) => Instruction::LoopBody(Box::new(LoopNest {
+                iters: nest.iters.clone(),
+                body: nest.body.iter().map(|inst| inst.normalize()).collect(),
+            })),
+        }
+    }
+
+    /// Replace the variable `var` by `replacement` in the index expressions and in the bounds
+    /// of the nested loops
+    pub fn substitute(&self, var: &str, replacement: &AffineExpr) -> Instruction {
+        match self {
+            Instruction::DataLoad(access) => {
+                Instruction::DataLoad(access.substitute(var, replacement))
+            }
+            Instruction::DataStore(access) => {
+                Instruction::DataStore(access.substitute(var, replacement))
+            }
+            Instruction::Prefetch(access) => {
+                Instruction::Prefetch(access.substitute(var, replacement))
+            }
+            Instruction::Compute(_) | Instruction::SyncBarrier(_) => self.clone(),
+            Instruction::LoopBody(nest) => Instruction::LoopBody(Box::new(LoopNest {
+                iters: nest
+                    .iters
+                    .iter()
+                    .map(|iter| LoopIter {
+                        bounds: (
+                            iter.bounds.0.substitute(var, replacement),
+                            iter.bounds.1.substitute(var, replacement),
+                        ),
+                        ..iter.clone()
+                    })
+                    .collect(),
+                body: nest
+                    .body
+                    .iter()
+                    .map(|inst| inst.substitute(var, replacement))
+                    .collect(),
+            })),
+        }
+    }
+}
+
 fn parse_var_id(input: &str) -> IResult<&str, &str> {
     recognize(pair(alpha1, alphanumeric0))(input)
 }
@@ -76,20 +306,47 @@ fn parse_reg_id(input: &str) -> IResult<&str, &str> {
     recognize(pair(char('R'), alphanumeric0))(input)
 }
 
+fn parse_vec_reg(input: &str) -> IResult<&str, &str> {
+    recognize(tuple((char('V'), alphanumeric0, char(':'), digit1)))(input)
+}
+
 fn parse_immediate(input: &str) -> IResult<&str, &str> {
-    recognize(pair(char('$'), digit1))(input)
+    recognize(tuple((char('$'), digit1, opt(pair(char('.'), digit1)))))(input)
 }
 
 fn parse_src(input: &str) -> IResult<&str, &str> {
-    alt((parse_reg_id, parse_immediate))(input)
+    alt((parse_vec_reg, parse_reg_id, parse_immediate))(input)
+}
+
+// each index is an affine expression spanning the whole text between its brackets
+fn parse_index(input: &str) -> IResult<&str, AffineExpr> {
+    map_parser(
+        is_not("[]"),
+        all_consuming(delimited(multispace0, affine_expr::parse_expr, multispace0)),
+    )(input)
 }
 
-fn parse_indices(input: &str) -> IResult<&str, Vec<&str>> {
-    delimited(tag("["), separated_list0(tag("]["), parse_var_id), tag("]"))(input)
+// a malformed index fails the whole instruction instead of being tried as another instruction
+fn parse_indices(input: &str) -> IResult<&str, Vec<AffineExpr>> {
+    preceded(
+        tag("["),
+        cut(terminated(
+            separated_list0(tag("]["), parse_index),
+            tag("]"),
+        )),
+    )(input)
 }
 
 fn parse_cond_code(input: &str) -> IResult<&str, &str> {
     alt((
+        tag("UNORD"),
+        tag("UEQ"),
+        tag("UNE"),
+        tag("ULT"),
+        tag("ULE"),
+        tag("UGT"),
+        tag("UGE"),
+        tag("ORD"),
         tag("EQ"),
         tag("NE"),
         tag("LT"),
@@ -118,12 +375,6 @@ fn parse_data_load(input: &str) -> IResult<&str, Instruction> {
         parse_indices,
         opt(preceded(multispace1, parse_condition)),
     ))(input)?;
-
-    // Parse the indices into AffineExpr
-    let idxs: Vec<AffineExpr> = idxs
-        .iter()
-        .map(|expr| affine_expr::parse_expr(expr).unwrap().1)
-        .collect();
     if let Some((cond_suffix, cond)) = cond {
         Ok((
             input,
@@ -158,12 +409,6 @@ fn parse_data_store(input: &str) -> IResult<&str, Instruction> {
         opt(preceded(multispace1, parse_condition)),
     ))(input)?;
 
-    // Parse the indices into AffineExpr
-    let idxs: Vec<AffineExpr> = idxs
-        .iter()
-        .map(|expr| affine_expr::parse_expr(expr).unwrap().1)
-        .collect();
-
     if let Some((cond_suffix, cond)) = cond {
         Ok((
             input,
@@ -189,23 +434,53 @@ fn parse_data_store(input: &str) -> IResult<&str, Instruction> {
     }
 }
 
+fn parse_prefetch(input: &str) -> IResult<&str, Instruction> {
+    let (input, (array, idxs, cond)) = tuple((
+        preceded(pair(tag("PREFETCH"), multispace1), parse_var_id),
+        parse_indices,
+        opt(preceded(multispace1, parse_condition)),
+    ))(input)?;
+    let (cond_suffix, cond) = match cond {
+        Some((cond_suffix, cond)) => (Some(cond_suffix), Some(cond)),
+        None => (None, None),
+    };
+    Ok((
+        input,
+        Instruction::Prefetch(DataAccess {
+            array_name: array.to_string(),
+            addr: idxs,
+            reg: Register::new(),
+            cond_suffix,
+            cond,
+        }),
+    ))
+}
+
+fn parse_sync_barrier(input: &str) -> IResult<&str, Instruction> {
+    let (input, label) = preceded(pair(tag("SYNC"), multispace1), parse_var_id)(input)?;
+    Ok((input, Instruction::SyncBarrier(label.to_string())))
+}
+
 fn parse_compute(input: &str) -> IResult<&str, Instruction> {
     let (input, (op, dst, srcs, cond)) = tuple((
         preceded(multispace0, alphanumeric1),
-        terminated(preceded(multispace0, parse_reg_id), multispace0),
+        terminated(
+            preceded(multispace0, alt((parse_vec_reg, parse_reg_id))),
+            multispace0,
+        ),
         separated_list1(tuple((multispace0, char(','), multispace0)), parse_src),
         opt(preceded(multispace0, parse_condition)),
     ))(input)?;
 
     // check dst operand cannot be immediat
-    assert!(dst.chars().next().unwrap() != '$');
+    assert!(!dst.starts_with('$'));
     if let Some((cond_suffix, cond)) = cond {
         Ok((
             input,
             Instruction::Compute(Compute {
                 op: op.to_string(),
                 src: srcs.iter().map(|s| Operand::from_str(s)).collect(),
-                dst: dst.to_string(),
+                dst: Operand::from_str(dst),
                 cond_suffix: Some(cond_suffix),
                 cond: Some(cond.to_string()),
             }),
@@ -216,7 +491,7 @@ fn parse_compute(input: &str) -> IResult<&str, Instruction> {
             Instruction::Compute(Compute {
                 op: op.to_string(),
                 src: srcs.iter().map(|s| Operand::from_str(s)).collect(),
-                dst: dst.to_string(),
+                dst: Operand::from_str(dst),
                 cond_suffix: None,
                 cond: None,
             }),
@@ -226,7 +501,13 @@ fn parse_compute(input: &str) -> IResult<&str, Instruction> {
 
 fn parse_instruction(input: &str) -> IResult<&str, Instruction> {
     terminated(
-        alt((parse_data_load, parse_data_store, parse_compute)),
+        alt((
+            parse_prefetch,
+            parse_sync_barrier,
+            parse_data_load,
+            parse_data_store,
+            parse_compute,
+        )),
         multispace0,
     )(input)
 }
@@ -272,13 +553,10 @@ impl fmt::Display for Instruction {
                 for idx in &data_access.addr {
                     write!(f, "[{}]", idx)?;
                 }
-                if data_access.cond.is_some() {
-                    write!(
-                        f,
-                        " ({} {})",
-                        data_access.cond_suffix.as_ref().unwrap(),
-                        data_access.cond.as_ref().unwrap()
-                    )
+                if let (Some(cond_suffix), Some(cond)) =
+                    (&data_access.cond_suffix, &data_access.cond)
+                {
+                    write!(f, " ({} {})", cond_suffix, cond)
                 } else {
                     Ok(())
                 }
@@ -290,18 +568,44 @@ impl fmt::Display for Instruction {
                 for idx in &data_access.addr {
                     write!(f, "[{}]", idx)?;
                 }
-                if data_access.cond.is_some() {
-                    write!(
-                        f,
-                        " ({} {})",
-                        data_access.cond_suffix.as_ref().unwrap(),
-                        data_access.cond.as_ref().unwrap()
-                    )
+                if let (Some(cond_suffix), Some(cond)) =
+                    (&data_access.cond_suffix, &data_access.cond)
+                {
+                    write!(f, " ({} {})", cond_suffix, cond)
                 } else {
                     Ok(())
                 }
             }
+            Instruction::Prefetch(data_access) => {
+                write!(f, "PREFETCH {}", data_access.array_name)?;
+                for idx in &data_access.addr {
+                    write!(f, "[{}]", idx)?;
+                }
+                if let (Some(cond_suffix), Some(cond)) =
+                    (&data_access.cond_suffix, &data_access.cond)
+                {
+                    write!(f, " ({} {})", cond_suffix, cond)
+                } else {
+                    Ok(())
+                }
+            }
+            Instruction::SyncBarrier(label) => write!(f, "SYNC {}", label),
             Instruction::Compute(compute) => write!(f, "{}", compute),
+            // written on a single line: `for i in (0..4) for j in (0..4) { Ra <= A[i][j]; ... }`
+            Instruction::LoopBody(nest) => {
+                for iter in &nest.iters {
+                    write!(f, "{} ", iter)?;
+                }
+                write!(f, "{{")?;
+                let mut body_iter = nest.body.iter();
+                if let Some(first_inst) = body_iter.next() {
+                    write!(f, " {}", first_inst)?;
+                    for inst in body_iter {
+                        write!(f, "; {}", inst)?;
+                    }
+                }
+                write!(f, " }}")
+            }
         }
     }
 }
@@ -310,7 +614,10 @@ impl fmt::Display for Operand {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Operand::Reg(reg) => write!(f, "{}", reg),
-            Operand::Imm(imm) => write!(f, "${}", imm),
+            Operand::ImmInt(imm) => write!(f, "${}", imm),
+            // `{:?}` keeps the decimal point of integral values, e.g. `$1.0`
+            Operand::ImmFloat(imm) => write!(f, "${:?}", imm),
+            Operand::VecReg(reg, width) => write!(f, "{}:{}", reg, width),
         }
     }
 }
@@ -325,13 +632,8 @@ impl fmt::Display for Compute {
                 write!(f, ", {}", src)?;
             }
         }
-        if self.cond.is_some() {
-            write!(
-                f,
-                " ({} {})",
-                self.cond_suffix.as_ref().unwrap(),
-                self.cond.as_ref().unwrap()
-            )
+        if let (Some(cond_suffix), Some(cond)) = (&self.cond_suffix, &self.cond) {
+            write!(f, " ({} {})", cond_suffix, cond)
         } else {
             Ok(())
         }
@@ -347,22 +649,68 @@ impl fmt::Display for ConditionSuffix {
             ConditionSuffix::LE => write!(f, "LE"),
             ConditionSuffix::GT => write!(f, "GT"),
             ConditionSuffix::GE => write!(f, "GE"),
+            ConditionSuffix::UEQ => write!(f, "UEQ"),
+            ConditionSuffix::UNE => write!(f, "UNE"),
+            ConditionSuffix::ULT => write!(f, "ULT"),
+            ConditionSuffix::ULE => write!(f, "ULE"),
+            ConditionSuffix::UGT => write!(f, "UGT"),
+            ConditionSuffix::UGE => write!(f, "UGE"),
+            ConditionSuffix::ORD => write!(f, "ORD"),
+            ConditionSuffix::UNORD => write!(f, "UNORD"),
         }
     }
 }
 
 // Serializers/Deserializers
+
+/// The instruction in the binary formats, which cannot tell a string from a map
+#[derive(serde_derive::Deserialize)]
+enum BinaryInstruction {
+    Text(String),
+    LoopBody(LoopNest),
+}
+
 impl<'de> Deserialize<'de> for Instruction {
     fn deserialize<D>(deserializer: D) -> Result<Instruction, D::Error>
     where
         D: Deserializer<'de>,
     {
-        // Deserialize the input as a string
-        let s = String::deserialize(deserializer)?;
+        if !deserializer.is_human_readable() {
+            return match BinaryInstruction::deserialize(deserializer)? {
+                BinaryInstruction::Text(s) => InstructionVisitor.visit_str(&s),
+                BinaryInstruction::LoopBody(nest) => Ok(Instruction::LoopBody(Box::new(nest))),
+            };
+        }
+        // An instruction is a string, a nested loop nest is a map of its iters and body
+        deserializer.deserialize_any(InstructionVisitor)
+    }
+}
+
+struct InstructionVisitor;
+
+impl<'de> Visitor<'de> for InstructionVisitor {
+    type Value = Instruction;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an instruction or a nested loop nest")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Instruction, E>
+    where
+        E: de::Error,
+    {
         // Parse the string into an Instruction
-        parse_instruction(&s)
+        all_consuming(parse_instruction)(s)
             .map(|(_, instr)| instr)
-            .map_err(|e| serde::de::Error::custom(format!("{:?}", e)))
+            .map_err(|e| E::custom(ParseError::from_nom(s, e)))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Instruction, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        LoopNest::deserialize(MapAccessDeserializer::new(map))
+            .map(|nest| Instruction::LoopBody(Box::new(nest)))
     }
 }
 
@@ -371,16 +719,30 @@ impl Serialize for Instruction {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        if !serializer.is_human_readable() {
+            // same encoding as `BinaryInstruction`, without copying the nested loop nest
+            return match self {
+                Instruction::LoopBody(nest) => serializer.serialize_newtype_variant(
+                    "BinaryInstruction",
+                    1,
+                    "LoopBody",
+                    nest.as_ref(),
+                ),
+                _ => serializer.serialize_newtype_variant(
+                    "BinaryInstruction",
+                    0,
+                    "Text",
+                    &self.to_string(),
+                ),
+            };
+        }
+        match self {
+            Instruction::LoopBody(nest) => nest.serialize(serializer),
+            _ => serializer.serialize_str(&self.to_string()),
+        }
     }
 }
 
-// impl PropertyHook for Instruction {
-//     fn property_hook_id(&self) -> String {
-//         format!("Instruction: {}", self.to_string())
-//     }
-// }
-
 // from_str of the simple structs
 impl ConditionSuffix {
     fn from_str(s: &str) -> ConditionSuffix {
@@ -391,15 +753,37 @@ impl ConditionSuffix {
             "LE" => ConditionSuffix::LE,
             "GT" => ConditionSuffix::GT,
             "GE" => ConditionSuffix::GE,
+            "UEQ" => ConditionSuffix::UEQ,
+            "UNE" => ConditionSuffix::UNE,
+            "ULT" => ConditionSuffix::ULT,
+            "ULE" => ConditionSuffix::ULE,
+            "UGT" => ConditionSuffix::UGT,
+            "UGE" => ConditionSuffix::UGE,
+            "ORD" => ConditionSuffix::ORD,
+            "UNORD" => ConditionSuffix::UNORD,
             _ => panic!("Invalid condition suffix"),
         }
     }
 }
 
 impl Operand {
+    /// The name of the register or of the vector register, `None` for an immediate
+    pub fn register(&self) -> Option<&Register> {
+        match self {
+            Operand::Reg(reg) | Operand::VecReg(reg, _) => Some(reg),
+            Operand::ImmInt(_) | Operand::ImmFloat(_) => None,
+        }
+    }
+
     fn from_str(s: &str) -> Operand {
-        if s.chars().next().unwrap() == '$' {
-            Operand::Imm(s[1..].parse().unwrap())
+        if let Some(imm) = s.strip_prefix('$') {
+            if imm.contains('.') {
+                Operand::ImmFloat(imm.parse().unwrap())
+            } else {
+                Operand::ImmInt(imm.parse().unwrap())
+            }
+        } else if let Some((reg, width)) = s.split_once(':') {
+            Operand::VecReg(reg.to_string(), width.parse().unwrap())
         } else {
             Operand::Reg(s.to_string())
         }
@@ -444,17 +828,206 @@ mod tests {
             })
         );
 
+        let instr = "R1 <= A[2 * x + 1][y % 4]";
+        let (_, instr) = parse_instruction(instr).unwrap();
+        assert_eq!(instr.to_string(), "R1 <= A[2 * x + 1][y % 4]");
+
         let instr = "cmp Rcmp Ra, $0";
         let (_, instr) = parse_compute(instr).unwrap();
         assert_eq!(
             instr,
             Instruction::Compute(Compute {
                 op: "cmp".to_string(),
-                src: vec![Operand::Reg("Ra".to_string()), Operand::Imm(0)],
-                dst: "Rcmp".to_string(),
+                src: vec![Operand::Reg("Ra".to_string()), Operand::ImmInt(0)],
+                dst: Operand::Reg("Rcmp".to_string()),
                 cond_suffix: None,
                 cond: None
             })
         );
     }
+
+    #[test]
+    fn test_parse_malformed_indices() {
+        // the parsed prefix of an index is not kept
+        for instr in [
+            "Ra <= A[i j]",
+            "Ra <= A[i +]",
+            "Rc => C[i *]",
+            "PREFETCH A[i i]",
+        ] {
+            let error = serde_yaml::from_str::<Instruction>(instr).unwrap_err();
+            assert!(error.to_string().contains("Cannot parse"), "{}", error);
+        }
+        // no panic on an index which is not an expression
+        for instr in ["Ra <= A[)]", "Ra <= A[@]", "Ra <= A[i][]"] {
+            assert!(
+                serde_yaml::from_str::<Instruction>(instr).is_err(),
+                "{}",
+                instr
+            );
+        }
+        let instr: Instruction = serde_yaml::from_str("Ra <= A[ i + 1 ][j]").unwrap();
+        assert_eq!(instr.to_string(), "Ra <= A[i + 1][j]");
+    }
+
+    #[test]
+    fn test_stride_in_dim() {
+        let access = |input: &str| match parse_instruction(input).unwrap().1 {
+            Instruction::DataLoad(access) => access,
+            _ => unreachable!(),
+        };
+        let a = access("Ra <= A[m][2 * k + 1 + m]");
+        assert_eq!(a.stride_in_dim("k"), Some(2));
+        assert_eq!(a.stride_in_dim("n"), Some(0));
+        // used in two indices
+        assert_eq!(a.stride_in_dim("m"), None);
+        assert_eq!(access("Ra <= A[k / 2]").stride_in_dim("k"), None);
+    }
+
+    #[test]
+    fn test_accessed_arrays_and_dims() {
+        let (_, instr) = parse_instruction("Ra <= A[0][m][N - 1][k - k]").unwrap();
+        match instr {
+            Instruction::DataLoad(access) => assert_eq!(
+                access.accessed_arrays_and_dims(),
+                vec![("A".to_string(), 1), ("A".to_string(), 2)]
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_accessed_arrays() {
+        let inst = |input: &str| parse_instruction(input).unwrap().1;
+        assert_eq!(inst("R1 <= A[x] (EQ Rcmp)").accessed_arrays(), vec!["A"]);
+        assert!(inst("R1 <= A[x] (EQ Rcmp)").is_conditional());
+        assert_eq!(inst("R1 => B[x]").accessed_arrays(), vec!["B"]);
+        assert!(!inst("R1 => B[x]").is_conditional());
+        assert!(inst("add R2 R1, $1").accessed_arrays().is_empty());
+        assert!(inst("add R2 R1, $1 (NE Rcmp)").is_conditional());
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let (_, instr) = parse_instruction("PREFETCH A[x + 1][y] (LT Rcmp)").unwrap();
+        assert_eq!(
+            instr,
+            Instruction::Prefetch(DataAccess {
+                array_name: "A".to_string(),
+                addr: vec![
+                    affine_expr::parse_expr("x + 1").unwrap().1,
+                    AffineExpr::Var("y".to_string())
+                ],
+                reg: String::new(),
+                cond_suffix: Some(ConditionSuffix::LT),
+                cond: Some("Rcmp".to_string())
+            })
+        );
+        assert_eq!(instr.to_string(), "PREFETCH A[x + 1][y] (LT Rcmp)");
+        assert_eq!(instr.accessed_arrays(), vec!["A"]);
+    }
+
+    #[test]
+    fn test_sync_barrier() {
+        let (_, instr) = parse_instruction("SYNC stage1").unwrap();
+        assert_eq!(instr, Instruction::SyncBarrier("stage1".to_string()));
+        assert_eq!(instr.to_string(), "SYNC stage1");
+        assert!(instr.accessed_arrays().is_empty());
+    }
+
+    #[test]
+    fn test_op_class() {
+        let compute = |input: &str| match parse_compute(input).unwrap().1 {
+            Instruction::Compute(compute) => compute,
+            _ => unreachable!(),
+        };
+        let mac = compute("mac Rc Ra, Rb, Rc");
+        assert_eq!(mac.op_class(), OpClass::MAC);
+        assert!(mac.is_fma());
+        assert!(mac.is_reduction_candidate());
+        let add = compute("add Rd Rc, $1");
+        assert_eq!(add.op_class(), OpClass::ADD);
+        assert!(!add.is_fma());
+        assert!(!add.is_reduction_candidate());
+        assert_eq!(compute("max Rd Rc, Ra").op_class(), OpClass::OTHER);
+    }
+
+    #[test]
+    fn test_vector_registers() {
+        let (_, instr) = parse_compute("mac V2:8 V0:8, V1:8, V2:8").unwrap();
+        assert_eq!(instr.to_string(), "mac V2:8 V0:8, V1:8, V2:8");
+        let compute = match instr {
+            Instruction::Compute(compute) => compute,
+            _ => unreachable!(),
+        };
+        assert_eq!(compute.src[0], Operand::VecReg("V0".to_string(), 8));
+        assert_eq!(compute.vector_width(), Some(8));
+        assert!(compute.is_reduction_candidate());
+
+        let (_, instr) = parse_compute("add Rb V0:4, $1").unwrap();
+        match instr {
+            Instruction::Compute(compute) => assert_eq!(compute.vector_width(), Some(4)),
+            _ => unreachable!(),
+        }
+        let (_, instr) = parse_compute("add Rb Ra, $1").unwrap();
+        match instr {
+            Instruction::Compute(compute) => assert_eq!(compute.vector_width(), None),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_float_condition_suffixes() {
+        for input in [
+            "R1 <= A[x] (UEQ Rcmp)",
+            "R1 => A[x] (UNE Rcmp)",
+            "add R2 R1, $1 (ULT Rcmp)",
+            "add R2 R1, $1 (ULE Rcmp)",
+            "PREFETCH A[x] (UGT Rcmp)",
+            "R1 <= A[x] (UGE Rcmp)",
+            "R1 <= A[x] (ORD Rcmp)",
+            "R1 <= A[x] (UNORD Rcmp)",
+        ] {
+            let (rest, instr) = parse_instruction(input).unwrap();
+            assert!(rest.is_empty(), "{}", input);
+            assert_eq!(instr.to_string(), input);
+        }
+        let (_, instr) = parse_instruction("R1 <= A[x] (UNORD Rcmp)").unwrap();
+        match instr {
+            Instruction::DataLoad(access) => {
+                assert_eq!(access.cond_suffix, Some(ConditionSuffix::UNORD))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_float_immediates() {
+        let (_, instr) = parse_compute("mul Rb Ra, $0.5, $1.0, $2").unwrap();
+        assert_eq!(instr.to_string(), "mul Rb Ra, $0.5, $1.0, $2");
+        match instr {
+            Instruction::Compute(compute) => assert_eq!(
+                compute.src[1..],
+                [
+                    Operand::ImmFloat(0.5),
+                    Operand::ImmFloat(1.0),
+                    Operand::ImmInt(2)
+                ]
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let inst = |input: &str| parse_instruction(input).unwrap().1;
+        assert_eq!(
+            inst("R1 <= A[1 + x][2 * y + x]").normalize().to_string(),
+            inst("R1 <= A[x + 1][x + 2 * y]").normalize().to_string()
+        );
+        assert_eq!(
+            inst("R1 => B[x - x + 3]").normalize().to_string(),
+            "R1 => B[3]"
+        );
+    }
 }
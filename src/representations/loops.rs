@@ -1,17 +1,23 @@
-use crate::passes::property::PropertyHook;
+use crate::error::ParseError;
+use crate::passes::property::{PropertyHook, PropertyManager};
 use core::fmt;
 use nom::{
     bytes::complete::tag,
-    character::complete::{alpha1, digit1, multispace0, multispace1},
+    character::complete::{alpha1, multispace0, multispace1},
     combinator::{map, opt},
     sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
 use property_hood_id_derive::PropertyHook;
 
+use super::affine_expr::{parse_coeff, parse_expr, AffineExpr, Coeff};
 use super::instruction::Instruction;
+use super::mapping::Mapping;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct LoopNest {
@@ -19,33 +25,497 @@ pub struct LoopNest {
     pub body: Vec<Instruction>,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, PropertyHook)]
+/// Identified by its iterators, the body is not part of the id
+impl PropertyHook for LoopNest {
+    fn property_hook_id(&self) -> String {
+        let iters: Vec<String> = self.iters.iter().map(|iter| iter.to_string()).collect();
+        format!("LoopNest::{}", iters.join("; "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PropertyHook)]
 pub struct LoopIter {
     pub iter_name: String,
-    pub bounds: (i32, i32),
-    pub step: i32,
+    /// `(lower, upper)`, the upper bound is excluded.
+    /// The variables of the bounds are symbolic parameters, e.g. `(0..M)`.
+    pub bounds: (AffineExpr, AffineExpr),
+    /// Either a constant, e.g. `.step(2)`, or a symbolic parameter, e.g. `.step(SIMD_W)`.
+    pub step: Coeff,
 }
 
-fn parse_identifier(input: &str) -> IResult<&str, String> {
-    map(alpha1, String::from)(input)
+impl LoopNest {
+    /// Parse a loop nest written in JSON
+    pub fn from_json_str(input: &str) -> Result<LoopNest, String> {
+        serde_json::from_str(input).map_err(|e| e.to_string())
+    }
+
+    /// Write the loop nest in JSON
+    pub fn to_json_str(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Encode the loop nest with bincode, faster to read back than YAML
+    pub fn to_binary(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("A loop nest can always be encoded")
+    }
+
+    /// Decode a loop nest encoded by `to_binary`
+    pub fn from_binary(bytes: &[u8]) -> Result<LoopNest, String> {
+        bincode::deserialize(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Read a loop nest from a YAML `.loop` file
+    pub fn from_loop_file(path: &Path) -> Result<LoopNest, String> {
+        let yaml_str = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read the loop nest {}: {}", path.display(), e))?;
+        serde_yaml::from_str(&yaml_str)
+            .map_err(|e| format!("Cannot parse the loop nest {}: {}", path.display(), e))
+    }
+
+    /// Write the loop nest to a YAML `.loop` file
+    pub fn to_loop_file(&self, path: &Path) -> Result<(), String> {
+        let yaml_str = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, yaml_str)
+            .map_err(|e| format!("Cannot write the loop nest {}: {}", path.display(), e))
+    }
+
+    /// Read a loop nest from a YAML file, whatever its extension, see `from_loop_file`
+    pub fn from_yaml_file(path: &Path) -> Result<LoopNest, String> {
+        LoopNest::from_loop_file(path)
+    }
+
+    /// Write the loop nest to a YAML file, see `to_loop_file`
+    pub fn to_yaml_file(&self, path: &Path) -> Result<(), String> {
+        self.to_loop_file(path)
+    }
+
+    /// Get the iterator named `name`
+    pub fn get_iter(&self, name: &str) -> Option<&LoopIter> {
+        self.iters.iter().find(|iter| iter.iter_name == name)
+    }
+
+    /// Get a mutable reference to the iterator named `name`
+    pub fn get_iter_mut(&mut self, name: &str) -> Option<&mut LoopIter> {
+        self.iters.iter_mut().find(|iter| iter.iter_name == name)
+    }
+
+    /// The loop nests nested in the body
+    pub fn sub_nests(&self) -> impl Iterator<Item = &LoopNest> {
+        self.body.iter().filter_map(|inst| match inst {
+            Instruction::LoopBody(nest) => Some(nest.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// The iterators of the loop nest and of the nested loop nests, in program order
+    pub fn all_iters(&self) -> Vec<&LoopIter> {
+        let mut iters: Vec<&LoopIter> = self.iters.iter().collect();
+        for nest in self.sub_nests() {
+            iters.extend(nest.all_iters());
+        }
+        iters
+    }
+
+    /// The instructions of the body and of the nested loop nests in program order, each with
+    /// its enclosing iterators from the outermost to the innermost.
+    /// The nested loop nests themselves are not returned, only their instructions.
+    pub fn scoped_instructions(&self) -> Vec<(Vec<&LoopIter>, &Instruction)> {
+        let mut instructions = Vec::new();
+        self.collect_instructions(&[], &mut instructions);
+        instructions
+    }
+
+    fn collect_instructions<'a>(
+        &'a self,
+        enclosing: &[&'a LoopIter],
+        instructions: &mut Vec<(Vec<&'a LoopIter>, &'a Instruction)>,
+    ) {
+        let mut iters = enclosing.to_vec();
+        iters.extend(self.iters.iter());
+        for inst in &self.body {
+            match inst {
+                Instruction::LoopBody(nest) => nest.collect_instructions(&iters, instructions),
+                _ => instructions.push((iters.clone(), inst)),
+            }
+        }
+    }
+
+    /// The instructions of the body and of the nested loop nests, in program order
+    pub fn all_instructions(&self) -> Vec<&Instruction> {
+        self.scoped_instructions()
+            .into_iter()
+            .map(|(_, inst)| inst)
+            .collect()
+    }
+
+    /// Number of executions of the loop body, i.e. the product of the trip counts of the iterators.
+    /// The iterators of the nested loop nests are not included.
+    /// `params` gives the values of the symbolic parameters of the bounds.
+    /// Returns `None` if a trip count cannot be computed (e.g. non positive step).
+    pub fn total_iteration_count(&self, params: &HashMap<String, i32>) -> Option<i64> {
+        iteration_count(self.iters.iter(), params)
+    }
+
+    /// Number of `(loads, stores, computes)` in the loop body, including the nested loop nests
+    pub fn count_memory_operations(&self) -> (usize, usize, usize) {
+        self.all_instructions()
+            .into_iter()
+            .fold((0, 0, 0), |(loads, stores, computes), inst| match inst {
+                Instruction::DataLoad(_) => (loads + 1, stores, computes),
+                Instruction::DataStore(_) => (loads, stores + 1, computes),
+                Instruction::Compute(_) => (loads, stores, computes + 1),
+                _ => (loads, stores, computes),
+            })
+    }
+
+    /// Split off the first iteration of the outermost iterator, e.g. to initialize an accumulator.
+    /// Returns `(prologue, remainder)`: the prologue is the body of the outermost iterator with the
+    /// iterator replaced by its lower bound, and the remainder runs the outermost iterator from the
+    /// lower bound plus the step to the upper bound.
+    /// The outermost iterator is assumed to run at least once.
+    /// Returns `None` if the loop nest has no iterator.
+    pub fn peel_first_iteration(&self) -> Option<(LoopNest, LoopNest)> {
+        let outer = self.iters.first()?;
+        let first = outer.bounds.0.clone();
+        let split = AffineExpr::Add(
+            Box::new(first.clone()),
+            Box::new(AffineExpr::Mul(
+                outer.step.clone(),
+                Box::new(AffineExpr::Const(1)),
+            )),
+        )
+        .simplify();
+        let mut remainder = self.clone();
+        remainder.iters[0].bounds.0 = split;
+        Some((self.outer_iteration(&first), remainder))
+    }
+
+    /// Split off the last iteration of the outermost iterator.
+    /// Returns `(remainder, epilogue)`, see `peel_first_iteration`.
+    pub fn peel_last_iteration(&self) -> Option<(LoopNest, LoopNest)> {
+        let outer = self.iters.first()?;
+        let (lower, upper) = &outer.bounds;
+        // lower + (ceildiv(upper - lower, step) - 1) * step
+        let trip_count = AffineExpr::CeilDiv(
+            Box::new(AffineExpr::Sub(
+                Box::new(upper.clone()),
+                Box::new(lower.clone()),
+            )),
+            outer.step.clone(),
+        );
+        let last = AffineExpr::Add(
+            Box::new(lower.clone()),
+            Box::new(AffineExpr::Mul(
+                outer.step.clone(),
+                Box::new(AffineExpr::Sub(
+                    Box::new(trip_count),
+                    Box::new(AffineExpr::Const(1)),
+                )),
+            )),
+        )
+        .simplify();
+        let mut remainder = self.clone();
+        remainder.iters[0].bounds.1 = last.clone();
+        Some((remainder, self.outer_iteration(&last)))
+    }
+
+    /// The loop nest without its outermost iterator, replaced by `value` in the inner bounds and
+    /// in the body
+    fn outer_iteration(&self, value: &AffineExpr) -> LoopNest {
+        let inner = Instruction::LoopBody(Box::new(LoopNest {
+            iters: self.iters[1..].to_vec(),
+            body: self.body.clone(),
+        }));
+        match inner.substitute(&self.iters[0].iter_name, value) {
+            Instruction::LoopBody(nest) => *nest,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The loads and stores of `array`, including the ones of the nested loop nests
+    pub fn instructions_accessing_array<'a>(&'a self, array: &str) -> Vec<&'a Instruction> {
+        self.all_instructions()
+            .into_iter()
+            .filter(|inst| match inst {
+                Instruction::DataLoad(access) | Instruction::DataStore(access) => {
+                    access.array_name == array
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// The instructions reading or writing the register `reg`, including its use as a condition
+    pub fn instructions_using_register<'a>(&'a self, reg: &str) -> Vec<&'a Instruction> {
+        self.all_instructions()
+            .into_iter()
+            .filter(|inst| match inst {
+                Instruction::DataLoad(access) | Instruction::DataStore(access) => {
+                    access.reg == reg || access.cond.as_deref() == Some(reg)
+                }
+                Instruction::Compute(compute) => {
+                    compute.cond.as_deref() == Some(reg)
+                        || std::iter::once(&compute.dst)
+                            .chain(&compute.src)
+                            .any(|operand| operand.register().is_some_and(|name| name == reg))
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// `(is_read, is_written)` for each array loaded or stored in the loop body,
+    /// including the nested loop nests. Prefetches are hints and are not reads.
+    pub fn all_arrays_accessed(&self) -> HashMap<String, (bool, bool)> {
+        let mut arrays: HashMap<String, (bool, bool)> = HashMap::new();
+        for inst in self.all_instructions() {
+            match inst {
+                Instruction::DataLoad(access) => {
+                    arrays.entry(access.array_name.clone()).or_default().0 = true
+                }
+                Instruction::DataStore(access) => {
+                    arrays.entry(access.array_name.clone()).or_default().1 = true
+                }
+                _ => {}
+            }
+        }
+        arrays
+    }
+
+    /// The arrays read but never written, sorted by name
+    pub fn live_in_arrays(&self) -> Vec<String> {
+        let mut arrays: Vec<String> = self
+            .all_arrays_accessed()
+            .into_iter()
+            .filter(|(_, (_, is_written))| !is_written)
+            .map(|(array, _)| array)
+            .collect();
+        arrays.sort();
+        arrays
+    }
+
+    /// The arrays written, sorted by name
+    pub fn live_out_arrays(&self) -> Vec<String> {
+        let mut arrays: Vec<String> = self
+            .all_arrays_accessed()
+            .into_iter()
+            .filter(|(_, (_, is_written))| *is_written)
+            .map(|(array, _)| array)
+            .collect();
+        arrays.sort();
+        arrays
+    }
+
+    /// Number of compute instructions in the loop body, including the nested loop nests,
+    /// see `count_memory_operations`
+    pub fn count_compute_operations(&self) -> usize {
+        self.count_memory_operations().2
+    }
+
+    /// Compute operations per memory operation over the whole execution of the loop nest,
+    /// the operational intensity of the roofline model.
+    /// Returns `None` if there is no memory operation or if the iteration count is unknown.
+    pub fn arithmetic_intensity(&self, params: &HashMap<String, i32>) -> Option<f64> {
+        let (mut compute_ops, mut memory_ops) = (0i64, 0i64);
+        for (iters, inst) in self.scoped_instructions() {
+            let executions = iteration_count(iters.into_iter(), params)?;
+            match inst {
+                Instruction::DataLoad(_) | Instruction::DataStore(_) => memory_ops += executions,
+                Instruction::Compute(_) => compute_ops += executions,
+                Instruction::Prefetch(_)
+                | Instruction::SyncBarrier(_)
+                | Instruction::LoopBody(_) => {}
+            }
+        }
+        if memory_ops == 0 {
+            return None;
+        }
+        Some(compute_ops as f64 / memory_ops as f64)
+    }
+
+    /// Check the structural invariants of the loop nest and of the nested loop nests:
+    /// at least one iterator, unique iterator names, non-empty ranges with positive steps,
+    /// and index expressions only referencing the enclosing iterators.
+    /// Returns all the violations found.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        self.validate_in_scope(&[], &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// `enclosing` are the iterator names of the enclosing loop nests
+    fn validate_in_scope(&self, enclosing: &[&str], errors: &mut Vec<String>) {
+        if self.iters.is_empty() {
+            errors.push("The loop nest has no iterator".to_string());
+        }
+        for (idx, iter) in self.iters.iter().enumerate() {
+            if self.iters[..idx]
+                .iter()
+                .any(|other| other.iter_name == iter.iter_name)
+            {
+                errors.push(format!("Duplicate iterator {}", iter.iter_name));
+            }
+            if enclosing.contains(&iter.iter_name.as_str()) {
+                errors.push(format!(
+                    "The iterator {} shadows the iterator of an enclosing loop",
+                    iter.iter_name
+                ));
+            }
+            // symbolic bounds cannot be compared
+            if let Some((lower, upper)) = iter.concrete_bounds(&HashMap::new()) {
+                if lower > upper {
+                    errors.push(format!(
+                        "The lower bound {} of the iterator {} is greater than its upper bound {}",
+                        lower, iter.iter_name, upper
+                    ));
+                }
+            }
+            // symbolic steps cannot be checked
+            if matches!(iter.concrete_step(&HashMap::new()), Some(step) if step <= 0) {
+                errors.push(format!(
+                    "The step {} of the iterator {} is not positive",
+                    iter.step, iter.iter_name
+                ));
+            }
+        }
+        let mut iter_names = enclosing.to_vec();
+        iter_names.extend(self.iter_names());
+        for inst in &self.body {
+            match inst {
+                Instruction::DataLoad(access)
+                | Instruction::DataStore(access)
+                | Instruction::Prefetch(access) => {
+                    for var in access.addr.iter().flat_map(|expr| expr.vars()) {
+                        if !iter_names.contains(&var.as_str()) {
+                            errors.push(format!(
+                                "The instruction {} references the undefined iterator {}",
+                                inst, var
+                            ));
+                        }
+                    }
+                }
+                Instruction::LoopBody(nest) => nest.validate_in_scope(&iter_names, errors),
+                Instruction::Compute(_) | Instruction::SyncBarrier(_) => {}
+            }
+        }
+    }
+
+    /// Names of the iterators, from the outermost to the innermost
+    pub fn iter_names(&self) -> Vec<&str> {
+        self.iters
+            .iter()
+            .map(|iter| iter.iter_name.as_str())
+            .collect()
+    }
+
+    /// C-style pseudocode of the loop nest, for debug output only (it is not parsed back).
+    /// If a mapping is given, the mapping type of each iterator is written as a comment.
+    pub fn to_pseudocode(&self, mapping: Option<&Mapping>) -> String {
+        let mut lines = Vec::new();
+        self.collect_pseudocode(0, mapping, &mut lines);
+        lines.join("\n")
+    }
+
+    fn collect_pseudocode(&self, depth: usize, mapping: Option<&Mapping>, lines: &mut Vec<String>) {
+        let indent = |depth: usize| "    ".repeat(depth);
+        for (idx, iter) in self.iters.iter().enumerate() {
+            let annotation = mapping
+                .and_then(|mapping| mapping.types.get(&iter.iter_name))
+                .map(|mapping_type| format!(" // {}", mapping_type))
+                .unwrap_or_default();
+            lines.push(format!(
+                "{}{} {{{}",
+                indent(depth + idx),
+                iter.to_pseudocode(),
+                annotation
+            ));
+        }
+        let body_depth = depth + self.iters.len();
+        for inst in &self.body {
+            match inst {
+                Instruction::LoopBody(nest) => nest.collect_pseudocode(body_depth, mapping, lines),
+                _ => lines.push(format!("{}{};", indent(body_depth), inst)),
+            }
+        }
+        for idx in (0..self.iters.len()).rev() {
+            lines.push(format!("{}}}", indent(depth + idx)));
+        }
+    }
 }
 
-fn parse_number(input: &str) -> IResult<&str, i32> {
-    map(digit1, |s: &str| s.parse().unwrap())(input)
+impl LoopIter {
+    /// Values of the bounds for the given symbolic parameters,
+    /// `None` if a parameter is missing
+    pub fn concrete_bounds(&self, params: &HashMap<String, i32>) -> Option<(i32, i32)> {
+        let lower = self.bounds.0.evaluate(params, params).ok()?;
+        let upper = self.bounds.1.evaluate(params, params).ok()?;
+        Some((lower, upper))
+    }
+
+    /// Number of iterations of the loop for the given symbolic parameters,
+    /// `None` if the bounds are unknown or if the step is not positive
+    pub fn trip_count(&self, params: &HashMap<String, i32>) -> Option<i64> {
+        let step = self.concrete_step(params)? as i64;
+        if step <= 0 {
+            return None;
+        }
+        let (lower, upper) = self.concrete_bounds(params)?;
+        let range = (upper as i64 - lower as i64).max(0);
+        Some((range + step - 1) / step)
+    }
+
+    /// Evaluate the step for the given symbolic parameters, `None` if a parameter is missing
+    pub fn concrete_step(&self, params: &HashMap<String, i32>) -> Option<i32> {
+        self.step.evaluate(params).ok()
+    }
+
+    /// C-style loop header, e.g. `for (int i = 0; i < N; i += 2)`
+    pub fn to_pseudocode(&self) -> String {
+        let name = &self.iter_name;
+        match &self.step {
+            Coeff::Add(_, _) => format!(
+                "for (int {} = {}; {} < {}; {} += ({}))",
+                name, self.bounds.0, name, self.bounds.1, name, self.step
+            ),
+            step => format!(
+                "for (int {} = {}; {} < {}; {} += {})",
+                name, self.bounds.0, name, self.bounds.1, name, step
+            ),
+        }
+    }
+}
+
+/// Product of the trip counts of the iterators, `None` if one of them is unknown
+/// or if the product overflows
+pub fn iteration_count<'a>(
+    mut iters: impl Iterator<Item = &'a LoopIter>,
+    params: &HashMap<String, i32>,
+) -> Option<i64> {
+    iters.try_fold(1i64, |total, iter| {
+        total.checked_mul(iter.trip_count(params)?)
+    })
+}
+
+fn parse_identifier(input: &str) -> IResult<&str, String> {
+    map(alpha1, String::from)(input)
 }
 
-fn parse_range(input: &str) -> IResult<&str, (i32, i32)> {
+fn parse_range(input: &str) -> IResult<&str, (AffineExpr, AffineExpr)> {
     delimited(
         tag("("),
-        tuple((parse_number, preceded(tag(".."), parse_number))),
+        tuple((parse_expr, preceded(tag(".."), parse_expr))),
         tag(")"),
     )(input)
 }
 
-fn parse_step(input: &str) -> IResult<&str, i32> {
+fn parse_step(input: &str) -> IResult<&str, Coeff> {
     preceded(
         tuple((tag("."), tag("step"), tag("("))),
-        terminated(parse_number, tag(")")),
+        terminated(parse_coeff, tag(")")),
     )(input)
 }
 
@@ -61,7 +531,7 @@ fn parse_loop_iter(input: &str) -> IResult<&str, LoopIter> {
         LoopIter {
             iter_name: name,
             bounds: (start, end),
-            step: if let Some(step) = step { step } else { 1 },
+            step: step.unwrap_or(Coeff::Const(1)),
         },
     ))
 }
@@ -76,7 +546,7 @@ impl<'de> Deserialize<'de> for LoopIter {
         // Parse the string into an AffineExpr
         parse_loop_iter(&s)
             .map(|(_, expr)| expr)
-            .map_err(|e| serde::de::Error::custom(format!("{:?}", e)))
+            .map_err(|e| serde::de::Error::custom(ParseError::from_nom(&s, e)))
     }
 }
 
@@ -91,7 +561,7 @@ impl Serialize for LoopIter {
 
 impl fmt::Display for LoopIter {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.step != 1 {
+        if !self.step.is_one() {
             write!(
                 f,
                 "for {} in ({}..{}).step({})",
@@ -107,10 +577,75 @@ impl fmt::Display for LoopIter {
     }
 }
 
+impl LoopNest {
+    /// Display the loop nest with the properties attached to its iterators and instructions
+    pub fn display_with_properties<'a>(
+        &'a self,
+        properties: &'a PropertyManager,
+    ) -> DisplayWithProperties<'a> {
+        DisplayWithProperties {
+            loop_nest: self,
+            properties,
+        }
+    }
+
+    /// Write one line per iterator and per instruction, indented by nesting depth,
+    /// each followed by its properties if given
+    fn fmt_indented(
+        &self,
+        f: &mut fmt::Formatter,
+        depth: usize,
+        properties: Option<&PropertyManager>,
+    ) -> fmt::Result {
+        for (idx, iter) in self.iters.iter().enumerate() {
+            writeln!(f, "{}{}", "  ".repeat(depth + idx), iter)?;
+            fmt_properties(f, depth + idx, properties, iter)?;
+        }
+        let depth = depth + self.iters.len();
+        for inst in &self.body {
+            match inst {
+                Instruction::LoopBody(nest) => nest.fmt_indented(f, depth, properties)?,
+                _ => {
+                    writeln!(f, "{}{}", "  ".repeat(depth), inst)?;
+                    fmt_properties(f, depth, properties, inst)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the properties attached to the hook, below the hook written at `depth`
+fn fmt_properties(
+    f: &mut fmt::Formatter,
+    depth: usize,
+    properties: Option<&PropertyManager>,
+    hook: impl PropertyHook,
+) -> fmt::Result {
+    let hook_properties = properties.and_then(|properties| properties.get_properties_by_hook(hook));
+    for property in hook_properties.into_iter().flatten() {
+        writeln!(f, "{}> {}", "  ".repeat(depth + 1), property)?;
+    }
+    Ok(())
+}
+
+/// The loop nest with the properties of its iterators and instructions,
+/// see `LoopNest::display_with_properties`
+pub struct DisplayWithProperties<'a> {
+    loop_nest: &'a LoopNest,
+    properties: &'a PropertyManager,
+}
+
+impl fmt::Display for DisplayWithProperties<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.loop_nest.fmt_indented(f, 0, Some(self.properties))
+    }
+}
+
+/// One line per iterator and per instruction, the loops being indented inside each other
 impl fmt::Display for LoopNest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let serialized = serde_yaml::to_string(self).unwrap();
-        write!(f, "{}", serialized)
+        self.fmt_indented(f, 0, None)
     }
 }
 
@@ -127,9 +662,18 @@ mod tests {
         let manifest_dir = env!("CARGO_MANIFEST_DIR");
         // Construct the file path to loopprob.yaml
         let file_path = Path::new(manifest_dir).join("example/prob.loop");
-        let yaml_str = fs::read_to_string(file_path).expect("Failed to read YAML file");
-        let _loop_prob: LoopNest =
-            serde_yaml::from_str(&yaml_str).expect("Failed to deserialize YAML");
+        let loop_prob = LoopNest::from_loop_file(&file_path).unwrap();
+
+        // write -> read round-trip
+        let out_path = std::env::temp_dir().join("loopana_test_prob.loop");
+        loop_prob.to_loop_file(&out_path).unwrap();
+        assert_eq!(LoopNest::from_loop_file(&out_path).unwrap(), loop_prob);
+        fs::remove_file(&out_path).unwrap();
+
+        let missing = Path::new(manifest_dir).join("example/missing.loop");
+        assert!(LoopNest::from_loop_file(&missing)
+            .unwrap_err()
+            .starts_with("Cannot read the loop nest"));
     }
 
     #[test]
@@ -153,4 +697,387 @@ body:
         let deserialized: LoopNest = serde_yaml::from_str(&serialized).unwrap();
         assert_eq!(loop_prob, deserialized);
     }
+
+    #[test]
+    fn test_get_iter() {
+        let mut loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..100)
+  - for k in (0..300).step(3)
+body:
+  - Ra <= A[m][k]
+"#,
+        )
+        .unwrap();
+        assert_eq!(loop_nest.iter_names(), vec!["m", "k"]);
+        assert_eq!(loop_nest.get_iter("k").unwrap().step, Coeff::Const(3));
+        assert!(loop_nest.get_iter("n").is_none());
+
+        loop_nest.get_iter_mut("m").unwrap().bounds = (AffineExpr::Const(0), AffineExpr::Const(50));
+        assert_eq!(
+            loop_nest.iters[0].concrete_bounds(&HashMap::new()),
+            Some((0, 50))
+        );
+        assert!(loop_nest.get_iter_mut("n").is_none());
+    }
+
+    #[test]
+    fn test_total_iteration_count() {
+        let mut loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..100)
+  - for k in (0..10).step(3)
+body:
+  - Ra <= A[m][k]
+"#,
+        )
+        .unwrap();
+        // k in {0, 3, 6, 9}
+        assert_eq!(loop_nest.total_iteration_count(&HashMap::new()), Some(400));
+
+        loop_nest.iters[1].step = Coeff::Const(0);
+        assert_eq!(loop_nest.total_iteration_count(&HashMap::new()), None);
+
+        // (2^31 - 1)^3 iterations do not fit in an i64
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..N)
+  - for j in (0..N)
+  - for k in (0..N)
+body:
+  - Ra <= A[i][j][k]
+"#,
+        )
+        .unwrap();
+        let params = HashMap::from([("N".to_string(), i32::MAX)]);
+        assert_eq!(loop_nest.total_iteration_count(&params), None);
+    }
+
+    #[test]
+    fn test_symbolic_bounds() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..M)
+  - for k in (1..2 * K + 1).step(2)
+body:
+  - Ra <= A[m][k]
+"#,
+        )
+        .unwrap();
+        assert_eq!(loop_nest.iters[0].to_string(), "for m in (0..M)");
+        assert_eq!(
+            loop_nest.iters[1].to_string(),
+            "for k in (1..2 * K + 1).step(2)"
+        );
+        let serialized = serde_yaml::to_string(&loop_nest).unwrap();
+        assert_eq!(loop_nest, serde_yaml::from_str(&serialized).unwrap());
+        assert_eq!(loop_nest.validate(), Ok(()));
+
+        assert_eq!(loop_nest.total_iteration_count(&HashMap::new()), None);
+        let params = HashMap::from([("M".to_string(), 10), ("K".to_string(), 8)]);
+        assert_eq!(loop_nest.iters[1].concrete_bounds(&params), Some((1, 17)));
+        assert_eq!(loop_nest.total_iteration_count(&params), Some(10 * 8));
+    }
+
+    #[test]
+    fn test_symbolic_step() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..N).step(SIMD_W)
+body:
+  - Ra <= A[i]
+"#,
+        )
+        .unwrap();
+        let iter = &loop_nest.iters[0];
+        assert_eq!(iter.step, Coeff::ConstVar("SIMD_W".to_string()));
+        assert_eq!(iter.to_string(), "for i in (0..N).step(SIMD_W)");
+        let serialized = serde_yaml::to_string(&loop_nest).unwrap();
+        assert_eq!(loop_nest, serde_yaml::from_str(&serialized).unwrap());
+        assert_eq!(loop_nest.validate(), Ok(()));
+
+        assert_eq!(iter.concrete_step(&HashMap::new()), None);
+        let params = HashMap::from([("N".to_string(), 64), ("SIMD_W".to_string(), 8)]);
+        assert_eq!(iter.concrete_step(&params), Some(8));
+        assert_eq!(iter.trip_count(&params), Some(8));
+    }
+
+    #[test]
+    fn test_validate() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..100)
+  - for k in (0..300)
+body:
+  - Ra <= A[m][k]
+  - Ra => B[k][m]
+"#,
+        )
+        .unwrap();
+        assert_eq!(loop_nest.validate(), Ok(()));
+
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..100)
+  - for m in (10..0)
+body:
+  - Ra <= A[m][k]
+"#,
+        )
+        .unwrap();
+        assert_eq!(loop_nest.validate().unwrap_err().len(), 3);
+
+        let loop_nest = LoopNest {
+            iters: vec![],
+            body: vec![],
+        };
+        assert_eq!(loop_nest.validate().unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn test_operation_counts() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..100)
+  - for k in (0..300)
+body:
+  - Ra <= A[m][k]
+  - Rb <= B[k]
+  - mul Rc Ra, Rb
+  - add Rd Rc, $1
+  - Rd => A[m][k]
+"#,
+        )
+        .unwrap();
+        assert_eq!(loop_nest.count_memory_operations(), (2, 1, 2));
+        assert_eq!(loop_nest.count_compute_operations(), 2);
+        let intensity = loop_nest.arithmetic_intensity(&HashMap::new()).unwrap();
+        assert!((intensity - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_peel() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for k in (0..10).step(4)
+  - for m in (k..M)
+body:
+  - Ra <= A[m][k]
+"#,
+        )
+        .unwrap();
+        let (prologue, remainder) = loop_nest.peel_first_iteration().unwrap();
+        assert_eq!(prologue.iter_names(), vec!["m"]);
+        assert_eq!(prologue.iters[0].to_string(), "for m in (0..M)");
+        assert_eq!(prologue.body[0].to_string(), "Ra <= A[m][0]");
+        assert_eq!(remainder.iters[0].to_string(), "for k in (4..10).step(4)");
+        assert_eq!(remainder.iters[1], loop_nest.iters[1]);
+        assert_eq!(remainder.body, loop_nest.body);
+
+        let (remainder, epilogue) = loop_nest.peel_last_iteration().unwrap();
+        assert_eq!(remainder.iters[0].to_string(), "for k in (0..8).step(4)");
+        assert_eq!(epilogue.iters[0].to_string(), "for m in (8..M)");
+        assert_eq!(epilogue.body[0].to_string(), "Ra <= A[m][8]");
+        let params = HashMap::new();
+        assert_eq!(remainder.iters[0].trip_count(&params), Some(2));
+
+        // symbolic bounds
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (1..M)
+body:
+  - Ra <= A[m]
+"#,
+        )
+        .unwrap();
+        let (prologue, remainder) = loop_nest.peel_first_iteration().unwrap();
+        assert!(prologue.iters.is_empty());
+        assert_eq!(prologue.body[0].to_string(), "Ra <= A[1]");
+        assert_eq!(remainder.iters[0].to_string(), "for m in (2..M)");
+        let (remainder, epilogue) = loop_nest.peel_last_iteration().unwrap();
+        let params = HashMap::from([("M".to_string(), 10)]);
+        assert_eq!(remainder.iters[0].trip_count(&params), Some(8));
+        assert!(epilogue.iters.is_empty());
+        match &epilogue.body[0] {
+            Instruction::DataLoad(access) => {
+                assert_eq!(access.addr[0].evaluate(&params, &params), Ok(9))
+            }
+            inst => panic!("unexpected instruction {}", inst),
+        }
+
+        let empty = LoopNest {
+            iters: vec![],
+            body: loop_nest.body.clone(),
+        };
+        assert!(empty.peel_first_iteration().is_none());
+        assert!(empty.peel_last_iteration().is_none());
+    }
+
+    #[test]
+    fn test_arrays_accessed() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..100)
+body:
+  - Ra <= A[m]
+  - PREFETCH D[m + 1]
+  - iters:
+      - for k in (0..4)
+    body:
+      - Rb <= B[k]
+      - Rb => C[m][k]
+  - Ra => A[m]
+"#,
+        )
+        .unwrap();
+        let arrays = loop_nest.all_arrays_accessed();
+        assert_eq!(arrays.len(), 3);
+        assert_eq!(arrays["A"], (true, true));
+        assert_eq!(arrays["B"], (true, false));
+        assert_eq!(arrays["C"], (false, true));
+        assert_eq!(loop_nest.live_in_arrays(), vec!["B"]);
+        assert_eq!(loop_nest.live_out_arrays(), vec!["A", "C"]);
+
+        let to_strings = |insts: Vec<&Instruction>| -> Vec<String> {
+            insts.iter().map(|inst| inst.to_string()).collect()
+        };
+        assert_eq!(
+            to_strings(loop_nest.instructions_accessing_array("A")),
+            vec!["Ra <= A[m]", "Ra => A[m]"]
+        );
+        assert!(loop_nest.instructions_accessing_array("D").is_empty());
+        assert_eq!(
+            to_strings(loop_nest.instructions_using_register("Rb")),
+            vec!["Rb <= B[k]", "Rb => C[m][k]"]
+        );
+
+        // vector registers are known by their name
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for j in (0..4)
+body:
+  - add V0:4 V1:4, V2:4
+  - mul V3:4 V0:4, V1:4
+"#,
+        )
+        .unwrap();
+        assert_eq!(loop_nest.instructions_using_register("V0").len(), 2);
+        assert_eq!(loop_nest.instructions_using_register("V3").len(), 1);
+    }
+
+    #[test]
+    fn test_nested_loop_nest() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..4)
+body:
+  - Ra <= A[m]
+  - iters:
+      - for n in (0..8)
+      - for k in (0..2)
+    body:
+      - Rb <= B[m][n][k]
+      - mul Rc Ra, Rb
+  - Rc => C[m]
+"#,
+        )
+        .unwrap();
+        assert_eq!(loop_nest.validate(), Ok(()));
+        assert_eq!(
+            loop_nest.body[1].to_string(),
+            "for n in (0..8) for k in (0..2) { Rb <= B[m][n][k]; mul Rc Ra, Rb }"
+        );
+        let serialized = serde_yaml::to_string(&loop_nest).unwrap();
+        assert_eq!(loop_nest, serde_yaml::from_str(&serialized).unwrap());
+        assert_eq!(
+            loop_nest.to_string(),
+            "for m in (0..4)
+  Ra <= A[m]
+  for n in (0..8)
+    for k in (0..2)
+      Rb <= B[m][n][k]
+      mul Rc Ra, Rb
+  Rc => C[m]
+"
+        );
+
+        let iter_names: Vec<&str> = loop_nest
+            .all_iters()
+            .iter()
+            .map(|iter| iter.iter_name.as_str())
+            .collect();
+        assert_eq!(iter_names, vec!["m", "n", "k"]);
+        let depths: Vec<usize> = loop_nest
+            .scoped_instructions()
+            .iter()
+            .map(|(iters, _)| iters.len())
+            .collect();
+        assert_eq!(depths, vec![1, 3, 3, 1]);
+        assert_eq!(loop_nest.total_iteration_count(&HashMap::new()), Some(4));
+        assert_eq!(loop_nest.count_memory_operations(), (2, 1, 1));
+        // 64 multiplications for 4 + 64 + 4 memory operations
+        let intensity = loop_nest.arithmetic_intensity(&HashMap::new()).unwrap();
+        assert!((intensity - 64.0 / 72.0).abs() < 1e-9);
+
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..4)
+body:
+  - iters:
+      - for m in (0..8)
+    body:
+      - Rb <= B[m][n]
+"#,
+        )
+        .unwrap();
+        let errors = loop_nest.validate().unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("shadows"));
+        assert!(errors[1].contains("undefined iterator n"));
+    }
+
+    #[test]
+    fn test_pseudocode() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..M)
+body:
+  - Ra <= A[m]
+  - iters:
+      - for n in (0..8).step(2)
+    body:
+      - mul Rc Ra, Rb
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            loop_nest.iters[0].to_pseudocode(),
+            "for (int m = 0; m < M; m += 1)"
+        );
+        let mapping = Mapping::from_str("Mapping:\n - n -> $x\n").unwrap();
+        assert_eq!(
+            loop_nest.to_pseudocode(Some(&mapping)),
+            "for (int m = 0; m < M; m += 1) {
+    Ra <= A[m];
+    for (int n = 0; n < 8; n += 2) { // $x
+        mul Rc Ra, Rb;
+    }
+}"
+        );
+    }
 }
@@ -1,12 +1,14 @@
+use super::arch::Arch;
 use super::loops::LoopNest;
+use crate::error::ParseError;
 use core::fmt;
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{alpha1, multispace0, space0},
-    combinator::{cut, map},
+    character::complete::{alpha1, char, digit1, multispace0, multispace1, space0},
+    combinator::{cut, map, map_res, opt},
     multi::separated_list0,
-    sequence::{delimited, terminated, tuple},
+    sequence::{delimited, pair, preceded, terminated, tuple},
     IResult,
 };
 use serde::{Deserialize, Deserializer, Serialize};
@@ -22,6 +24,8 @@ pub struct Mapping {
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub enum MappingType {
     Spatial(String),
+    /// The iterator is unrolled across the SIMD lanes of a dimension, `(dimension, width)`
+    Vectorized(String, u32),
     TemporalTODO,
     InterTile,
     IntraTile,
@@ -31,9 +35,20 @@ fn parse_identifier(input: &str) -> IResult<&str, String> {
     map(alpha1, String::from)(input)
 }
 
+// "$x" is a spatial mapping, "$simd:4" a vectorization over 4 lanes
 fn parse_spatial_mapping(input: &str) -> IResult<&str, MappingType> {
     let (input, _) = tag("$")(input)?;
-    map(alpha1, |s: &str| MappingType::Spatial(s.to_string()))(input)
+    let (input, (dim, width)) = pair(
+        alpha1,
+        opt(preceded(char(':'), map_res(digit1, str::parse::<u32>))),
+    )(input)?;
+    Ok((
+        input,
+        match width {
+            Some(width) => MappingType::Vectorized(dim.to_string(), width),
+            None => MappingType::Spatial(dim.to_string()),
+        },
+    ))
 }
 
 fn parse_temporal_todo(input: &str) -> IResult<&str, MappingType> {
@@ -65,7 +80,7 @@ fn parse_mapping_entry(input: &str) -> IResult<&str, (String, MappingType)> {
 }
 
 fn parse_type_mapping(input: &str) -> IResult<&str, HashMap<String, MappingType>> {
-    let (input, entries) = separated_list0(multispace0, parse_mapping_entry)(input)?;
+    let (input, entries) = separated_list0(multispace1, parse_mapping_entry)(input)?;
     Ok((input, entries.into_iter().collect()))
 }
 
@@ -78,22 +93,122 @@ fn parse_mapping(input: &str) -> IResult<&str, Mapping> {
         input,
         Mapping {
             loop_nest: None,
-            types: types,
+            types,
         },
     ))
 }
 
 impl Mapping {
-    pub fn from_str(input: &str) -> Mapping {
-        parse_mapping(input).unwrap().1
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &str) -> Result<Mapping, ParseError> {
+        parse_mapping(input)
+            .map(|(_, mapping)| mapping)
+            .map_err(|e| ParseError::from_nom(input, e))
+    }
+
+    /// Returns true if every iterator of the loop nest is mapped
+    pub fn is_complete(&self, loop_nest: &LoopNest) -> bool {
+        self.unmapped_iters(loop_nest).is_empty()
+    }
+
+    /// Names of the iterators of the loop nest which are not mapped, in program order
+    pub fn unmapped_iters<'a>(&'a self, loop_nest: &'a LoopNest) -> Vec<&'a str> {
+        loop_nest
+            .all_iters()
+            .into_iter()
+            .map(|iter| iter.iter_name.as_str())
+            .filter(|iter| !self.types.contains_key(*iter))
+            .collect()
+    }
+
+    /// Check the mapping of the loop nest on the architecture:
+    /// every iterator is mapped, the spatial mappings reference dimensions of the architecture
+    /// (with SIMD widths fitting the dimensions),
+    /// a dimension does not have more iterators mapped on it than its shape,
+    /// and the temporal mappings reference iterators of the loop nest.
+    /// Returns all the errors found.
+    pub fn validate_against_arch(
+        &self,
+        arch: &Arch,
+        loop_nest: &LoopNest,
+    ) -> Result<(), Vec<String>> {
+        let mut errors: Vec<String> = self
+            .unmapped_iters(loop_nest)
+            .into_iter()
+            .map(|iter| format!("The iterator {} is not mapped", iter))
+            .collect();
+        let iter_names: Vec<&str> = loop_nest
+            .all_iters()
+            .into_iter()
+            .map(|iter| iter.iter_name.as_str())
+            .collect();
+
+        // sorted for deterministic errors
+        let mut entries: Vec<(&String, &MappingType)> = self.types.iter().collect();
+        entries.sort_by_key(|(iter, _)| *iter);
+        for (iter, mapping_type) in &entries {
+            match mapping_type {
+                MappingType::Spatial(dim) => {
+                    if !arch.dimensions.iter().any(|d| d.name == *dim) {
+                        errors.push(format!(
+                            "The iterator {} is mapped on the unknown dimension {}",
+                            iter, dim
+                        ));
+                    }
+                }
+                MappingType::Vectorized(dim, width) => {
+                    match arch.dimensions.iter().find(|d| d.name == *dim) {
+                        Some(d) if *width as i32 > d.shape => errors.push(format!(
+                            "The SIMD width {} of the iterator {} exceeds the shape {} of the dimension {}",
+                            width, iter, d.shape, dim
+                        )),
+                        Some(_) => {}
+                        None => errors.push(format!(
+                            "The iterator {} is mapped on the unknown dimension {}",
+                            iter, dim
+                        )),
+                    }
+                }
+                MappingType::TemporalTODO => {
+                    if !iter_names.contains(&iter.as_str()) {
+                        errors.push(format!(
+                            "The temporal mapping references the unknown iterator {}",
+                            iter
+                        ));
+                    }
+                }
+                MappingType::InterTile | MappingType::IntraTile => {}
+            }
+        }
+        for dim in &arch.dimensions {
+            let mapped = entries
+                .iter()
+                .filter(|(_, mapping_type)| {
+                    matches!(mapping_type, MappingType::Spatial(name)
+                        | MappingType::Vectorized(name, _) if *name == dim.name)
+                })
+                .count();
+            if mapped as i32 > dim.shape {
+                errors.push(format!(
+                    "{} iterators are mapped on the dimension {} of shape {}",
+                    mapped, dim.name, dim.shape
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 }
 
 impl fmt::Display for Mapping {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Mapping:\n")?;
+        writeln!(f, "Mapping:")?;
         for (iter, mapping_type) in &self.types {
-            write!(f, "\t- {} -> {}\n", iter, mapping_type)?;
+            writeln!(f, "\t- {} -> {}", iter, mapping_type)?;
         }
         Ok(())
     }
@@ -103,6 +218,7 @@ impl fmt::Display for MappingType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             MappingType::Spatial(s) => write!(f, "${}", s),
+            MappingType::Vectorized(s, width) => write!(f, "${}:{}", s, width),
             MappingType::TemporalTODO => write!(f, "Temporal"),
             MappingType::InterTile => write!(f, "InterTile"),
             MappingType::IntraTile => write!(f, "IntraTile"),
@@ -116,7 +232,9 @@ impl<'de> Deserialize<'de> for Mapping {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Ok(Mapping::from_str(&s))
+        parse_mapping(&s)
+            .map(|(_, mapping)| mapping)
+            .map_err(|e| serde::de::Error::custom(ParseError::from_nom(&s, e)))
     }
 }
 
@@ -131,7 +249,6 @@ impl Serialize for Mapping {
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
     #[test]
@@ -143,7 +260,7 @@ Mapping:
     - tn -> IntraTile
     - x -> $x
     - y -> $y
-    - simd -> $simd
+    - simd -> $simd:4
         "#;
         let expected_mapping = Mapping {
             loop_nest: None,
@@ -153,16 +270,75 @@ Mapping:
                 ("tn".to_string(), MappingType::IntraTile),
                 ("x".to_string(), MappingType::Spatial("x".to_string())),
                 ("y".to_string(), MappingType::Spatial("y".to_string())),
-                ("simd".to_string(), MappingType::Spatial("simd".to_string())),
+                (
+                    "simd".to_string(),
+                    MappingType::Vectorized("simd".to_string(), 4),
+                ),
             ]
             .into_iter()
             .collect(),
         };
-        let _mapping: Mapping = Mapping::from_str(input);
+        let mapping: Mapping = Mapping::from_str(input).unwrap();
+        assert_eq!(mapping, expected_mapping);
+        assert_eq!(mapping.types["simd"].to_string(), "$simd:4");
+        let error = Mapping::from_str("Mapped:\n - i -> $x\n").unwrap_err();
+        assert_eq!(error.input, "Mapped:\n - i -> $x\n");
 
         //try serialize -> deserialize
         let serialized = serde_yaml::to_string(&expected_mapping).unwrap();
         let deserialized: Mapping = serde_yaml::from_str(&serialized).unwrap();
         assert_eq!(deserialized, expected_mapping);
     }
+
+    #[test]
+    fn test_validate() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..8)
+  - for j in (0..8)
+  - for k in (0..4)
+body:
+  - Ra <= A[i][j][k]
+"#,
+        )
+        .unwrap();
+        let arch: Arch = serde_yaml::from_str(
+            r#"
+pe_arch:
+  data_ports: []
+  data_width: 4
+dimensions:
+  - name: "x"
+    shape: 8
+  - name: "y"
+    shape: 1
+"#,
+        )
+        .unwrap();
+        let mapping =
+            Mapping::from_str("Mapping:\n - i -> $x\n - j -> $y\n - k -> InterTile\n").unwrap();
+        assert_eq!(mapping.validate_against_arch(&arch, &loop_nest), Ok(()));
+        assert!(mapping.is_complete(&loop_nest));
+
+        let mapping = Mapping::from_str(
+            "Mapping:\n - i -> $y\n - j -> $x:16\n - l -> Temporal\n - m -> $simd\n - n -> $y\n",
+        )
+        .unwrap();
+        assert!(!mapping.is_complete(&loop_nest));
+        assert_eq!(mapping.unmapped_iters(&loop_nest), vec!["k"]);
+        let errors = mapping
+            .validate_against_arch(&arch, &loop_nest)
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "The iterator k is not mapped",
+                "The SIMD width 16 of the iterator j exceeds the shape 8 of the dimension x",
+                "The temporal mapping references the unknown iterator l",
+                "The iterator m is mapped on the unknown dimension simd",
+                "2 iterators are mapped on the dimension y of shape 1",
+            ]
+        );
+    }
 }
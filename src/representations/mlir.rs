@@ -0,0 +1,1094 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until, take_while1},
+    character::complete::{
+        alpha1, alphanumeric1, char, digit1, multispace0, multispace1, not_line_ending, one_of,
+    },
+    combinator::{map, map_res, opt, recognize},
+    error::ErrorKind,
+    multi::{many0, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+use std::collections::HashMap;
+
+use crate::error::ParseError;
+
+use super::affine_expr::{AffineExpr, Coeff};
+use super::instruction::{Compute, DataAccess, Instruction, OpClass, Operand};
+use super::loops::{LoopIter, LoopNest};
+
+impl LoopNest {
+    /// The loop nest as a `func.func` in the textual format of the MLIR affine dialect.
+    /// The iterators are `affine.for` loops, the loads and stores `affine.load` and
+    /// `affine.store`, and the computations `arith` operations on `f32` values.
+    /// The arrays are `memref` arguments of dynamic shape, the symbolic parameters `index`
+    /// arguments and the registers read before being written `f32` arguments.
+    /// Fails if the loop nest has no affine equivalent, e.g. a symbolic step,
+    /// a conditional instruction or a vector register.
+    pub fn to_mlir_affine_dialect(&self) -> Result<String, String> {
+        let mut emitter = MlirEmitter::default();
+        emitter.emit_nest(self, 1)?;
+        Ok(emitter.finish())
+    }
+
+    /// Parse a loop nest from a subset of the textual format of the MLIR affine dialect:
+    /// `affine.for` loops with constant steps, `affine.load`, `affine.store`,
+    /// `affine.prefetch`, and the `arith` operations written by `to_mlir_affine_dialect`,
+    /// optionally wrapped in a `func.func`.
+    /// The perfectly nested loops form a single loop nest, the other inner loops are loop
+    /// bodies. The SSA values are named as registers, e.g. `Ra0` for `%Ra_0`, and the
+    /// `arith.constant` values are immediate operands.
+    pub fn from_mlir_affine_str(input: &str) -> Result<LoopNest, String> {
+        let items = match terminated(alt((parse_func, parse_items)), multispace0)(input) {
+            Ok(("", items)) => items,
+            Ok((rest, _)) => {
+                return Err(ParseError::new(
+                    input,
+                    input.len() - rest.len(),
+                    "unsupported operation",
+                )
+                .to_string())
+            }
+            Err(e) => return Err(ParseError::from_nom(input, e).to_string()),
+        };
+        let mut constants = HashMap::new();
+        let mut nests = Vec::new();
+        for item in items {
+            match item {
+                Item::For(iter, body) => nests.push((iter, body)),
+                Item::Constant(value, operand) => {
+                    constants.insert(value, operand);
+                }
+                Item::Skip => {}
+                Item::Inst(_) | Item::Arith(_, _, _) => {
+                    return Err("Only constants can be defined outside of the loops".to_string())
+                }
+            }
+        }
+        match nests.pop() {
+            Some((iter, body)) if nests.is_empty() => Ok(build_nest(iter, body, &mut constants)),
+            _ => Err(format!(
+                "Expected a single top-level affine.for, found {}",
+                nests.len() + 1
+            )),
+        }
+    }
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+/// Position of `name` in `names`, appended if missing
+fn position_or_insert(names: &mut Vec<String>, name: &str) -> usize {
+    match names.iter().position(|n| n == name) {
+        Some(pos) => pos,
+        None => {
+            names.push(name.to_string());
+            names.len() - 1
+        }
+    }
+}
+
+fn memref_type(rank: usize) -> String {
+    format!("memref<{}f32>", "?x".repeat(rank))
+}
+
+#[derive(Default)]
+struct MlirEmitter {
+    lines: Vec<String>,
+    /// Rank of each accessed array, in order of first access
+    arrays: Vec<(String, usize)>,
+    symbols: Vec<String>,
+    /// Registers read before being written, passed as arguments
+    live_in: Vec<String>,
+    /// Iterators of the enclosing `affine.for`
+    scope: Vec<String>,
+    /// The SSA value holding the content of each register
+    values: HashMap<String, String>,
+    value_count: usize,
+}
+
+impl MlirEmitter {
+    fn finish(self) -> String {
+        let args: Vec<String> = self
+            .arrays
+            .iter()
+            .map(|(array, rank)| format!("%{}: {}", array, memref_type(*rank)))
+            .chain(
+                self.symbols
+                    .iter()
+                    .map(|symbol| format!("%{}: index", symbol)),
+            )
+            .chain(self.live_in.iter().map(|reg| format!("%{}: f32", reg)))
+            .collect();
+        let mut lines = vec![format!("func.func @loop_nest({}) {{", args.join(", "))];
+        lines.extend(self.lines);
+        lines.push(format!("{}return", indent(1)));
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    /// The registers written in the loops are not visible after them
+    fn emit_nest(&mut self, nest: &LoopNest, depth: usize) -> Result<(), String> {
+        let values = self.values.clone();
+        let scope_len = self.scope.len();
+        for (idx, iter) in nest.iters.iter().enumerate() {
+            let header = self.loop_header(iter)?;
+            self.lines
+                .push(format!("{}{} {{", indent(depth + idx), header));
+            self.scope.push(iter.iter_name.clone());
+        }
+        let body_depth = depth + nest.iters.len();
+        for inst in &nest.body {
+            self.emit_instruction(inst, body_depth)?;
+        }
+        for idx in (0..nest.iters.len()).rev() {
+            self.lines.push(format!("{}}}", indent(depth + idx)));
+        }
+        self.scope.truncate(scope_len);
+        self.values = values;
+        Ok(())
+    }
+
+    /// e.g. `affine.for %n = 0 to %N step 2`
+    fn loop_header(&mut self, iter: &LoopIter) -> Result<String, String> {
+        let step = match iter.step.normalize() {
+            Coeff::Const(step) if step > 0 => step,
+            step => {
+                return Err(format!(
+                    "The step {} of the iterator {} is not a positive constant",
+                    step, iter.iter_name
+                ))
+            }
+        };
+        let lower = self.bound(&iter.bounds.0, true)?;
+        let upper = self.bound(&iter.bounds.1, false)?;
+        let step = if step == 1 {
+            String::new()
+        } else {
+            format!(" step {}", step)
+        };
+        Ok(format!(
+            "affine.for %{} = {} to {}{}",
+            iter.iter_name, lower, upper, step
+        ))
+    }
+
+    /// A constant, a symbol or an `affine_map` applied to the enclosing iterators and the
+    /// symbols. The maximum of the lower bounds and the minimum of the upper bounds are
+    /// written as maps with several results.
+    fn bound(&mut self, expr: &AffineExpr, lower: bool) -> Result<String, String> {
+        match expr {
+            AffineExpr::Const(value) => return Ok(value.to_string()),
+            AffineExpr::Var(var) if !self.scope.contains(var) => {
+                position_or_insert(&mut self.symbols, var);
+                return Ok(format!("%{}", var));
+            }
+            _ => {}
+        }
+        let mut bounds = Vec::new();
+        flatten_bound(expr, lower, &mut bounds);
+        let mut dims = Vec::new();
+        let mut syms = Vec::new();
+        let scope = &self.scope;
+        let symbols = &mut self.symbols;
+        let results = bounds
+            .iter()
+            .map(|bound| {
+                render_expr(bound, &mut |var| {
+                    if scope.iter().any(|iter| iter == var) {
+                        format!("d{}", position_or_insert(&mut dims, var))
+                    } else {
+                        position_or_insert(symbols, var);
+                        format!("s{}", position_or_insert(&mut syms, var))
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let keyword = match (results.len() > 1, lower) {
+            (false, _) => "",
+            (true, true) => "max ",
+            (true, false) => "min ",
+        };
+        let dim_names: Vec<String> = (0..dims.len()).map(|idx| format!("d{}", idx)).collect();
+        let sym_names: Vec<String> = (0..syms.len()).map(|idx| format!("s{}", idx)).collect();
+        let dim_operands: Vec<String> = dims.iter().map(|dim| format!("%{}", dim)).collect();
+        let sym_operands: Vec<String> = syms.iter().map(|sym| format!("%{}", sym)).collect();
+        let (sym_names, sym_operands) = if syms.is_empty() {
+            (String::new(), String::new())
+        } else {
+            (
+                format!("[{}]", sym_names.join(", ")),
+                format!("[{}]", sym_operands.join(", ")),
+            )
+        };
+        Ok(format!(
+            "{}affine_map<({}){} -> ({})>({}){}",
+            keyword,
+            dim_names.join(", "),
+            sym_names,
+            results.join(", "),
+            dim_operands.join(", "),
+            sym_operands
+        ))
+    }
+
+    fn emit_instruction(&mut self, inst: &Instruction, depth: usize) -> Result<(), String> {
+        let conditional = match inst {
+            Instruction::DataLoad(access)
+            | Instruction::DataStore(access)
+            | Instruction::Prefetch(access) => access.cond.is_some(),
+            Instruction::Compute(compute) => compute.cond.is_some(),
+            Instruction::SyncBarrier(_) | Instruction::LoopBody(_) => false,
+        };
+        if conditional {
+            return Err(format!(
+                "The conditional instruction {} has no affine equivalent",
+                inst
+            ));
+        }
+        let line = match inst {
+            Instruction::DataLoad(access) => {
+                let (memref, memref_type) = self.access(access)?;
+                let value = self.define(&access.reg);
+                format!("{} = affine.load {} : {}", value, memref, memref_type)
+            }
+            Instruction::DataStore(access) => {
+                let value = self.use_reg(&access.reg);
+                let (memref, memref_type) = self.access(access)?;
+                format!("affine.store {}, {} : {}", value, memref, memref_type)
+            }
+            Instruction::Prefetch(access) => {
+                let (memref, memref_type) = self.access(access)?;
+                format!(
+                    "affine.prefetch {}, read, locality<3>, data : {}",
+                    memref, memref_type
+                )
+            }
+            Instruction::Compute(compute) => return self.emit_compute(compute, depth),
+            Instruction::SyncBarrier(label) => format!("// SYNC {}", label),
+            Instruction::LoopBody(nest) => return self.emit_nest(nest, depth),
+        };
+        self.lines.push(format!("{}{}", indent(depth), line));
+        Ok(())
+    }
+
+    /// e.g. `(%A[%i, %j + 1], memref<?x?xf32>)`, the iterators being dimensions and the
+    /// other variables symbols
+    fn access(&mut self, access: &DataAccess) -> Result<(String, String), String> {
+        let rank = access.addr.len();
+        match self
+            .arrays
+            .iter()
+            .find(|(array, _)| *array == access.array_name)
+        {
+            Some((_, array_rank)) if *array_rank != rank => {
+                return Err(format!(
+                    "The array {} is accessed with {} and {} indices",
+                    access.array_name, array_rank, rank
+                ))
+            }
+            Some(_) => {}
+            None => self.arrays.push((access.array_name.clone(), rank)),
+        }
+        let scope = &self.scope;
+        let symbols = &mut self.symbols;
+        let indices = access
+            .addr
+            .iter()
+            .map(|idx| {
+                render_expr(idx, &mut |var| {
+                    if scope.iter().any(|iter| iter == var) {
+                        format!("%{}", var)
+                    } else {
+                        position_or_insert(symbols, var);
+                        format!("symbol(%{})", var)
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((
+            format!("%{}[{}]", access.array_name, indices.join(", ")),
+            memref_type(rank),
+        ))
+    }
+
+    /// `mac` and `fma` accumulate the product of the sources into the destination,
+    /// or add the third source to it if given
+    fn emit_compute(&mut self, compute: &Compute, depth: usize) -> Result<(), String> {
+        let sources = compute
+            .src
+            .iter()
+            .map(|src| self.operand(src, depth))
+            .collect::<Result<Vec<_>, _>>()?;
+        let dst = match &compute.dst {
+            Operand::Reg(reg) => reg.clone(),
+            dst => {
+                return Err(format!(
+                    "The vector register {} has no affine equivalent",
+                    dst
+                ))
+            }
+        };
+        match (compute.op_class(), sources.as_slice()) {
+            (OpClass::MOV, [src]) => {
+                self.values.insert(dst.clone(), src.clone());
+            }
+            (OpClass::MAC, [lhs, rhs]) => {
+                let acc = self.use_reg(&dst);
+                let product = self.arith("mulf", lhs, rhs, "prod", depth);
+                let sum = self.arith("addf", &acc, &product, &dst, depth);
+                self.values.insert(dst.clone(), sum);
+            }
+            (OpClass::MAC, [lhs, rhs, addend]) => {
+                let product = self.arith("mulf", lhs, rhs, "prod", depth);
+                let sum = self.arith("addf", &product, addend, &dst, depth);
+                self.values.insert(dst.clone(), sum);
+            }
+            (_, [lhs, rhs]) => {
+                let op = match compute.op.as_str() {
+                    "add" => "addf",
+                    "sub" => "subf",
+                    "mul" => "mulf",
+                    "div" => "divf",
+                    "max" => "maximumf",
+                    "min" => "minimumf",
+                    _ => return Err(format!("The operation {} has no arith equivalent", compute)),
+                };
+                let result = self.arith(op, lhs, rhs, &dst, depth);
+                self.values.insert(dst.clone(), result);
+            }
+            _ => return Err(format!("The operation {} has no arith equivalent", compute)),
+        }
+        Ok(())
+    }
+
+    /// Emit the binary operation `arith.<op>` and return its result
+    fn arith(&mut self, op: &str, lhs: &str, rhs: &str, name: &str, depth: usize) -> String {
+        let result = self.fresh(name);
+        self.lines.push(format!(
+            "{}{} = arith.{} {}, {} : f32",
+            indent(depth),
+            result,
+            op,
+            lhs,
+            rhs
+        ));
+        result
+    }
+
+    /// The SSA value of a source, an immediate being emitted as an `arith.constant`
+    fn operand(&mut self, operand: &Operand, depth: usize) -> Result<String, String> {
+        let value = match operand {
+            Operand::Reg(reg) => return Ok(self.use_reg(reg)),
+            Operand::ImmInt(value) => *value as f64,
+            Operand::ImmFloat(value) => *value,
+            Operand::VecReg(_, _) => {
+                return Err(format!(
+                    "The vector register {} has no affine equivalent",
+                    operand
+                ))
+            }
+        };
+        let cst = self.fresh("cst");
+        self.lines.push(format!(
+            "{}{} = arith.constant {:.6e} : f32",
+            indent(depth),
+            cst,
+            value
+        ));
+        Ok(cst)
+    }
+
+    /// A new SSA value for `reg`, e.g. `%Ra_0`, distinct from the arguments
+    fn define(&mut self, reg: &str) -> String {
+        let value = self.fresh(reg);
+        self.values.insert(reg.to_string(), value.clone());
+        value
+    }
+
+    /// The current value of `reg`, an argument if it was not written before
+    fn use_reg(&mut self, reg: &str) -> String {
+        match self.values.get(reg) {
+            Some(value) => value.clone(),
+            None => {
+                position_or_insert(&mut self.live_in, reg);
+                format!("%{}", reg)
+            }
+        }
+    }
+
+    fn fresh(&mut self, name: &str) -> String {
+        self.value_count += 1;
+        format!("%{}_{}", name, self.value_count - 1)
+    }
+}
+
+/// The bounds combined by `max` in a lower bound, or by `min` in an upper bound
+fn flatten_bound<'a>(expr: &'a AffineExpr, lower: bool, bounds: &mut Vec<&'a AffineExpr>) {
+    match (expr, lower) {
+        (AffineExpr::Max(e1, e2), true) | (AffineExpr::Min(e1, e2), false) => {
+            flatten_bound(e1, lower, bounds);
+            flatten_bound(e2, lower, bounds);
+        }
+        _ => bounds.push(expr),
+    }
+}
+
+/// The expression in the syntax of MLIR affine expressions, `name` giving the name of each
+/// variable and const variable
+fn render_expr(expr: &AffineExpr, name: &mut dyn FnMut(&str) -> String) -> Result<String, String> {
+    Ok(match expr {
+        AffineExpr::Var(var) => name(var),
+        AffineExpr::Const(value) => value.to_string(),
+        AffineExpr::Add(e1, e2) => {
+            format!(
+                "{} + {}",
+                render_operand(e1, name)?,
+                render_operand(e2, name)?
+            )
+        }
+        AffineExpr::Sub(e1, e2) => {
+            format!(
+                "{} - {}",
+                render_operand(e1, name)?,
+                render_operand(e2, name)?
+            )
+        }
+        AffineExpr::Mul(coeff, e) => {
+            format!(
+                "{} * {}",
+                render_coeff(coeff, name),
+                render_operand(e, name)?
+            )
+        }
+        AffineExpr::Div(e, coeff) => {
+            format!(
+                "{} floordiv {}",
+                render_operand(e, name)?,
+                render_coeff(coeff, name)
+            )
+        }
+        AffineExpr::CeilDiv(e, coeff) => {
+            format!(
+                "{} ceildiv {}",
+                render_operand(e, name)?,
+                render_coeff(coeff, name)
+            )
+        }
+        AffineExpr::Mod(e, coeff) => {
+            format!(
+                "{} mod {}",
+                render_operand(e, name)?,
+                render_coeff(coeff, name)
+            )
+        }
+        AffineExpr::Neg(e) => format!("-{}", render_operand(e, name)?),
+        AffineExpr::Max(_, _) | AffineExpr::Min(_, _) => {
+            return Err(format!(
+                "{} can only be written as a bound of an affine.for",
+                expr
+            ))
+        }
+        AffineExpr::Pow(_, _) => return Err(format!("{} is not an affine expression", expr)),
+    })
+}
+
+fn render_operand(
+    expr: &AffineExpr,
+    name: &mut dyn FnMut(&str) -> String,
+) -> Result<String, String> {
+    match expr {
+        AffineExpr::Var(_) | AffineExpr::Const(_) => render_expr(expr, name),
+        _ => Ok(format!("({})", render_expr(expr, name)?)),
+    }
+}
+
+fn render_coeff(coeff: &Coeff, name: &mut dyn FnMut(&str) -> String) -> String {
+    let operand = |coeff: &Coeff, name: &mut dyn FnMut(&str) -> String| match coeff {
+        Coeff::Const(_) | Coeff::ConstVar(_) => render_coeff(coeff, name),
+        _ => format!("({})", render_coeff(coeff, name)),
+    };
+    match coeff {
+        Coeff::Const(value) => value.to_string(),
+        Coeff::ConstVar(var) => name(var),
+        Coeff::Mul(c1, c2) => format!("{} * {}", operand(c1, name), operand(c2, name)),
+        Coeff::Add(c1, c2) => format!("{} + {}", operand(c1, name), operand(c2, name)),
+        Coeff::Neg(c) => format!("-{}", operand(c, name)),
+    }
+}
+
+/// A parsed operation of a region, before the registers are named
+enum Item {
+    For(LoopIter, Vec<Item>),
+    Inst(Instruction),
+    /// An `arith.constant`, used as an immediate operand
+    Constant(String, Operand),
+    /// `(result, op, operands)` of an `arith` or `math` operation
+    Arith(String, String, Vec<String>),
+    Skip,
+}
+
+/// The register holding an SSA value, e.g. `Ra0` for `%Ra_0` and `R1` for `%1`
+fn register_name(value: &str) -> String {
+    let name: String = value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if name.starts_with('R') {
+        name
+    } else {
+        format!("R{}", name)
+    }
+}
+
+/// The operation of `Compute` for an `arith` or `math` operation
+fn compute_op(op: &str) -> Option<&'static str> {
+    Some(match op {
+        "arith.addf" | "arith.addi" => "add",
+        "arith.subf" | "arith.subi" => "sub",
+        "arith.mulf" | "arith.muli" => "mul",
+        "arith.divf" | "arith.divsi" | "arith.divui" => "div",
+        "arith.maximumf" | "arith.maxf" | "arith.maxsi" => "max",
+        "arith.minimumf" | "arith.minf" | "arith.minsi" => "min",
+        "math.fma" => "fma",
+        _ => return None,
+    })
+}
+
+fn build_nest(
+    iter: LoopIter,
+    body: Vec<Item>,
+    constants: &mut HashMap<String, Operand>,
+) -> LoopNest {
+    let mut iters = vec![iter];
+    let mut body = body;
+    // the iterators of a perfect nest form a single loop nest
+    while body.len() == 1 && matches!(body[0], Item::For(_, _)) {
+        if let Some(Item::For(iter, inner)) = body.pop() {
+            iters.push(iter);
+            body = inner;
+        }
+    }
+    let mut instructions = Vec::new();
+    for item in body {
+        match item {
+            Item::For(iter, inner) => instructions.push(Instruction::LoopBody(Box::new(
+                build_nest(iter, inner, constants),
+            ))),
+            Item::Inst(inst) => instructions.push(inst),
+            Item::Constant(value, operand) => {
+                constants.insert(value, operand);
+            }
+            Item::Arith(result, op, operands) => instructions.push(Instruction::Compute(Compute {
+                op,
+                src: operands
+                    .iter()
+                    .map(|value| match constants.get(value) {
+                        Some(operand) => operand.clone(),
+                        None => Operand::Reg(register_name(value)),
+                    })
+                    .collect(),
+                dst: Operand::Reg(register_name(&result)),
+                cond_suffix: None,
+                cond: None,
+            })),
+            Item::Skip => {}
+        }
+    }
+    LoopNest {
+        iters,
+        body: instructions,
+    }
+}
+
+/// Gives the variable named by a token of an affine expression and whether it is a symbol
+type Resolver<'r> = &'r dyn Fn(&str) -> Option<(String, bool)>;
+
+fn ws<'a, O>(
+    inner: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    preceded(multispace0, inner)
+}
+
+fn verify_failure(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Failure(nom::error::Error::new(input, ErrorKind::Verify))
+}
+
+/// The name of an SSA value, without the `%`
+fn parse_ssa(input: &str) -> IResult<&str, &str> {
+    preceded(
+        char('%'),
+        take_while1(|c: char| c.is_ascii_alphanumeric() || "$._-".contains(c)),
+    )(input)
+}
+
+fn parse_int(input: &str) -> IResult<&str, i32> {
+    map_res(recognize(pair(opt(char('-')), digit1)), str::parse)(input)
+}
+
+/// An MLIR affine expression and, if it only uses constants and symbols, its coefficient
+fn parse_affine<'a>(
+    input: &'a str,
+    resolve: Resolver,
+) -> IResult<&'a str, (AffineExpr, Option<Coeff>)> {
+    let (mut input, (mut expr, mut coeff)) = parse_affine_term(input, resolve)?;
+    loop {
+        let (rest, op) = match ws(alt((char('+'), char('-'))))(input) {
+            Ok(res) => res,
+            Err(nom::Err::Error(_)) => return Ok((input, (expr, coeff))),
+            Err(e) => return Err(e),
+        };
+        let (rest, (term, term_coeff)) = parse_affine_term(rest, resolve)?;
+        let term_coeff = match op {
+            '+' => term_coeff,
+            _ => term_coeff.map(|c| Coeff::Neg(Box::new(c))),
+        };
+        coeff = match (coeff, term_coeff) {
+            (Some(c1), Some(c2)) => Some(Coeff::Add(Box::new(c1), Box::new(c2))),
+            _ => None,
+        };
+        expr = match op {
+            '+' => AffineExpr::Add(Box::new(expr), Box::new(term)),
+            _ => AffineExpr::Sub(Box::new(expr), Box::new(term)),
+        };
+        input = rest;
+    }
+}
+
+/// Products, divisions and modulos, one side of which must be a coefficient
+fn parse_affine_term<'a>(
+    input: &'a str,
+    resolve: Resolver,
+) -> IResult<&'a str, (AffineExpr, Option<Coeff>)> {
+    let (mut input, (mut expr, mut coeff)) = parse_affine_factor(input, resolve)?;
+    loop {
+        let (rest, op) =
+            match ws(alt((tag("*"), tag("floordiv"), tag("ceildiv"), tag("mod"))))(input) {
+                Ok(res) => res,
+                Err(nom::Err::Error(_)) => return Ok((input, (expr, coeff))),
+                Err(e) => return Err(e),
+            };
+        let (rest, (factor, factor_coeff)) = parse_affine_factor(rest, resolve)?;
+        (expr, coeff) = match (op, coeff, factor_coeff) {
+            ("*", Some(c1), Some(c2)) => (
+                AffineExpr::Mul(c2.clone(), Box::new(expr)),
+                Some(Coeff::Mul(Box::new(c1), Box::new(c2))),
+            ),
+            ("*", _, Some(c)) => (AffineExpr::Mul(c, Box::new(expr)), None),
+            ("*", Some(c), None) => (AffineExpr::Mul(c, Box::new(factor)), None),
+            ("floordiv", _, Some(c)) => (AffineExpr::Div(Box::new(expr), c), None),
+            ("ceildiv", _, Some(c)) => (AffineExpr::CeilDiv(Box::new(expr), c), None),
+            ("mod", _, Some(c)) => (AffineExpr::Mod(Box::new(expr), c), None),
+            _ => return Err(verify_failure(input)),
+        };
+        input = rest;
+    }
+}
+
+fn parse_affine_factor<'a>(
+    input: &'a str,
+    resolve: Resolver,
+) -> IResult<&'a str, (AffineExpr, Option<Coeff>)> {
+    let (input, _) = multispace0(input)?;
+    if let Ok((rest, _)) = char::<_, nom::error::Error<&str>>('-')(input) {
+        let (rest, (expr, coeff)) = parse_affine_factor(rest, resolve)?;
+        let expr = match expr {
+            AffineExpr::Const(value) => AffineExpr::Const(-value),
+            expr => AffineExpr::Neg(Box::new(expr)),
+        };
+        let coeff = coeff.map(|c| match c {
+            Coeff::Const(value) => Coeff::Const(-value),
+            c => Coeff::Neg(Box::new(c)),
+        });
+        return Ok((rest, (expr, coeff)));
+    }
+    if let Ok((rest, value)) =
+        map_res(digit1::<_, nom::error::Error<&str>>, str::parse::<i32>)(input)
+    {
+        return Ok((rest, (AffineExpr::Const(value), Some(Coeff::Const(value)))));
+    }
+    if let Ok((rest, _)) = char::<_, nom::error::Error<&str>>('(')(input) {
+        return terminated(|i| parse_affine(i, resolve), ws(char(')')))(rest);
+    }
+    let (rest, token) = alt((
+        recognize(tuple((
+            tag("symbol("),
+            multispace0,
+            parse_ssa,
+            multispace0,
+            char(')'),
+        ))),
+        recognize(parse_ssa),
+        alphanumeric1,
+    ))(input)?;
+    match resolve(token) {
+        Some((name, true)) => Ok((
+            rest,
+            (AffineExpr::Var(name.clone()), Some(Coeff::ConstVar(name))),
+        )),
+        Some((name, false)) => Ok((rest, (AffineExpr::Var(name), None))),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Verify,
+        ))),
+    }
+}
+
+/// A variable of an index, `symbol(%N)` for a symbol and `%i` for a dimension
+fn resolve_inline(token: &str) -> Option<(String, bool)> {
+    if let Some(symbol) = token.strip_prefix("symbol(") {
+        let symbol = symbol.trim_end_matches(')').trim().trim_start_matches('%');
+        return Some((symbol.to_string(), true));
+    }
+    token.strip_prefix('%').map(|dim| (dim.to_string(), false))
+}
+
+fn parse_name_list(input: &str) -> IResult<&str, Vec<&str>> {
+    separated_list0(ws(char(',')), ws(alphanumeric1))(input)
+}
+
+fn parse_operand_list(input: &str) -> IResult<&str, Vec<&str>> {
+    separated_list0(ws(char(',')), ws(parse_ssa))(input)
+}
+
+/// A constant, a symbol or an `affine_map` applied to operands, its results being combined
+/// by `max` in a lower bound and by `min` in an upper bound
+fn parse_bound(input: &str, lower: bool) -> IResult<&str, AffineExpr> {
+    if let Ok((rest, value)) = parse_int(input) {
+        return Ok((rest, AffineExpr::Const(value)));
+    }
+    if let Ok((rest, symbol)) = parse_ssa(input) {
+        return Ok((rest, AffineExpr::Var(symbol.to_string())));
+    }
+    let (input, _) = opt(terminated(alt((tag("max"), tag("min"))), multispace1))(input)?;
+    let (input, _) = tuple((tag("affine_map<"), ws(char('('))))(input)?;
+    let (input, dims) = terminated(parse_name_list, ws(char(')')))(input)?;
+    let (input, syms) = opt(delimited(ws(char('[')), parse_name_list, ws(char(']'))))(input)?;
+    let (input, _) = tuple((ws(tag("->")), ws(char('('))))(input)?;
+    // the names of the operands are only known after the map
+    let placeholder = |token: &str| Some((token.to_string(), token.starts_with('s')));
+    let (input, results) = recognize(separated_list1(ws(char(',')), |i| {
+        parse_affine(i, &placeholder)
+    }))(input)?;
+    let (input, _) = tuple((ws(char(')')), ws(char('>'))))(input)?;
+    let (input, dim_operands) = delimited(ws(char('(')), parse_operand_list, ws(char(')')))(input)?;
+    let (input, sym_operands) =
+        opt(delimited(ws(char('[')), parse_operand_list, ws(char(']'))))(input)?;
+    let syms = syms.unwrap_or_default();
+    let sym_operands = sym_operands.unwrap_or_default();
+    if dims.len() != dim_operands.len() || syms.len() != sym_operands.len() {
+        return Err(verify_failure(input));
+    }
+    let resolve = |token: &str| match dims.iter().position(|dim| *dim == token) {
+        Some(pos) => Some((dim_operands[pos].to_string(), false)),
+        None => syms
+            .iter()
+            .position(|sym| *sym == token)
+            .map(|pos| (sym_operands[pos].to_string(), true)),
+    };
+    let (_, results) = separated_list1(ws(char(',')), |i| parse_affine(i, &resolve))(results)?;
+    let bound = results
+        .into_iter()
+        .map(|(expr, _)| expr)
+        .reduce(|e1, e2| match lower {
+            true => AffineExpr::Max(Box::new(e1), Box::new(e2)),
+            false => AffineExpr::Min(Box::new(e1), Box::new(e2)),
+        });
+    match bound {
+        Some(bound) => Ok((input, bound)),
+        None => Err(verify_failure(input)),
+    }
+}
+
+/// e.g. `%A[%i, %j + 1]`
+fn parse_memref_access(input: &str) -> IResult<&str, (String, Vec<AffineExpr>)> {
+    let (input, array) = ws(parse_ssa)(input)?;
+    let (input, addr) = delimited(
+        ws(char('[')),
+        separated_list0(ws(char(',')), |i| parse_affine(i, &resolve_inline)),
+        ws(char(']')),
+    )(input)?;
+    Ok((
+        input,
+        (
+            array.to_string(),
+            addr.into_iter().map(|(expr, _)| expr).collect(),
+        ),
+    ))
+}
+
+fn data_access(array_name: String, addr: Vec<AffineExpr>, reg: String) -> DataAccess {
+    DataAccess {
+        array_name,
+        addr,
+        reg,
+        cond_suffix: None,
+        cond: None,
+    }
+}
+
+/// The rest of the line, e.g. the type of an operation
+fn skip_line(input: &str) -> IResult<&str, &str> {
+    not_line_ending(input)
+}
+
+fn parse_for(input: &str) -> IResult<&str, Item> {
+    let (input, _) = tag("affine.for")(input)?;
+    let (input, iter_name) = ws(parse_ssa)(input)?;
+    let (input, _) = ws(char('='))(input)?;
+    let (input, lower) = preceded(multispace0, |i| parse_bound(i, true))(input)?;
+    let (input, _) = ws(tag("to"))(input)?;
+    let (input, upper) = preceded(multispace0, |i| parse_bound(i, false))(input)?;
+    let (input, step) = opt(preceded(ws(tag("step")), ws(parse_int)))(input)?;
+    let (input, body) = delimited(ws(char('{')), parse_items, ws(char('}')))(input)?;
+    let iter = LoopIter {
+        iter_name: iter_name.to_string(),
+        bounds: (lower, upper),
+        step: Coeff::Const(step.unwrap_or(1)),
+    };
+    Ok((input, Item::For(iter, body)))
+}
+
+/// `// SYNC label` is a synchronization barrier, the other comments are skipped
+fn parse_comment(input: &str) -> IResult<&str, Item> {
+    let (input, comment) = preceded(tag("//"), skip_line)(input)?;
+    let item = match comment.trim().strip_prefix("SYNC ") {
+        Some(label) => Item::Inst(Instruction::SyncBarrier(label.trim().to_string())),
+        None => Item::Skip,
+    };
+    Ok((input, item))
+}
+
+fn parse_store(input: &str) -> IResult<&str, Item> {
+    let (input, value) = preceded(tag("affine.store"), ws(parse_ssa))(input)?;
+    let (input, (array, addr)) = preceded(ws(char(',')), parse_memref_access)(input)?;
+    let (input, _) = preceded(ws(char(':')), skip_line)(input)?;
+    let access = data_access(array, addr, register_name(value));
+    Ok((input, Item::Inst(Instruction::DataStore(access))))
+}
+
+fn parse_prefetch(input: &str) -> IResult<&str, Item> {
+    let (input, (array, addr)) = preceded(tag("affine.prefetch"), parse_memref_access)(input)?;
+    let (input, _) = preceded(ws(char(',')), skip_line)(input)?;
+    let access = data_access(array, addr, String::new());
+    Ok((input, Item::Inst(Instruction::Prefetch(access))))
+}
+
+/// `%r = affine.load ...`, `%r = arith.constant ...` or `%r = arith.<op> ...`
+fn parse_assignment(input: &str) -> IResult<&str, Item> {
+    let (input, result) = terminated(parse_ssa, ws(char('=')))(input)?;
+    let (input, op) = ws(recognize(tuple((
+        alpha1,
+        char('.'),
+        take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+    ))))(input)?;
+    let (input, item) = match op {
+        "affine.load" => {
+            let (input, (array, addr)) = parse_memref_access(input)?;
+            let access = data_access(array, addr, register_name(result));
+            (input, Item::Inst(Instruction::DataLoad(access)))
+        }
+        "arith.constant" => {
+            let (input, value) = ws(recognize(tuple((
+                opt(char('-')),
+                digit1,
+                opt(pair(char('.'), digit1)),
+                opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+            ))))(input)?;
+            let operand = match value.parse::<i32>() {
+                Ok(value) => Operand::ImmInt(value),
+                Err(_) => match value.parse::<f64>() {
+                    Ok(value) => Operand::ImmFloat(value),
+                    Err(_) => return Err(verify_failure(input)),
+                },
+            };
+            (input, Item::Constant(result.to_string(), operand))
+        }
+        op => {
+            let compute_op = match compute_op(op) {
+                Some(compute_op) => compute_op,
+                None => return Err(verify_failure(input)),
+            };
+            let (input, operands) = separated_list1(ws(char(',')), ws(parse_ssa))(input)?;
+            let operands = operands.into_iter().map(String::from).collect();
+            (
+                input,
+                Item::Arith(result.to_string(), compute_op.to_string(), operands),
+            )
+        }
+    };
+    let (input, _) = preceded(ws(char(':')), skip_line)(input)?;
+    Ok((input, item))
+}
+
+fn parse_items(input: &str) -> IResult<&str, Vec<Item>> {
+    many0(ws(alt((
+        parse_for,
+        parse_comment,
+        parse_store,
+        parse_prefetch,
+        map(pair(tag("affine.yield"), skip_line), |_| Item::Skip),
+        parse_assignment,
+    ))))(input)
+}
+
+/// `func.func @name(...) { ... return }`, the arguments being ignored
+fn parse_func(input: &str) -> IResult<&str, Vec<Item>> {
+    let (input, _) = tuple((
+        tag("func.func"),
+        ws(char('@')),
+        take_while1(|c: char| c.is_ascii_alphanumeric() || "$._-".contains(c)),
+        ws(char('(')),
+        take_until(")"),
+        char(')'),
+        ws(char('{')),
+    ))(input)?;
+    terminated(parse_items, tuple((opt(ws(tag("return"))), ws(char('}')))))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::representations::loops::LoopNest;
+
+    #[test]
+    fn test_to_mlir_affine_dialect() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..M)
+  - for j in (0..N).step(2)
+  - for k in (max(0, i - 4)..K)
+body:
+  - Ra <= A[i][k]
+  - Rb <= B[k][j + 1]
+  - Rc <= C[i][j]
+  - mac Rc Ra, Rb
+  - add Rc Rc, $1
+  - Rc => C[i][j]
+"#,
+        )
+        .unwrap();
+        let mlir = loop_nest.to_mlir_affine_dialect().unwrap();
+        assert_eq!(
+            mlir,
+            "func.func @loop_nest(%A: memref<?x?xf32>, %B: memref<?x?xf32>, %C: memref<?x?xf32>, %M: index, %N: index, %K: index) {
+  affine.for %i = 0 to %M {
+    affine.for %j = 0 to %N step 2 {
+      affine.for %k = max affine_map<(d0) -> (0, d0 - 4)>(%i) to %K {
+        %Ra_0 = affine.load %A[%i, %k] : memref<?x?xf32>
+        %Rb_1 = affine.load %B[%k, %j + 1] : memref<?x?xf32>
+        %Rc_2 = affine.load %C[%i, %j] : memref<?x?xf32>
+        %prod_3 = arith.mulf %Ra_0, %Rb_1 : f32
+        %Rc_4 = arith.addf %Rc_2, %prod_3 : f32
+        %cst_5 = arith.constant 1.000000e0 : f32
+        %Rc_6 = arith.addf %Rc_4, %cst_5 : f32
+        affine.store %Rc_6, %C[%i, %j] : memref<?x?xf32>
+      }
+    }
+  }
+  return
+}"
+        );
+
+        // every loop is closed and every value is defined before its use
+        assert_eq!(mlir.matches('{').count(), mlir.matches('}').count());
+        for line in mlir.lines().filter(|line| line.contains(" = arith.")) {
+            let operands = line.split(" = ").nth(1).unwrap();
+            for value in operands.split([' ', ',']) {
+                if value.starts_with('%') {
+                    assert!(mlir.contains(&format!("{} = ", value)), "{}", value);
+                }
+            }
+        }
+
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..N).step(SIMD_W)
+body:
+  - Ra <= A[i]
+"#,
+        )
+        .unwrap();
+        assert!(loop_nest
+            .to_mlir_affine_dialect()
+            .unwrap_err()
+            .contains("step SIMD_W"));
+    }
+
+    #[test]
+    fn test_from_mlir_affine_str() {
+        let loop_nest = LoopNest::from_mlir_affine_str(
+            r#"
+affine.for %i = 0 to %N {
+  affine.for %j = 0 to 16 step 4 {
+    %a = affine.load %A[%i, %j] : memref<?x?xf32>
+    %b = affine.load %B[%j floordiv 4, symbol(%M) * %i] : memref<?x?xf32>
+    %c = arith.mulf %a, %b : f32
+    %two = arith.constant 2.0 : f32
+    %d = arith.addf %c, %two : f32
+    affine.store %d, %C[%i, %j + 1] : memref<?x?xf32>
+  }
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            loop_nest.to_string(),
+            "for i in (0..N)
+  for j in (0..16).step(4)
+    Ra <= A[i][j]
+    Rb <= B[j / 4][M * i]
+    mul Rc Ra, Rb
+    add Rd Rc, $2.0
+    Rd => C[i][j + 1]
+"
+        );
+
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..M)
+  - for k in (max(0, i - 4)..K)
+body:
+  - Ra <= A[i][k]
+  - iters:
+      - for j in (0..N).step(2)
+    body:
+      - Rc <= C[i][j]
+      - mac Rc Ra, Rc
+      - Rc => C[i][j]
+  - SYNC done
+"#,
+        )
+        .unwrap();
+        let mlir = loop_nest.to_mlir_affine_dialect().unwrap();
+        let parsed = LoopNest::from_mlir_affine_str(&mlir).unwrap();
+        assert_eq!(parsed.iters, loop_nest.iters);
+        assert_eq!(
+            parsed.to_string(),
+            "for i in (0..M)
+  for k in (max(0, i - 4)..K)
+    Ra0 <= A[i][k]
+    for j in (0..N).step(2)
+      Rc1 <= C[i][j]
+      mul Rprod2 Ra0, Rc1
+      add Rc3 Rc1, Rprod2
+      Rc3 => C[i][j]
+    SYNC done
+"
+        );
+
+        let error = LoopNest::from_mlir_affine_str("affine.for %i = 0 to %N {\n  scf.yield\n}")
+            .unwrap_err();
+        assert!(error.contains("at offset"), "{}", error);
+        let error = LoopNest::from_mlir_affine_str(
+            "affine.for %i = 0 to 4 {\n}\naffine.for %j = 0 to 4 {\n}",
+        )
+        .unwrap_err();
+        assert_eq!(error, "Expected a single top-level affine.for, found 2");
+    }
+}
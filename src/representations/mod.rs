@@ -4,4 +4,5 @@ pub mod arch;
 pub mod instruction;
 pub mod loops;
 pub mod mapping;
+pub mod mlir;
 pub mod transforms;
@@ -1,4 +1,10 @@
 use core::fmt;
+use std::{fs, path::Path};
+
+use super::loops::LoopNest;
+use crate::error::ParseError;
+
+use log::warn;
 
 use nom::{
     branch::alt,
@@ -6,22 +12,37 @@ use nom::{
     character::complete::{
         char, digit1, line_ending, multispace0, multispace1, not_line_ending, space0,
     },
-    combinator::{cut, map, opt},
+    combinator::{cut, map, map_res, opt},
+    error::ErrorKind,
     multi::many0,
     sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
+use serde::{Deserialize, Deserializer, Serialize};
 /// A transform is a way to modify a loop nest. It can be a spatial or temporal mapping, tiling, or renaming.
 /// Tiling: Tiles a loop with a given factor.
 /// Renaming: Renames a loop iterator.
-#[derive(Debug, PartialEq)]
+/// Interchange: Swaps the loops of two iterators, formerly `Reorder`.
+/// Unroll: Inlines the first `factor` iterations of an iterator as constants, followed by a residual loop.
+/// Skew: Skews the first iterator by the second one: `i' = i + factor * j`.
+/// Shift: Shifts an iterator by a constant offset: `i' = i + offset`.
+/// Scale: Scales an iterator by a positive factor: `i' = factor * i`.
+/// Fusion: Fuses the loop of the second iterator into the consecutive loop of the first one.
+/// Distribution: Splits the body of the loop of an iterator at an instruction index into two loops.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Transform {
     Tiling((String, String, i32)),
     Renaming((String, String)),
-    Reorder((String, String)),
+    Interchange((String, String)),
+    Unroll(String, u32),
+    Skew(String, String, i32),
+    Shift(String, i32),
+    Scale(String, i32),
+    Fusion(String, String),
+    Distribution(String, usize),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Transforms {
     pub transforms: Vec<Transform>,
 }
@@ -34,44 +55,53 @@ fn parse_identifier(input: &str) -> IResult<&str, &str> {
     )(input)
 }
 
+/// `name` followed by `body`, which is required once the keyword is found, so that the error
+/// is reported in the body rather than at the keyword
+fn keyword<'a, O>(
+    name: &'static str,
+    body: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    preceded(delimited(space0, tag(name), space0), cut(body))
+}
+
 fn parse_tiling_target(input: &str) -> IResult<&str, (String, String, i32)> {
     let (input, _) = delimited(space0, char('('), space0)(input)?;
-    let (input, first) = parse_identifier(input)?;
-    let (input, _) = delimited(space0, char(','), space0)(input)?;
-    let (input, second) = parse_identifier(input)?;
-    let (input, _) = delimited(space0, char(')'), space0)(input)?;
-    let (input, _) = tag("by")(input)?;
-    let (input, _) = space0(input)?;
-    let (input, factor) = digit1(input)?;
-    Ok((
-        input,
-        (
-            first.to_string(),
-            second.to_string(),
-            factor.parse().unwrap(),
-        ),
-    ))
+    // only a tiling has a parenthesized target
+    let (input, (first, _, second, _, _, _, factor)) = cut(tuple((
+        parse_identifier,
+        delimited(space0, char(','), space0),
+        parse_identifier,
+        delimited(space0, char(')'), space0),
+        tag("by"),
+        space0,
+        map_res(digit1, str::parse),
+    )))(input)?;
+    Ok((input, (first.to_string(), second.to_string(), factor)))
 }
 
-fn parse_tiling(input: &str) -> IResult<&str, Transform> {
-    let prefix = delimited(space0, tag("!Tiling"), space0);
-    let (input, (_, old_var, _, (old_var1, new_var, factor))) = tuple((
-        opt(prefix),
-        parse_identifier,
-        terminated(tag("->"), space0),
-        parse_tiling_target,
-    ))(input)?;
-    assert_eq!(old_var, old_var1, "When tiling, the old variable should be the same as the first variable in the target, you can use renanming after to change the name of the new variable");
+// When tiling, the old variable should be the same as the first variable in the target,
+// renaming can be used afterwards to change the name of the new variable
+fn parse_tiling_body(input: &str) -> IResult<&str, Transform> {
+    let (target, (old_var, _)) = tuple((parse_identifier, terminated(tag("->"), space0)))(input)?;
+    let (input, (old_var1, new_var, factor)) = parse_tiling_target(target)?;
+    if old_var != old_var1 {
+        return Err(nom::Err::Failure(nom::error::Error::new(
+            target,
+            ErrorKind::Verify,
+        )));
+    }
     Ok((
         input,
         Transform::Tiling((old_var.to_string(), new_var.to_string(), factor)),
     ))
 }
 
-fn parse_renaming(input: &str) -> IResult<&str, Transform> {
-    let prefix = delimited(space0, tag("!Renaming"), space0);
-    let (input, (_, old_var, _, new_var)) = tuple((
-        opt(prefix),
+fn parse_tiling(input: &str) -> IResult<&str, Transform> {
+    alt((keyword("!Tiling", parse_tiling_body), parse_tiling_body))(input)
+}
+
+fn parse_renaming_body(input: &str) -> IResult<&str, Transform> {
+    let (input, (old_var, _, new_var)) = tuple((
         preceded(space0, parse_identifier),
         terminated(tag("->"), space0),
         parse_identifier,
@@ -82,20 +112,111 @@ fn parse_renaming(input: &str) -> IResult<&str, Transform> {
     ))
 }
 
-fn parse_reorder(input: &str) -> IResult<&str, Transform> {
-    let prefix = delimited(space0, tag("!Reorder"), space0);
-    let (input, (_, old_var, _, new_var)) = tuple((
-        opt(prefix),
+fn parse_renaming(input: &str) -> IResult<&str, Transform> {
+    alt((
+        keyword("!Renaming", parse_renaming_body),
+        parse_renaming_body,
+    ))(input)
+}
+
+fn parse_interchange_body(input: &str) -> IResult<&str, Transform> {
+    let (input, (old_var, _, new_var)) = tuple((
         preceded(space0, parse_identifier),
         terminated(tag("<->"), space0),
         parse_identifier,
     ))(input)?;
     Ok((
         input,
-        Transform::Reorder((old_var.to_string(), new_var.to_string())),
+        Transform::Interchange((old_var.to_string(), new_var.to_string())),
+    ))
+}
+
+fn parse_interchange(input: &str) -> IResult<&str, Transform> {
+    alt((
+        keyword("!Interchange", parse_interchange_body),
+        // `!Reorder` is the deprecated spelling of `!Interchange`
+        map(keyword("!Reorder", parse_interchange_body), |transform| {
+            warn!("`!Reorder` is deprecated, use `!Interchange` instead");
+            transform
+        }),
+        parse_interchange_body,
+    ))(input)
+}
+
+fn parse_unroll(input: &str) -> IResult<&str, Transform> {
+    let (input, (iter, _, factor)) = keyword(
+        "!Unroll",
+        tuple((
+            parse_identifier,
+            terminated(tag("by"), space0),
+            map_res(digit1, str::parse),
+        )),
+    )(input)?;
+    Ok((input, Transform::Unroll(iter.to_string(), factor)))
+}
+
+fn parse_skew(input: &str) -> IResult<&str, Transform> {
+    let (input, (outer, _, factor, _, inner)) = keyword(
+        "!Skew",
+        tuple((
+            parse_identifier,
+            terminated(tag("by"), space0),
+            nom::character::complete::i32,
+            delimited(space0, char('*'), space0),
+            parse_identifier,
+        )),
+    )(input)?;
+    Ok((
+        input,
+        Transform::Skew(outer.to_string(), inner.to_string(), factor),
     ))
 }
 
+fn parse_shift(input: &str) -> IResult<&str, Transform> {
+    let (input, (iter, _, offset)) = keyword(
+        "!Shift",
+        tuple((
+            parse_identifier,
+            terminated(tag("by"), space0),
+            nom::character::complete::i32,
+        )),
+    )(input)?;
+    Ok((input, Transform::Shift(iter.to_string(), offset)))
+}
+
+fn parse_scale(input: &str) -> IResult<&str, Transform> {
+    let (input, (iter, _, factor)) = keyword(
+        "!Scale",
+        tuple((
+            parse_identifier,
+            terminated(tag("by"), space0),
+            map_res(digit1, str::parse),
+        )),
+    )(input)?;
+    Ok((input, Transform::Scale(iter.to_string(), factor)))
+}
+
+fn parse_fusion(input: &str) -> IResult<&str, Transform> {
+    let (input, (first, second)) =
+        keyword("!Fuse", tuple((parse_identifier, parse_identifier)))(input)?;
+    Ok((
+        input,
+        Transform::Fusion(first.to_string(), second.to_string()),
+    ))
+}
+
+fn parse_distribution(input: &str) -> IResult<&str, Transform> {
+    let (input, (iter, _, index)) = keyword(
+        "!Distribute",
+        tuple((
+            parse_identifier,
+            terminated(tag("at"), space0),
+            map_res(digit1, str::parse),
+        )),
+    )(input)?;
+    Ok((input, Transform::Distribution(iter.to_string(), index)))
+}
+
 fn parse_comment(input: &str) -> IResult<&str, ()> {
     let (input, _) = tuple((multispace0, tag("//"), not_line_ending, opt(line_ending)))(input)?;
     Ok((input, ()))
@@ -107,7 +228,17 @@ fn ws_and_comments(input: &str) -> IResult<&str, ()> {
 }
 
 fn parse_transform(input: &str) -> IResult<&str, Transform> {
-    cut(alt((parse_tiling, parse_renaming, parse_reorder)))(input)
+    cut(alt((
+        parse_tiling,
+        parse_renaming,
+        parse_interchange,
+        parse_unroll,
+        parse_skew,
+        parse_shift,
+        parse_scale,
+        parse_fusion,
+        parse_distribution,
+    )))(input)
 }
 
 fn parse_transforms(input: &str) -> IResult<&str, Transforms> {
@@ -145,19 +276,221 @@ fn parse_transforms(input: &str) -> IResult<&str, Transforms> {
 }
 
 impl Transforms {
-    pub fn from_str(input: &str) -> Result<Transforms, String> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &str) -> Result<Transforms, ParseError> {
         match parse_transforms(input) {
             Ok((_, transforms)) => Ok(transforms),
-            Err(e) => Err(format!("{:?}", e)),
+            Err(e) => {
+                let mut error = ParseError::from_nom(input, e);
+                // every transform of the list is on its own line starting with "-"
+                let entries = input[..error.offset]
+                    .lines()
+                    .filter(|line| line.trim_start().starts_with('-'))
+                    .count();
+                if entries > 0 {
+                    error.message =
+                        format!("Error in transform {}: {}", entries - 1, error.message);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Read a sequence of transforms from a `.trf` file, a YAML list of transforms as written by
+    /// `to_yaml_file`
+    pub fn from_trf_file(path: &Path) -> Result<Transforms, String> {
+        let trf_str = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read the transforms {}: {}", path.display(), e))?;
+        Transforms::from_str(&trf_str)
+            .map_err(|e| format!("Cannot parse the transforms {}: {}", path.display(), e))
+    }
+
+    /// Read a sequence of transforms from a YAML file, whatever its extension, see `from_trf_file`
+    pub fn from_yaml_file(path: &Path) -> Result<Transforms, String> {
+        Transforms::from_trf_file(path)
+    }
+
+    /// Write the transforms as a YAML list, readable by `from_yaml_file` and `from_trf_file`
+    pub fn to_yaml_file(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_string())
+            .map_err(|e| format!("Cannot write the transforms {}: {}", path.display(), e))
+    }
+
+    /// Check that every transform of the sequence can be applied to the loop nest
+    /// The iterator names are tracked through the sequence, all the errors found are returned
+    pub fn validate_sequence(&self, loop_nest: &LoopNest) -> Result<(), Vec<String>> {
+        // the distributed loops of an iterator share its name
+        let mut iters: Vec<String> = Vec::new();
+        for iter in loop_nest.all_iters() {
+            if !iters.contains(&iter.iter_name) {
+                iters.push(iter.iter_name.clone());
+            }
+        }
+        let mut errors = Vec::new();
+        for (idx, transform) in self.transforms.iter().enumerate() {
+            let mut error = |message: String| {
+                errors.push(format!("Transform {} ({}): {}", idx, transform, message));
+            };
+            let missing: Vec<&String> = transform
+                .iter_names()
+                .into_iter()
+                .filter(|name| !iters.contains(name))
+                .collect();
+            if !missing.is_empty() {
+                for name in missing {
+                    error(format!(
+                        "the iterator {} does not exist, current iterators: {:?}",
+                        name, iters
+                    ));
+                }
+                continue;
+            }
+            match transform {
+                Transform::Tiling((old, new, factor)) => {
+                    if *factor <= 0 {
+                        error(format!("the tiling factor {} should be positive", factor));
+                    }
+                    if iters.contains(new) {
+                        error(format!("the new iterator {} already exists", new));
+                    } else {
+                        let pos = iters.iter().position(|name| name == old).unwrap();
+                        iters.insert(pos + 1, new.clone());
+                    }
+                }
+                Transform::Renaming((old, new)) => {
+                    if old != new && iters.contains(new) {
+                        error(format!("the new name {} already exists", new));
+                    } else {
+                        let pos = iters.iter().position(|name| name == old).unwrap();
+                        iters[pos] = new.clone();
+                    }
+                }
+                Transform::Interchange((iter1, iter2)) => {
+                    if iter1 == iter2 {
+                        error(format!(
+                            "cannot interchange the iterator {} with itself",
+                            iter1
+                        ));
+                    }
+                }
+                Transform::Skew(outer, inner, _) => {
+                    if outer == inner {
+                        error(format!("cannot skew the iterator {} by itself", outer));
+                    }
+                }
+                Transform::Unroll(_, factor) => {
+                    if *factor == 0 {
+                        error("the unrolling factor should be positive".to_string());
+                    }
+                }
+                Transform::Scale(_, factor) => {
+                    if *factor <= 0 {
+                        error(format!("the scaling factor {} should be positive", factor));
+                    }
+                }
+                // the loops of a distribution can be fused back with the same iterator
+                Transform::Fusion(first, second) => {
+                    if first != second {
+                        iters.retain(|name| name != second);
+                    }
+                }
+                Transform::Distribution(_, index) => {
+                    if *index == 0 {
+                        error("cannot distribute a loop at the first instruction".to_string());
+                    }
+                }
+                Transform::Shift(_, _) => {}
+            }
         }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The sequence undoing this sequence on the loop nest, the inverses are applied in reverse order
+    pub fn inverse(&self, loop_nest: &LoopNest) -> Result<Transforms, String> {
+        self.validate_sequence(loop_nest)
+            .map_err(|errors| errors.join("\n"))?;
+        let transforms = self
+            .transforms
+            .iter()
+            .rev()
+            .map(|transform| {
+                transform
+                    .inverse()
+                    .ok_or_else(|| format!("The transform {} is not invertible", transform))
+            })
+            .collect::<Result<Vec<Transform>, String>>()?;
+        Ok(Transforms { transforms })
     }
 }
 
 impl Transform {
-    pub fn from_str(input: &str) -> Result<Transform, String> {
+    /// The interchange of the loops of `iter1` and `iter2`, the former `Reorder` variant
+    #[deprecated(note = "`Transform::Reorder` was renamed to `Transform::Interchange`")]
+    pub fn reorder(iter1: &str, iter2: &str) -> Transform {
+        Transform::Interchange((iter1.to_string(), iter2.to_string()))
+    }
+
+    /// The iterators the transform is applied to
+    pub fn iter_names(&self) -> Vec<&String> {
+        match self {
+            Transform::Tiling((old, _, _))
+            | Transform::Renaming((old, _))
+            | Transform::Unroll(old, _)
+            | Transform::Shift(old, _)
+            | Transform::Scale(old, _)
+            | Transform::Distribution(old, _) => vec![old],
+            Transform::Interchange((iter1, iter2))
+            | Transform::Skew(iter1, iter2, _)
+            | Transform::Fusion(iter1, iter2) => vec![iter1, iter2],
+        }
+    }
+
+    /// The transform undoing this one, if any
+    /// Tiling and unrolling change the structure of the loop nest, scaling and skewing change the
+    /// bounds in a way that cannot be recovered, so they are not invertible, and neither is a
+    /// shift by `i32::MIN` whose opposite does not fit in an `i32`
+    pub fn inverse(&self) -> Option<Transform> {
+        match self {
+            Transform::Renaming((old, new)) => {
+                Some(Transform::Renaming((new.clone(), old.clone())))
+            }
+            Transform::Interchange((iter1, iter2)) => {
+                Some(Transform::Interchange((iter1.clone(), iter2.clone())))
+            }
+            Transform::Shift(iter, offset) => offset
+                .checked_neg()
+                .map(|opposite| Transform::Shift(iter.clone(), opposite)),
+            Transform::Tiling(_)
+            | Transform::Unroll(_, _)
+            | Transform::Skew(_, _, _)
+            | Transform::Scale(_, _)
+            | Transform::Fusion(_, _)
+            | Transform::Distribution(_, _) => None,
+        }
+    }
+
+    /// The tilings building a tile hierarchy of the iterator, each `(new_name, factor)` level
+    /// tiles the innermost loop of the previous one, e.g. `m` by 8 into `mt` then `mt` by 4
+    /// into `mtt` gives the loops `m`, `mt` and `mtt` of extents `M / 8`, 2 and 4
+    pub fn pipeline_for_tiling(iter: &str, tiles: &[(String, i32)]) -> Transforms {
+        let mut transforms = Vec::new();
+        let mut tiled = iter.to_string();
+        for (new_name, factor) in tiles {
+            transforms.push(Transform::Tiling((tiled, new_name.clone(), *factor)));
+            tiled = new_name.clone();
+        }
+        Transforms { transforms }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &str) -> Result<Transform, ParseError> {
         match parse_transform(input) {
             Ok((_, transform)) => Ok(transform),
-            Err(e) => Err(format!("{:?}", e)),
+            Err(e) => Err(ParseError::from_nom(input, e)),
         }
     }
 }
@@ -175,13 +508,42 @@ impl fmt::Display for Transform {
             Transform::Renaming((old_var, new_var)) => {
                 write!(f, "!Renaming {} -> {}", old_var, new_var)
             }
-            Transform::Reorder((old_var, new_var)) => {
-                write!(f, "!Reorder {} <-> {}", old_var, new_var)
+            Transform::Interchange((old_var, new_var)) => {
+                write!(f, "!Interchange {} <-> {}", old_var, new_var)
+            }
+            Transform::Unroll(iter, factor) => write!(f, "!Unroll {} by {}", iter, factor),
+            Transform::Skew(outer, inner, factor) => {
+                write!(f, "!Skew {} by {} * {}", outer, factor, inner)
+            }
+            Transform::Shift(iter, offset) => write!(f, "!Shift {} by {}", iter, offset),
+            Transform::Scale(iter, factor) => write!(f, "!Scale {} by {}", iter, factor),
+            Transform::Fusion(first, second) => write!(f, "!Fuse {} {}", first, second),
+            Transform::Distribution(iter, index) => {
+                write!(f, "!Distribute {} at {}", iter, index)
             }
         }
     }
 }
 
+impl<'de> Deserialize<'de> for Transform {
+    fn deserialize<D>(deserializer: D) -> Result<Transform, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Transform::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Transform {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
 impl fmt::Display for Transforms {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for transform in &self.transforms {
@@ -212,16 +574,26 @@ mod tests {
         let expected_transform = Transform::Renaming(("m".to_string(), "ty".to_string()));
         assert_eq!(transform, expected_transform);
 
-        let test_str = "!Reorder y <-> tn";
+        let test_str = "!Interchange y <-> tn";
         let transform: Transform = Transform::from_str(test_str).unwrap();
-        let expected_transform = Transform::Reorder(("y".to_string(), "tn".to_string()));
+        let expected_transform = Transform::Interchange(("y".to_string(), "tn".to_string()));
         assert_eq!(transform, expected_transform);
 
         let test_str = "x <-> y";
         let transform: Transform = Transform::from_str(test_str).unwrap();
-        let expected_transform = Transform::Reorder(("x".to_string(), "y".to_string()));
+        let expected_transform = Transform::Interchange(("x".to_string(), "y".to_string()));
         assert_eq!(transform, expected_transform);
 
+        // the deprecated `!Reorder` spelling is still accepted
+        let test_str = "!Reorder y <-> tn";
+        let transform: Transform = Transform::from_str(test_str).unwrap();
+        let expected_transform = Transform::Interchange(("y".to_string(), "tn".to_string()));
+        assert_eq!(transform, expected_transform);
+        assert_eq!(transform.to_string(), "!Interchange y <-> tn");
+        #[allow(deprecated)]
+        let reorder = Transform::reorder("y", "tn");
+        assert_eq!(reorder, expected_transform);
+
         let test_str = r#"
 // tiling n into simd by 4
  - n -> (n, simd) by 4
@@ -229,8 +601,14 @@ mod tests {
  - !Tiling m -> (m, y) by 8
  - m -> ty
  - n -> tn
- - !Reorder y <-> tn
+  - !Interchange y <-> tn
  - x <-> y
+ - !Unroll x by 2
+ - !Skew x by -2 * y
+ - !Shift y by -1
+ - !Scale x by 4
+ - !Fuse x y
+ - !Distribute x at 3
         "#;
         let transforms: Transforms = Transforms::from_str(test_str).unwrap();
         let expected_transforms = Transforms {
@@ -240,8 +618,14 @@ mod tests {
                 Transform::Tiling(("m".to_string(), "y".to_string(), 8)),
                 Transform::Renaming(("m".to_string(), "ty".to_string())),
                 Transform::Renaming(("n".to_string(), "tn".to_string())),
-                Transform::Reorder(("y".to_string(), "tn".to_string())),
-                Transform::Reorder(("x".to_string(), "y".to_string())),
+                Transform::Interchange(("y".to_string(), "tn".to_string())),
+                Transform::Interchange(("x".to_string(), "y".to_string())),
+                Transform::Unroll("x".to_string(), 2),
+                Transform::Skew("x".to_string(), "y".to_string(), -2),
+                Transform::Shift("y".to_string(), -1),
+                Transform::Scale("x".to_string(), 4),
+                Transform::Fusion("x".to_string(), "y".to_string()),
+                Transform::Distribution("x".to_string(), 3),
             ],
         };
         assert_eq!(transforms, expected_transforms);
@@ -255,4 +639,93 @@ mod tests {
             serialized
         );
     }
+
+    #[test]
+    fn test_parse_error() {
+        let error = Transforms::from_str(
+            r#"
+ - n -> (n, simd) by 4
+ // the comments are not counted
+ - m -> ty
+ - !Unroll n by x
+ - !Shift m by 1
+"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.message.starts_with("Error in transform 2:"),
+            "{}",
+            error
+        );
+
+        // the tiled iterator must be the first one of the target
+        let error = Transform::from_str("!Tiling i -> (j, k) by 4").unwrap_err();
+        assert_eq!(error.offset, 13);
+        let error = Transform::from_str("i -> (i, k) by 99999999999").unwrap_err();
+        assert_eq!(error.message, "the number is out of range");
+        let error = Transforms::from_str(" - !Unroll i by 99999999999").unwrap_err();
+        assert_eq!(error.offset, 16);
+        assert!(Transforms::from_str(" - !Scale i by 99999999999").is_err());
+        assert!(Transforms::from_str(" - !Distribute i at 99999999999999999999999").is_err());
+    }
+
+    #[test]
+    fn test_validate_sequence() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..16)
+  - for n in (0..16)
+body:
+  - Ra <= A[m][n]
+"#,
+        )
+        .unwrap();
+        let transforms = Transforms::from_str(
+            r#"
+ - n -> (n, x) by 4
+ - m -> y
+  - !Interchange y <-> x
+ - !Unroll n by 2
+"#,
+        )
+        .unwrap();
+        assert_eq!(transforms.validate_sequence(&loop_nest), Ok(()));
+
+        let transforms = Transforms::from_str(
+            r#"
+ - k -> (k, x) by 4
+ - m -> n
+  - !Interchange m <-> m
+ - m -> y
+ - !Shift m by 1
+ - !Scale y by 2
+"#,
+        )
+        .unwrap();
+        let errors = transforms.validate_sequence(&loop_nest).unwrap_err();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert!(errors[0].starts_with("Transform 0"));
+        assert!(errors[0].contains("the iterator k does not exist"));
+        assert!(errors[1].contains("the new name n already exists"));
+        assert!(errors[2].contains("cannot interchange the iterator m with itself"));
+        // m was renamed to y
+        assert!(errors[3].starts_with("Transform 4"));
+    }
+
+    #[test]
+    fn test_pipeline_for_tiling() {
+        let tiles = [("mt".to_string(), 8), ("mtt".to_string(), 4)];
+        let transforms = Transform::pipeline_for_tiling("m", &tiles);
+        assert_eq!(
+            transforms,
+            Transforms::from_str(" - m -> (m, mt) by 8\n - mt -> (mt, mtt) by 4").unwrap()
+        );
+        let loop_nest: LoopNest =
+            serde_yaml::from_str("iters:\n  - for m in (0..64)\nbody:\n  - Ra <= A[m]\n").unwrap();
+        assert_eq!(transforms.validate_sequence(&loop_nest), Ok(()));
+        assert!(Transform::pipeline_for_tiling("m", &[])
+            .transforms
+            .is_empty());
+    }
 }
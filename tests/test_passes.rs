@@ -2,7 +2,8 @@ use std::{fs, path::Path};
 
 use loopana::{
     passes::{
-        mem_access_analysis::MemAccessAnalysis, pass_pipeline::PassPipeline, workspace::Workspace, free_dim_analysis::FreeDimAnalysis,
+        free_dim_analysis::FreeDimAnalysis, mem_access_analysis::MemAccessAnalysis,
+        pass_pipeline::PassPipeline, workspace::Workspace,
     },
     representations::loops::LoopNest,
 };
@@ -13,13 +14,12 @@ fn test_passes() {
     // load the loop nest
     let manifest = env!("CARGO_MANIFEST_DIR");
     let file_path = Path::new(manifest).join("example/transformed_prob.loop");
-    let yaml_str = fs::read_to_string(file_path).expect("Failed to read YAML file");
-    let loop_nest: LoopNest = serde_yaml::from_str(&yaml_str).expect("Failed to deserialize YAML");
+    let loop_nest = LoopNest::from_loop_file(&file_path).unwrap();
 
     let mut workspace = Workspace::new(loop_nest, None);
     let mut pass_pipeline = PassPipeline::new();
-    let mem_access_pass = MemAccessAnalysis;
-    let free_dim_pass = FreeDimAnalysis;
+    let mem_access_pass = MemAccessAnalysis::default();
+    let free_dim_pass = FreeDimAnalysis::default();
     pass_pipeline.register_pass(Box::new(mem_access_pass));
     pass_pipeline.register_pass(Box::new(free_dim_pass));
     pass_pipeline.run(&mut workspace).unwrap();
@@ -27,5 +27,1572 @@ fn test_passes() {
     let output_file_path = Path::new(manifest).join("example/transformed_prob.ana");
     let output_str = format!("{}", workspace);
     fs::write(output_file_path, output_str).expect("Failed to write to output file");
-    return;
+}
+
+#[test]
+fn test_workspace_from_files() {
+    let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("example");
+    let workspace = Workspace::from_files(
+        &example.join("prob.loop"),
+        &example.join("mesh_distributed-mem.arch"),
+    )
+    .unwrap();
+    assert_eq!(workspace.loop_nest.iters.len(), 3);
+    assert_eq!(workspace.arch.unwrap().total_pe_count(), 64);
+
+    let workspace =
+        Workspace::from_files(&example.join("prob.loop"), &example.join("missing.arch")).unwrap();
+    assert!(workspace.arch.is_none());
+
+    assert!(
+        Workspace::from_files(&example.join("missing.loop"), &example.join("missing.arch"))
+            .is_err()
+    );
+    // an existing file which is not an architecture is an error
+    assert!(Workspace::from_files(&example.join("prob.loop"), &example.join("prob.loop")).is_err());
+}
+
+mod derived_passes {
+    use core::fmt;
+
+    use loopana::passes::feature::Feature;
+    use loopana::passes::pass_pipeline::PassPipeline;
+    use loopana::passes::passes::{InstPass, IterPass, LoopPass, PassInfo, PassRun, WorkspacePass};
+    use loopana::passes::property::{Property, PropertyHook};
+    use loopana::passes::workspace::Workspace;
+    use loopana::representations::instruction::Instruction;
+    use loopana::representations::loops::{LoopIter, LoopNest};
+    use loopana::representations::transforms::{Transform, Transforms};
+    use property_hood_id_derive::{InstPass, IterPass, LoopPass, WorkspacePass};
+
+    #[derive(Clone)]
+    struct TripCountProp {
+        trip_count: i32,
+    }
+
+    impl Property for TripCountProp {
+        fn property_id(&self) -> String {
+            "TripCountProp".to_string()
+        }
+    }
+
+    impl fmt::Display for TripCountProp {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Trip count: {}", self.trip_count)
+        }
+    }
+
+    #[derive(IterPass)]
+    struct TripCountPass;
+
+    impl IterPass for TripCountPass {
+        fn pass_iter(&self, iter: &LoopIter) -> Vec<Box<dyn Property>> {
+            vec![Box::new(TripCountProp {
+                trip_count: iter.trip_count(&Default::default()).unwrap() as i32,
+            })]
+        }
+    }
+
+    #[derive(Clone)]
+    struct IsComputeProp;
+
+    impl Property for IsComputeProp {
+        fn property_id(&self) -> String {
+            "IsComputeProp".to_string()
+        }
+    }
+
+    impl fmt::Display for IsComputeProp {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Compute")
+        }
+    }
+
+    #[derive(InstPass)]
+    struct IsComputePass;
+
+    impl InstPass for IsComputePass {
+        fn pass_inst(&self, inst: &Instruction) -> Vec<Box<dyn Property>> {
+            match inst {
+                Instruction::Compute(_) => vec![Box::new(IsComputeProp)],
+                _ => vec![],
+            }
+        }
+    }
+
+    /// Total trip count of the loop nest, attached to the workspace
+    #[derive(WorkspacePass)]
+    struct TotalTripCountPass;
+
+    impl WorkspacePass for TotalTripCountPass {
+        fn pass_workspace(&self, workspace: &mut Workspace) -> Vec<Box<dyn Property>> {
+            let trip_count = workspace
+                .loop_nest
+                .total_iteration_count(&Default::default())
+                .unwrap();
+            vec![Box::new(TripCountProp {
+                trip_count: trip_count as i32,
+            })]
+        }
+    }
+
+    impl PassInfo for TotalTripCountPass {
+        fn name(&self) -> &str {
+            "TotalTripCountPass"
+        }
+        fn description(&self) -> &str {
+            "Trip count of the whole loop nest"
+        }
+        fn required_features(&self) -> Vec<Feature> {
+            vec![]
+        }
+        fn produced_features(&self) -> Vec<Feature> {
+            vec![Feature::Custom("TotalTripCount".to_string())]
+        }
+    }
+
+    /// Depth of the loop nest, attached to the loop nest
+    #[derive(LoopPass)]
+    struct DepthPass;
+
+    impl LoopPass for DepthPass {
+        fn pass_loop(&self, loop_nest: &LoopNest) -> Vec<Box<dyn Property>> {
+            vec![Box::new(TripCountProp {
+                trip_count: loop_nest.iters.len() as i32,
+            })]
+        }
+    }
+
+    impl PassInfo for TripCountPass {
+        fn name(&self) -> &str {
+            "TripCountPass"
+        }
+        fn description(&self) -> &str {
+            "Trip count of each iterator"
+        }
+        fn required_features(&self) -> Vec<Feature> {
+            vec![]
+        }
+        fn produced_features(&self) -> Vec<Feature> {
+            vec![Feature::Custom("TripCount".to_string())]
+        }
+    }
+
+    impl PassInfo for IsComputePass {
+        fn name(&self) -> &str {
+            "IsComputePass"
+        }
+        fn description(&self) -> &str {
+            "Marks compute instructions"
+        }
+        fn required_features(&self) -> Vec<Feature> {
+            vec![]
+        }
+        fn produced_features(&self) -> Vec<Feature> {
+            vec![Feature::Custom("IsCompute".to_string())]
+        }
+    }
+
+    /// Workspace of a small loop nest after running the derived passes
+    fn analyzed_workspace() -> (LoopNest, Workspace) {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..128).step(2)
+  - for k in (0..256)
+body:
+  - Ra <= A[m][k]
+  - add Rb Ra, $1
+  - Rb => A[m][k]
+"#,
+        )
+        .unwrap();
+        let mut workspace = Workspace::new(loop_nest.clone(), None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(TripCountPass));
+        pass_pipeline.register_pass(Box::new(IsComputePass));
+        pass_pipeline.register_pass(Box::new(TotalTripCountPass));
+        pass_pipeline.run(&mut workspace).unwrap();
+        (loop_nest, workspace)
+    }
+
+    #[test]
+    fn test_derived_passes() {
+        let (loop_nest, workspace) = analyzed_workspace();
+        let trip_counts: Vec<String> = loop_nest
+            .iters
+            .iter()
+            .map(|iter| {
+                workspace
+                    .get_property(iter, "TripCountProp")
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(trip_counts, vec!["Trip count: 64", "Trip count: 256"]);
+
+        let computes: Vec<bool> = loop_nest
+            .body
+            .iter()
+            .map(|inst| workspace.get_property(inst, "IsComputeProp").is_some())
+            .collect();
+        assert_eq!(computes, vec![false, true, false]);
+    }
+
+    #[test]
+    fn test_get_typed_property() {
+        let (loop_nest, workspace) = analyzed_workspace();
+        assert_eq!(
+            workspace.get_typed_property::<TripCountProp>(&workspace)[0].trip_count,
+            64 * 256
+        );
+
+        let trip_counts: Vec<i32> = loop_nest
+            .iters
+            .iter()
+            .flat_map(|iter| workspace.get_typed_property::<TripCountProp>(iter))
+            .map(|prop| prop.trip_count)
+            .collect();
+        assert_eq!(trip_counts, vec![64, 256]);
+        assert!(workspace
+            .get_typed_property::<IsComputeProp>(&loop_nest.iters[0])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_remove_properties() {
+        let (loop_nest, mut workspace) = analyzed_workspace();
+        // remove the results of a single pass, then everything
+        let first_iter = &loop_nest.iters[0];
+        workspace.add_property(first_iter, Box::new(IsComputeProp));
+        workspace
+            .properties
+            .remove_property_by_type::<TripCountProp>(first_iter);
+        assert!(workspace
+            .get_typed_property::<TripCountProp>(first_iter)
+            .is_empty());
+        assert_eq!(workspace.get_properties(first_iter).unwrap().len(), 1);
+        workspace.properties.remove_properties_for(first_iter);
+        assert!(workspace.get_properties(first_iter).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let (loop_nest, mut workspace) = analyzed_workspace();
+        let snapshot = workspace.snapshot();
+        let first_iter = &loop_nest.iters[0];
+        workspace.add_property(first_iter, Box::new(IsComputeProp));
+        workspace
+            .properties
+            .remove_properties_for(&loop_nest.body[1]);
+        assert_eq!(workspace.get_properties(first_iter).unwrap().len(), 2);
+
+        workspace.restore(snapshot);
+        assert_eq!(
+            workspace.get_typed_property::<TripCountProp>(first_iter)[0].trip_count,
+            64
+        );
+        assert!(workspace
+            .get_typed_property::<IsComputeProp>(first_iter)
+            .is_empty());
+        assert!(workspace
+            .get_property(&loop_nest.body[1], "IsComputeProp")
+            .is_some());
+    }
+
+    #[test]
+    fn test_loop_property() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..128).step(2)
+  - for k in (0..K)
+body:
+  - Ra <= A[m][k]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            loop_nest.property_hook_id(),
+            "LoopNest::for m in (0..128).step(2); for k in (0..K)"
+        );
+        let mut workspace = Workspace::new(loop_nest.clone(), None);
+        workspace.add_loop_property(Box::new(TripCountProp { trip_count: 64 }));
+        PassRun::run(&DepthPass, &mut workspace).unwrap();
+        let trip_counts: Vec<i32> = workspace
+            .get_typed_property::<TripCountProp>(&loop_nest)
+            .iter()
+            .map(|prop| prop.trip_count)
+            .collect();
+        assert_eq!(trip_counts, vec![64, 2]);
+        assert!(workspace
+            .get_typed_property::<TripCountProp>(&workspace)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_clear_and_rerun() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..128)
+body:
+  - Ra <= A[m]
+"#,
+        )
+        .unwrap();
+        let mut workspace = Workspace::new(loop_nest, None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(TripCountPass));
+        pass_pipeline.run(&mut workspace).unwrap();
+
+        // tiling-like change of the loop nest, the old iterator is gone
+        let old_iter = workspace.loop_nest.iters[0].clone();
+        workspace.loop_nest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..128).step(4)
+body:
+  - Ra <= A[m]
+"#,
+        )
+        .unwrap();
+        workspace.clear_and_rerun(&mut pass_pipeline).unwrap();
+        assert!(workspace.get_properties(&old_iter).is_none());
+        let new_iter = &workspace.loop_nest.iters[0];
+        assert_eq!(
+            workspace.get_typed_property::<TripCountProp>(new_iter)[0].trip_count,
+            32
+        );
+        assert!(workspace.feature_available_str("TripCount"));
+    }
+
+    #[test]
+    fn test_apply_transform() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..128)
+body:
+  - Ra <= A[m]
+"#,
+        )
+        .unwrap();
+        let mut workspace = Workspace::new(loop_nest, None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(TripCountPass));
+        pass_pipeline.run(&mut workspace).unwrap();
+        let old_iter = workspace.loop_nest.iters[0].clone();
+
+        // an inapplicable transform is reported and leaves the workspace unchanged
+        assert!(workspace
+            .apply_transform(&Transform::Tiling(("m".to_string(), "mt".to_string(), 3)))
+            .is_err());
+        assert!(workspace.feature_available_str("TripCount"));
+
+        workspace
+            .apply_transform(&Transform::Tiling(("m".to_string(), "mt".to_string(), 4)))
+            .unwrap();
+        assert_eq!(workspace.loop_nest.iter_names(), vec!["m", "mt"]);
+        assert!(workspace.get_properties(&old_iter).is_none());
+        // the hooks of the new loop nest are registered without properties
+        assert!(workspace
+            .get_properties(&workspace.loop_nest.iters[1])
+            .is_some_and(|properties| properties.is_empty()));
+        assert!(!workspace.feature_available_str("TripCount"));
+
+        workspace
+            .apply_transforms(&Transforms::from_str(" - mt -> t\n - !Unroll t by 2").unwrap())
+            .unwrap();
+        // the first two iterations of t are inlined, the last two run in the residual loop
+        assert_eq!(workspace.loop_nest.iter_names(), vec!["m"]);
+        pass_pipeline.run(&mut workspace).unwrap();
+        let residual = workspace.loop_nest.all_iters()[1];
+        assert_eq!(residual.iter_name, "t");
+        assert_eq!(
+            workspace.get_typed_property::<TripCountProp>(residual)[0].trip_count,
+            2
+        );
+    }
+
+    #[test]
+    fn test_body_modification() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for m in (0..128)
+body:
+  - Ra <= A[m]
+"#,
+        )
+        .unwrap();
+        let mut workspace = Workspace::new(loop_nest.clone(), None);
+        let prefetch: Instruction = serde_yaml::from_str("Rp <= A[m + 1]").unwrap();
+        workspace.add_inst_entry_for_new_instruction(&prefetch);
+        assert_eq!(workspace.loop_nest.body.len(), 2);
+        workspace.add_property(&prefetch, Box::new(IsComputeProp));
+        assert_eq!(workspace.get_properties(&prefetch).unwrap().len(), 1);
+
+        workspace.add_inst_entry_for_new_instruction(&prefetch);
+        assert!(workspace.remove_instruction(&prefetch));
+        // an identical instruction remains
+        assert_eq!(workspace.get_properties(&prefetch).unwrap().len(), 1);
+        assert!(workspace.remove_instruction(&prefetch));
+        assert!(workspace.get_properties(&prefetch).is_none());
+        assert!(!workspace.remove_instruction(&prefetch));
+        assert_eq!(workspace.loop_nest.body, loop_nest.body);
+    }
+}
+
+mod pipeline {
+    use loopana::passes::feature::Feature;
+    use loopana::passes::pass_pipeline::{LoggingCallback, PassPipeline, PassProgressCallback};
+    use loopana::passes::passes::{PassInfo, PassRun};
+    use loopana::passes::registry::PassRegistry;
+    use loopana::passes::workspace::Workspace;
+    use loopana::representations::loops::LoopNest;
+    use std::sync::{Arc, Mutex};
+
+    /// A pass doing nothing but declaring features
+    struct FeaturePass {
+        name: &'static str,
+        required: Vec<&'static str>,
+        produced: Vec<&'static str>,
+    }
+
+    impl FeaturePass {
+        fn boxed(
+            name: &'static str,
+            required: Vec<&'static str>,
+            produced: Vec<&'static str>,
+        ) -> Box<Self> {
+            Box::new(FeaturePass {
+                name,
+                required,
+                produced,
+            })
+        }
+    }
+
+    impl PassInfo for FeaturePass {
+        fn name(&self) -> &str {
+            self.name
+        }
+        fn description(&self) -> &str {
+            "Test pass"
+        }
+        fn required_features(&self) -> Vec<Feature> {
+            self.required
+                .iter()
+                .map(|f| Feature::from_name(f))
+                .collect()
+        }
+        fn produced_features(&self) -> Vec<Feature> {
+            self.produced
+                .iter()
+                .map(|f| Feature::from_name(f))
+                .collect()
+        }
+    }
+
+    impl PassRun for FeaturePass {
+        fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn run(&self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+            Ok(())
+        }
+    }
+
+    fn new_workspace() -> Workspace {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..16)
+body:
+  - Ra <= A[i]
+"#,
+        )
+        .unwrap();
+        Workspace::new(loop_nest, None)
+    }
+
+    #[test]
+    fn test_run_dry() {
+        let mut workspace = new_workspace();
+
+        let mut pipeline = PassPipeline::new();
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        pipeline.register_pass(FeaturePass::boxed("B", vec!["a"], vec!["b"]));
+        assert_eq!(pipeline.run_dry(&workspace), Ok(vec![]));
+        pipeline.run(&mut workspace).unwrap();
+        assert!(workspace.feature_available_str("b"));
+
+        let mut pipeline = PassPipeline::new();
+        pipeline.register_pass(FeaturePass::boxed("B", vec!["a"], vec!["b"]));
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        let err = pipeline.run_dry(&new_workspace()).unwrap_err();
+        assert!(err.contains("runs after"), "{}", err);
+
+        let mut pipeline = PassPipeline::new();
+        pipeline.register_pass(FeaturePass::boxed("C", vec!["c"], vec![]));
+        let err = pipeline.run_dry(&new_workspace()).unwrap_err();
+        assert!(err.contains("never produced"), "{}", err);
+
+        let mut workspace = new_workspace();
+        workspace
+            .available_features
+            .push(Feature::Custom("c".to_string()));
+        assert_eq!(pipeline.run_dry(&workspace).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_add_pass_ordered() {
+        let mut pipeline = PassPipeline::new();
+        pipeline
+            .add_pass_ordered(FeaturePass::boxed("C", vec!["b"], vec!["c"]))
+            .unwrap();
+        pipeline
+            .add_pass_ordered(FeaturePass::boxed("Other", vec![], vec!["x"]))
+            .unwrap();
+        pipeline
+            .add_pass_ordered(FeaturePass::boxed("B", vec!["a"], vec!["b"]))
+            .unwrap();
+        pipeline
+            .add_pass_ordered(FeaturePass::boxed("A", vec![], vec!["a"]))
+            .unwrap();
+        assert_eq!(pipeline.run_dry(&new_workspace()), Ok(vec![]));
+
+        let err = pipeline
+            .add_pass_ordered(FeaturePass::boxed("Cyclic", vec!["c"], vec!["a"]))
+            .unwrap_err();
+        assert!(err.contains("Cyclic"), "{}", err);
+        // the pipeline is left untouched
+        assert_eq!(pipeline.run_dry(&new_workspace()), Ok(vec![]));
+        pipeline.run(&mut new_workspace()).unwrap();
+    }
+
+    #[test]
+    fn test_register_pass_conditional() {
+        let mut workspace = new_workspace();
+        let mut pipeline = PassPipeline::new();
+        pipeline.register_pass_conditional(FeaturePass::boxed("NoArch", vec![], vec!["x"]), |ws| {
+            ws.arch.is_some()
+        });
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        // evaluated at run time, after A produced its feature
+        pipeline.register_pass_conditional(FeaturePass::boxed("B", vec![], vec!["b"]), |ws| {
+            ws.feature_available_str("a")
+        });
+        pipeline.run(&mut workspace).unwrap();
+        assert!(!workspace.feature_available_str("x"));
+        assert!(workspace.feature_available_str("a"));
+        assert!(workspace.feature_available_str("b"));
+    }
+
+    #[test]
+    fn test_run_until() {
+        let new_pipeline = || {
+            let mut pipeline = PassPipeline::new();
+            pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+            pipeline.register_pass(FeaturePass::boxed("B", vec!["a"], vec!["b"]));
+            pipeline.register_pass(FeaturePass::boxed("C", vec!["b"], vec!["c"]));
+            pipeline
+        };
+
+        let mut workspace = new_workspace();
+        new_pipeline()
+            .run_until(&mut workspace, Feature::from_name("b"))
+            .unwrap();
+        assert!(workspace.feature_available_str("b"));
+        assert!(!workspace.feature_available_str("c"));
+
+        let mut workspace = new_workspace();
+        let err = new_pipeline()
+            .run_until(&mut workspace, Feature::MemAccess)
+            .unwrap_err();
+        assert!(err.contains("MemAccess"), "{}", err);
+        assert!(workspace.feature_available_str("c"));
+    }
+
+    #[test]
+    fn test_feature_queries() {
+        let mut pipeline = PassPipeline::new();
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        pipeline.register_pass(FeaturePass::boxed("B", vec!["a"], vec!["b"]));
+        pipeline.register_pass(FeaturePass::boxed("C", vec!["a", "b"], vec!["a"]));
+        let names = |passes: Vec<&dyn PassInfo>| -> Vec<String> {
+            passes.iter().map(|pass| pass.name().to_string()).collect()
+        };
+        assert_eq!(
+            names(pipeline.passes_for_feature(Feature::from_name("a"))),
+            vec!["A", "C"]
+        );
+        assert_eq!(
+            names(pipeline.passes_requiring_feature(Feature::from_name("a"))),
+            vec!["B", "C"]
+        );
+        assert!(pipeline.passes_for_feature(Feature::MemAccess).is_empty());
+    }
+
+    #[test]
+    fn test_pass_registry() {
+        PassRegistry::register(|| FeaturePass::boxed("Custom", vec!["MemAccess"], vec!["custom"]));
+        assert!(PassRegistry::global()
+            .producer(&Feature::from_name("custom"))
+            .is_some());
+        let mut pipeline =
+            PassPipeline::from_feature_requirements(vec![Feature::from_name("custom")]).unwrap();
+        let mut workspace = new_workspace();
+        pipeline.run(&mut workspace).unwrap();
+        assert!(workspace.feature_available(&Feature::MemAccess));
+        assert!(workspace.feature_available(&Feature::from_name("custom")));
+
+        // a factory using the registry is not called while the registry is locked
+        PassRegistry::register(|| {
+            let required = match PassRegistry::global().producer(&Feature::MemAccess) {
+                Some(_) => vec!["MemAccess"],
+                None => vec![],
+            };
+            FeaturePass::boxed("Reentrant", required, vec!["reentrant"])
+        });
+        let pipeline =
+            PassPipeline::from_feature_requirements(vec![Feature::from_name("reentrant")]).unwrap();
+        assert_eq!(
+            pipeline.passes_for_feature(Feature::from_name("reentrant"))[0].name(),
+            "Reentrant"
+        );
+        assert_eq!(pipeline.passes_for_feature(Feature::MemAccess).len(), 1);
+        assert!(Workspace::feature_graph()["MemAccess"].contains(&"reentrant".to_string()));
+    }
+
+    struct RecordingCallback(Arc<Mutex<Vec<String>>>);
+
+    impl PassProgressCallback for RecordingCallback {
+        fn on_pass_start(&self, name: &str) {
+            self.0.lock().unwrap().push(format!("start {}", name));
+        }
+        fn on_pass_end(&self, name: &str) {
+            self.0.lock().unwrap().push(format!("end {}", name));
+        }
+        fn on_pass_error(&self, name: &str, err: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("error {}: {}", name, err));
+        }
+    }
+
+    struct FailingPass;
+
+    impl PassInfo for FailingPass {
+        fn name(&self) -> &str {
+            "Failing"
+        }
+        fn description(&self) -> &str {
+            "Test pass always failing"
+        }
+        fn required_features(&self) -> Vec<Feature> {
+            vec![]
+        }
+        fn produced_features(&self) -> Vec<Feature> {
+            vec![]
+        }
+    }
+
+    impl PassRun for FailingPass {
+        fn setup(&mut self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+            Ok(())
+        }
+        fn run(&self, _workspace: &mut Workspace) -> Result<(), &'static str> {
+            Err("failure")
+        }
+    }
+
+    #[test]
+    fn test_progress_callback() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut pipeline =
+            PassPipeline::new().with_callback(Box::new(RecordingCallback(events.clone())));
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        pipeline.register_pass(Box::new(FailingPass));
+        pipeline.register_pass(FeaturePass::boxed("B", vec!["a"], vec!["b"]));
+        assert!(pipeline.run(&mut new_workspace()).is_err());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "start A",
+                "end A",
+                "start Failing",
+                "error Failing: failure"
+            ]
+        );
+
+        let mut pipeline = PassPipeline::new().with_callback(Box::new(LoggingCallback));
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        pipeline.run(&mut new_workspace()).unwrap();
+    }
+
+    #[test]
+    fn test_timing_report() {
+        let mut pipeline = PassPipeline::new().with_profiling();
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        pipeline.register_pass(FeaturePass::boxed("B", vec!["a"], vec!["b"]));
+        pipeline.run(&mut new_workspace()).unwrap();
+        let names: Vec<String> = pipeline
+            .timing_report()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["A", "B"]);
+
+        let mut pipeline = PassPipeline::new();
+        pipeline.register_pass(FeaturePass::boxed("A", vec![], vec!["a"]));
+        pipeline.run(&mut new_workspace()).unwrap();
+        assert!(pipeline.timing_report().is_empty());
+    }
+}
+
+mod analysis_passes {
+    use loopana::passes::arch_info::{ArchInfo, ArchInfoBuilder};
+    use loopana::passes::arithmetic_intensity::{ArithmeticIntensityPass, ArithmeticIntensityProp};
+    use loopana::passes::bandwidth_demand::{
+        BandwidthDemandPass, BandwidthDemandProp, BandwidthViolationProp,
+    };
+    use loopana::passes::compute_count::{ComputeCountPass, ComputeCountProp};
+    use loopana::passes::data_reuse_analysis::{DataReuseAnalysis, DataReuseProp, ReuseType};
+    use loopana::passes::feature::Feature;
+    use loopana::passes::loop_dependence_analysis::{
+        DependenceKind, DependenceProp, LoopDependenceAnalysis,
+    };
+    use loopana::passes::mapping_info::MappingInfoBuilder;
+    use loopana::passes::mem_access_analysis::{MemAccessAnalysis, MemAccessProp};
+    use loopana::passes::memory_footprint::{MemoryFootprintPass, MemoryFootprintProp};
+    use loopana::passes::noc_analysis::NoCAnalysis;
+    use loopana::passes::operation_count::{OperationCountPass, OperationCountProp};
+    use loopana::passes::parallelizability::ParallelizabilityPass;
+    use loopana::passes::pass_pipeline::PassPipeline;
+    use loopana::passes::passes::Pass;
+    use loopana::passes::stride_detection::{StrideDetectionPass, StrideProp};
+    use loopana::passes::sync_barrier::{SyncBarrierPass, SyncBarrierViolationProp};
+    use loopana::passes::vectorization_analysis::{
+        VectorOperandProp, VectorizationAnalysisPass, VectorizationProp,
+    };
+    use loopana::passes::workspace::Workspace;
+    use loopana::representations::affine_expr::AffineExpr;
+    use loopana::representations::arch::Arch;
+    use loopana::representations::loops::LoopNest;
+    use loopana::representations::mapping::Mapping;
+    use loopana::representations::transforms::Transform;
+    use std::collections::HashMap;
+
+    fn run_pass(loop_nest: &str, pass: Box<dyn Pass>) -> Workspace {
+        let loop_nest: LoopNest = serde_yaml::from_str(loop_nest).unwrap();
+        let mut workspace = Workspace::new(loop_nest, None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(pass);
+        pass_pipeline.run(&mut workspace).unwrap();
+        workspace
+    }
+
+    const MATMUL: &str = r#"
+iters:
+  - for i in (0..16)
+  - for j in (0..32)
+  - for k in (0..8)
+body:
+  - Ra <= A[i][k]
+  - Rb <= B[k][j]
+  - Rc <= C[i][j]
+  - mac Rc Ra, Rb
+  - Rc => C[i][j]
+"#;
+
+    #[test]
+    fn test_data_reuse_analysis() {
+        let workspace = run_pass(MATMUL, Box::new(DataReuseAnalysis));
+        let body = workspace.loop_nest.body.clone();
+        let reuse = |idx: usize| {
+            workspace.get_typed_property::<DataReuseProp>(&body[idx])[0]
+                .reuse
+                .clone()
+        };
+        use ReuseType::*;
+        let expected = |i, j, k| {
+            vec![
+                ("i".to_string(), i),
+                ("j".to_string(), j),
+                ("k".to_string(), k),
+            ]
+        };
+        assert_eq!(reuse(0), expected(NoReuse, TemporalReuse, SpatialReuse));
+        assert_eq!(reuse(1), expected(TemporalReuse, SpatialReuse, NoReuse));
+        assert_eq!(reuse(2), expected(NoReuse, SpatialReuse, TemporalReuse));
+        assert!(reuse(3).is_empty());
+        assert_eq!(
+            workspace
+                .get_property(&body[4], "DataReuseProp")
+                .unwrap()
+                .to_string(),
+            "Reuse: {i: None, j: Spatial, k: Temporal}"
+        );
+    }
+
+    #[test]
+    fn test_memory_footprint() {
+        let workspace = run_pass(MATMUL, Box::new(MemoryFootprintPass));
+        let footprint = &workspace.get_typed_property::<MemoryFootprintProp>(&workspace)[0];
+        assert_eq!(footprint.footprints.len(), 3);
+        assert_eq!(footprint.footprints["A"], AffineExpr::Const(16 * 8));
+        assert_eq!(footprint.footprints["B"], AffineExpr::Const(8 * 32));
+        assert_eq!(footprint.footprints["C"], AffineExpr::Const(16 * 32));
+        assert_eq!(
+            footprint.to_string(),
+            "Memory Footprint: {A: 128, B: 256, C: 512}"
+        );
+
+        let workspace = run_pass(
+            r#"
+iters:
+  - for i in (0..16)
+  - for k in (0..4)
+body:
+  - Ra <= A[3 * i + 2]
+  - Rb <= B[i + k]
+  - Rb <= B[i + 20]
+  - Rc <= C[i / 2]
+"#,
+            Box::new(MemoryFootprintPass),
+        );
+        let footprint = &workspace.get_typed_property::<MemoryFootprintProp>(&workspace)[0];
+        assert_eq!(footprint.footprints["A"], AffineExpr::Const(3 * 15 + 1));
+        // bounding box of the two accesses to B
+        assert_eq!(footprint.footprints["B"], AffineExpr::Const(36));
+        assert!(!footprint.footprints.contains_key("C"));
+
+        let workspace = run_pass(
+            r#"
+iters:
+  - for i in (0..N)
+  - for k in (0..K)
+body:
+  - Ra <= A[i][k]
+  - Rb <= B[2 * i + 1]
+  - Rc <= C[k][3]
+"#,
+            Box::new(MemoryFootprintPass),
+        );
+        let footprint = &workspace.get_typed_property::<MemoryFootprintProp>(&workspace)[0];
+        let params = HashMap::from([("N".to_string(), 10), ("K".to_string(), 7)]);
+        let evaluate = |array: &str| footprint.footprints[array].evaluate(&params, &params);
+        assert_eq!(evaluate("A"), Ok(70));
+        assert_eq!(evaluate("B"), Ok(19));
+        assert_eq!(evaluate("C"), Ok(7));
+
+        // 65536 * 65536 elements do not fit in an i32
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..65536)
+  - for j in (0..65536)
+body:
+  - Ra <= A[i][j]
+"#,
+        )
+        .unwrap();
+        assert!(MemoryFootprintPass::footprints(&loop_nest).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_intensity() {
+        let loop_nest: LoopNest = serde_yaml::from_str(MATMUL).unwrap();
+        let mut workspace = Workspace::new(loop_nest, None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline
+            .add_pass_ordered(Box::new(ArithmeticIntensityPass))
+            .unwrap();
+        pass_pipeline
+            .add_pass_ordered(Box::new(MemAccessAnalysis::default()))
+            .unwrap();
+        pass_pipeline
+            .add_pass_ordered(Box::new(MemoryFootprintPass))
+            .unwrap();
+        pass_pipeline
+            .add_pass_ordered(Box::new(ComputeCountPass))
+            .unwrap();
+        pass_pipeline.run(&mut workspace).unwrap();
+
+        let intensity = &workspace.get_typed_property::<ArithmeticIntensityProp>(&workspace)[0];
+        // one mac per iteration
+        assert_eq!(intensity.total_flops, 2 * 16 * 32 * 8);
+        assert_eq!(intensity.total_bytes, (128 + 256 + 512) * 4);
+        assert!((intensity.intensity - 8192.0 / 3584.0).abs() < 1e-9);
+
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(ArithmeticIntensityPass));
+        let loop_nest: LoopNest = serde_yaml::from_str(MATMUL).unwrap();
+        assert!(pass_pipeline
+            .run(&mut Workspace::new(loop_nest, None))
+            .is_err());
+    }
+
+    fn dependences(loop_nest: &str) -> Vec<(String, DependenceKind, Vec<String>)> {
+        let loop_nest: LoopNest = serde_yaml::from_str(loop_nest).unwrap();
+        let mut workspace = Workspace::new(loop_nest.clone(), None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(MemAccessAnalysis::default()));
+        pass_pipeline.register_pass(Box::new(LoopDependenceAnalysis));
+        pass_pipeline.run(&mut workspace).unwrap();
+        loop_nest
+            .body
+            .iter()
+            .flat_map(|inst| workspace.get_typed_property::<DependenceProp>(inst))
+            .map(|dep| {
+                (
+                    format!("{} -> {}", dep.source_inst_id, dep.sink_inst_id),
+                    dep.kind,
+                    dep.carriers.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_loop_dependence_analysis() {
+        use DependenceKind::*;
+        let load = "Instruction::Rc <= C[i][j]".to_string();
+        let store = "Instruction::Rc => C[i][j]".to_string();
+        let k = vec!["k".to_string()];
+        assert_eq!(
+            dependences(MATMUL),
+            vec![
+                (format!("{} -> {}", load, store), WAR, k.clone()),
+                (format!("{} -> {}", store, load), RAW, k.clone()),
+                (format!("{} -> {}", store, store), WAW, k.clone()),
+            ]
+        );
+
+        // even and odd elements never overlap, A[i + 1] is read after being written by the
+        // previous iteration
+        let deps = dependences(
+            r#"
+iters:
+  - for i in (0..16)
+body:
+  - Ra <= A[2 * i + 1]
+  - Rb <= A[i + 1]
+  - Ra => A[2 * i]
+  - Rb => B[i]
+"#,
+        );
+        let kinds: Vec<(DependenceKind, Vec<String>)> = deps
+            .into_iter()
+            .map(|(_, kind, carriers)| (kind, carriers))
+            .collect();
+        let i = vec!["i".to_string()];
+        assert_eq!(
+            kinds,
+            vec![
+                (None, vec![]),
+                (WAR, i.clone()),
+                (RAW, i.clone()),
+                (WAW, vec![]),
+                (WAW, vec![]),
+            ]
+        );
+
+        // the polynomial index is not tested, the dependence is assumed
+        let deps = dependences(
+            r#"
+iters:
+  - for i in (0..16)
+body:
+  - Ra <= A[i^2]
+  - Ra => A[2 * i + 1]
+"#,
+        );
+        let kinds: Vec<(DependenceKind, Vec<String>)> = deps
+            .into_iter()
+            .map(|(_, kind, carriers)| (kind, carriers))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![(WAR, i.clone()), (RAW, i.clone()), (WAW, vec![])]
+        );
+    }
+
+    #[test]
+    fn test_parallelizability() {
+        let loop_nest: LoopNest = serde_yaml::from_str(MATMUL).unwrap();
+        let mut workspace = Workspace::new(loop_nest, None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(MemAccessAnalysis::default()));
+        pass_pipeline.register_pass(Box::new(LoopDependenceAnalysis));
+        pass_pipeline.register_pass(Box::new(ParallelizabilityPass));
+        pass_pipeline.run(&mut workspace).unwrap();
+        let parallel: Vec<&str> = workspace
+            .get_parallel_iters()
+            .iter()
+            .map(|iter| iter.iter_name.as_str())
+            .collect();
+        // the reduction over k is sequential
+        assert_eq!(parallel, vec!["i", "j"]);
+    }
+
+    fn vectorization(loop_nest: &str) -> VectorizationProp {
+        let loop_nest: LoopNest = serde_yaml::from_str(loop_nest).unwrap();
+        let mut workspace = Workspace::new(loop_nest, None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(MemAccessAnalysis::default()));
+        pass_pipeline.register_pass(Box::new(LoopDependenceAnalysis));
+        pass_pipeline.register_pass(Box::new(VectorizationAnalysisPass));
+        pass_pipeline.run(&mut workspace).unwrap();
+        workspace.get_typed_property::<VectorizationProp>(&workspace)[0].clone()
+    }
+
+    #[test]
+    fn test_vectorization_analysis() {
+        // k is the reduction dimension and B[k][j] is strided along k
+        let prop = vectorization(MATMUL);
+        assert!(!prop.vectorizable);
+        assert!(prop
+            .blocking_reason
+            .unwrap()
+            .contains("access to B does not have a unit stride"));
+
+        let prop = vectorization(
+            r#"
+iters:
+  - for i in (0..16)
+  - for j in (0..24)
+body:
+  - Ra <= A[i][j]
+  - Rb <= B[i]
+  - add Rc Ra, Rb
+  - Rc => C[i][j]
+"#,
+        );
+        assert!(prop.vectorizable);
+        assert_eq!(prop.suggested_width, Some(8));
+        assert_eq!(prop.to_string(), "Vectorizable, width 8");
+
+        let prop = vectorization(
+            r#"
+iters:
+  - for j in (0..16)
+body:
+  - Ra <= A[j]
+  - Ra => A[j + 1]
+"#,
+        );
+        assert!(prop.blocking_reason.unwrap().contains("carried by j"));
+
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..16)
+body:
+  - Ra <= A[i]
+  - add V1:4 V0:4, Ra
+  - mul Rc Ra, Ra
+"#,
+        )
+        .unwrap();
+        let mut workspace = Workspace::new(loop_nest.clone(), None);
+        let mut pass_pipeline = PassPipeline::new();
+        pass_pipeline.register_pass(Box::new(MemAccessAnalysis::default()));
+        pass_pipeline.register_pass(Box::new(LoopDependenceAnalysis));
+        pass_pipeline.register_pass(Box::new(VectorizationAnalysisPass));
+        pass_pipeline.run(&mut workspace).unwrap();
+        let operands: Vec<String> = loop_nest.body[1..]
+            .iter()
+            .map(|inst| workspace.get_typed_property::<VectorOperandProp>(inst)[0].to_string())
+            .collect();
+        assert_eq!(
+            operands,
+            vec!["Vector operands, width 4", "Scalar operands"]
+        );
+        assert!(workspace
+            .get_typed_property::<VectorOperandProp>(&loop_nest.body[0])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_bandwidth_demand() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for x in (0..8)
+  - for y in (0..8)
+  - for k in (0..16)
+body:
+  - Ra <= A[x][k]
+  - Rb <= B[k][y]
+  - mac Rc Ra, Rb
+  - Rc => C[x][y]
+"#,
+        )
+        .unwrap();
+        let manifest = env!("CARGO_MANIFEST_DIR");
+        let arch = Arch::from_file(
+            &std::path::Path::new(manifest).join("example/mesh_distributed-mem.arch"),
+        )
+        .unwrap();
+        let demands = |mapping: Mapping| -> (Vec<(String, f64)>, Vec<String>) {
+            let mut workspace = Workspace::new(loop_nest.clone(), None);
+            let mut pass_pipeline = PassPipeline::new();
+            pass_pipeline.register_pass(Box::new(ArchInfoBuilder {
+                arch_info: ArchInfo { arch: arch.clone() },
+            }));
+            pass_pipeline.register_pass(Box::new(MappingInfoBuilder::from_mapping(mapping)));
+            pass_pipeline.register_pass(Box::new(MemoryFootprintPass));
+            pass_pipeline.register_pass(Box::new(BandwidthDemandPass));
+            pass_pipeline.run(&mut workspace).unwrap();
+            let demands = workspace
+                .get_typed_property::<BandwidthDemandProp>(&workspace)
+                .iter()
+                .map(|demand| (demand.port_name.clone(), demand.bytes_per_cycle))
+                .collect();
+            let violations = workspace
+                .get_typed_property::<BandwidthViolationProp>(&workspace)
+                .iter()
+                .map(|violation| violation.port_name.clone())
+                .collect();
+            (demands, violations)
+        };
+
+        // A is reused along y and B along x, each PE forwards one element per cycle, 4 bytes
+        // the 128 + 128 elements of A and B are read in 16 cycles by the 64 PEs
+        let mapping = Mapping::from_str(
+            r#"
+Mapping:
+    - x -> $x
+    - y -> $y
+    - k -> Temporal
+"#,
+        )
+        .unwrap();
+        let (port_demands, violations) = demands(mapping);
+        assert_eq!(
+            port_demands,
+            vec![
+                ("NORTH".to_string(), 4.0),
+                ("SOUTH".to_string(), 4.0),
+                ("WEST".to_string(), 4.0),
+                ("EAST".to_string(), 4.0),
+                ("RP1".to_string(), 1.0),
+            ]
+        );
+        assert!(violations.is_empty());
+
+        // with k on 4 SIMD lanes, the elements are forwarded 4 at a time and read in 4 cycles
+        let mapping = Mapping::from_str(
+            r#"
+Mapping:
+    - x -> $x
+    - y -> $y
+    - k -> $SIMD:4
+"#,
+        )
+        .unwrap();
+        let (port_demands, violations) = demands(mapping.clone());
+        assert_eq!(
+            port_demands,
+            vec![
+                ("NORTH".to_string(), 16.0),
+                ("SOUTH".to_string(), 16.0),
+                ("WEST".to_string(), 16.0),
+                ("EAST".to_string(), 16.0),
+                ("RP1".to_string(), 4.0),
+            ]
+        );
+        // the NoC ports carry 4 bytes/cycle and the read port 2 bytes/cycle
+        assert_eq!(violations, vec!["NORTH", "SOUTH", "WEST", "EAST", "RP1"]);
+
+        // only 16 of the 32 SIMD lanes are used, the elements are read in a single cycle
+        let (port_demands, _) = demands(
+            Mapping::from_str(
+                r#"
+Mapping:
+    - x -> $x
+    - y -> $y
+    - k -> $SIMD:32
+"#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            port_demands,
+            vec![
+                ("NORTH".to_string(), 64.0),
+                ("SOUTH".to_string(), 64.0),
+                ("WEST".to_string(), 64.0),
+                ("EAST".to_string(), 64.0),
+                ("RP1".to_string(), 16.0),
+            ]
+        );
+
+        // 16 is not a multiple of 3 SIMD lanes, the last vector is partially filled
+        let (port_demands, _) = demands(
+            Mapping::from_str(
+                r#"
+Mapping:
+    - x -> $x
+    - y -> $y
+    - k -> $SIMD:3
+"#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(port_demands[0], ("NORTH".to_string(), 12.0));
+        assert_eq!(port_demands[4], ("RP1".to_string(), 1024.0 / 384.0));
+
+        let mut noc_analysis = NoCAnalysis::new(loop_nest, arch);
+        let traffic = noc_analysis.compute_traffic(&mapping);
+        assert_eq!(traffic["EAST"], 16.0);
+        assert_eq!(traffic["RP1"], 4.0);
+        assert_eq!(noc_analysis.violations.len(), 5);
+    }
+
+    #[test]
+    fn test_operation_count() {
+        let workspace = run_pass(
+            r#"
+iters:
+  - for i in (0..16)
+body:
+  - Ra <= A[i]
+  - Rb <= B[i]
+  - mul Rc Ra, Rb
+  - add Rc Rc, $1
+  - add Rd Rc, Ra
+  - Rd => C[i]
+"#,
+            Box::new(OperationCountPass),
+        );
+        let counts = &workspace.get_typed_property::<OperationCountProp>(&workspace)[0];
+        assert_eq!((counts.loads, counts.stores), (2, 1));
+        assert_eq!(counts.total_memory_ops(), 3);
+        assert_eq!(counts.total_compute_ops(), 3);
+        assert_eq!(counts.compute_ops["add"], 2);
+        assert_eq!(
+            counts.to_string(),
+            "Loads: 2, Stores: 1, Compute: {add: 2, mul: 1}"
+        );
+    }
+
+    #[test]
+    fn test_sync_barrier() {
+        let loop_nest = r#"
+iters:
+  - for i in (0..16)
+body:
+  - Ra <= A[i]
+  - SYNC load
+  - add Rb Ra, $1
+  - Rb => B[i]
+  - SYNC store
+"#;
+        let workspace = run_pass(loop_nest, Box::new(SyncBarrierPass));
+        assert!(workspace
+            .get_typed_property::<SyncBarrierViolationProp>(&workspace)
+            .is_empty());
+        // barriers have no memory access property
+        let workspace = run_pass(loop_nest, Box::new(MemAccessAnalysis::default()));
+        let barrier = &workspace.loop_nest.body[1];
+        assert_eq!(barrier.to_string(), "SYNC load");
+        assert!(workspace.get_properties(barrier).unwrap().is_empty());
+
+        let workspace = run_pass(
+            r#"
+iters:
+  - for i in (0..16)
+body:
+  - SYNC start
+  - Ra <= A[i]
+  - SYNC load
+  - SYNC again
+  - iters:
+      - for j in (0..4)
+    body:
+      - Rb <= B[i][j]
+      - SYNC load
+"#,
+            Box::new(SyncBarrierPass),
+        );
+        let violations: Vec<String> = workspace
+            .get_typed_property::<SyncBarrierViolationProp>(&workspace)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                "Illegal sync barrier start: no instruction before it in its body",
+                "Illegal sync barrier again: directly follows another sync barrier",
+                "Illegal sync barrier load: the label is used by another sync barrier",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stride_detection() {
+        let workspace = run_pass(
+            r#"
+iters:
+  - for i in (0..16)
+  - for j in (0..16)
+body:
+  - Ra <= A[i][j]
+  - Rb <= B[j][i]
+  - Rc <= C[i][2 * j + 1]
+  - Rd <= D[i]
+  - Re <= E[i][j / 2]
+  - add Rf Ra, Rb
+  - Rf => F[i][j]
+"#,
+            Box::new(StrideDetectionPass),
+        );
+        let strides: Vec<Option<i32>> = workspace
+            .loop_nest
+            .body
+            .iter()
+            .flat_map(|inst| workspace.get_typed_property::<StrideProp>(inst))
+            .map(|prop| {
+                assert_eq!(prop.iterator, "j");
+                prop.stride
+            })
+            .collect();
+        assert_eq!(
+            strides,
+            vec![Some(1), None, Some(2), Some(0), None, Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_nested_loop_nest() {
+        let nested = r#"
+iters:
+  - for i in (0..4)
+body:
+  - Ra <= A[i]
+  - iters:
+      - for j in (0..8)
+    body:
+      - Rb <= B[i][j]
+      - mac Rc Ra, Rb
+  - Rc => C[i]
+"#;
+        // the nested instructions are executed 8 times per iteration of i
+        let workspace = run_pass(nested, Box::new(ComputeCountPass));
+        let count = &workspace.get_typed_property::<ComputeCountProp>(&workspace)[0];
+        assert_eq!(count.flops_per_iteration, 2 * 8);
+        assert_eq!(count.total_flops, 2 * 8 * 4);
+
+        let workspace = run_pass(nested, Box::new(StrideDetectionPass));
+        let instructions = workspace.loop_nest.all_instructions();
+        let stride = &workspace.get_typed_property::<StrideProp>(instructions[1])[0];
+        assert_eq!(stride.iterator, "j");
+        assert_eq!(stride.stride, Some(1));
+        let stride = &workspace.get_typed_property::<StrideProp>(instructions[3])[0];
+        assert_eq!(stride.iterator, "i");
+
+        let workspace = run_pass(nested, Box::new(MemAccessAnalysis::default()));
+        let instructions = workspace.loop_nest.all_instructions();
+        let accessed = &workspace.get_typed_property::<MemAccessProp>(instructions[1])[0];
+        assert_eq!(accessed.accessed_dims, vec!["i", "j"]);
+
+        let workspace = run_pass(
+            nested,
+            Box::new(MemAccessAnalysis {
+                top_level_only: true,
+            }),
+        );
+        let instructions = workspace.loop_nest.all_instructions();
+        assert_eq!(
+            workspace
+                .get_typed_property::<MemAccessProp>(instructions[0])
+                .len(),
+            1
+        );
+        assert!(workspace
+            .get_typed_property::<MemAccessProp>(instructions[1])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_apply_transform_checked() {
+        let dependence_pipeline = || {
+            let mut pass_pipeline = PassPipeline::new();
+            pass_pipeline.register_pass(Box::new(MemAccessAnalysis::default()));
+            pass_pipeline.register_pass(Box::new(LoopDependenceAnalysis));
+            pass_pipeline
+        };
+        let interchange = |iter1: &str, iter2: &str| {
+            Transform::Interchange((iter1.to_string(), iter2.to_string()))
+        };
+
+        // only k carries a dependence in the matmul
+        let loop_nest: LoopNest = serde_yaml::from_str(MATMUL).unwrap();
+        let mut workspace = Workspace::new(loop_nest, None);
+        dependence_pipeline().run(&mut workspace).unwrap();
+        workspace
+            .apply_transform_checked(&interchange("i", "k"))
+            .unwrap();
+        assert_eq!(workspace.loop_nest.iters[0].iter_name, "k");
+        assert!(workspace.available_features.is_empty());
+        assert!(workspace
+            .apply_transform_checked(&interchange("i", "x"))
+            .is_err());
+
+        // A[i + 1][j] depends on A[i][j + 1], the distance (1, -1) would be reversed
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..16)
+  - for j in (0..16)
+body:
+  - Ra <= A[i][j + 1]
+  - Ra => A[i + 1][j]
+"#,
+        )
+        .unwrap();
+        let mut workspace = Workspace::new(loop_nest.clone(), None);
+        dependence_pipeline().run(&mut workspace).unwrap();
+        let error = workspace
+            .apply_transform_checked(&interchange("i", "j"))
+            .unwrap_err();
+        assert!(error.contains("Cannot interchange i and j"), "{}", error);
+        assert_eq!(workspace.loop_nest, loop_nest);
+
+        // without the dependence information the interchange is not checked
+        let mut workspace = Workspace::new(loop_nest, None);
+        workspace
+            .apply_transform_checked(&interchange("i", "j"))
+            .unwrap();
+        assert_eq!(workspace.loop_nest.iters[0].iter_name, "j");
+
+        // A[i][j][k] is read at (i, j + 1, k - 1), the direction (=, <, >) is only reversed when
+        // k moves outside of j
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..16)
+  - for j in (0..16)
+  - for k in (0..16)
+body:
+  - Ra <= A[i][j - 1][k + 1]
+  - Ra => A[i][j][k]
+"#,
+        )
+        .unwrap();
+        let mut workspace = Workspace::new(loop_nest.clone(), None);
+        dependence_pipeline().run(&mut workspace).unwrap();
+        let error = workspace
+            .apply_transform_checked(&interchange("i", "k"))
+            .unwrap_err();
+        assert!(error.contains("with direction (=, <, >)"), "{}", error);
+        assert!(workspace
+            .apply_transform_checked(&interchange("j", "k"))
+            .is_err());
+        assert_eq!(workspace.loop_nest, loop_nest);
+        workspace
+            .apply_transform_checked(&interchange("i", "j"))
+            .unwrap();
+        assert_eq!(workspace.loop_nest.iter_names(), vec!["j", "i", "k"]);
+    }
+
+    #[test]
+    fn test_from_feature_requirements() {
+        let mut pipeline = PassPipeline::from_feature_requirements(vec![
+            Feature::Parallelizable,
+            Feature::ArithmeticIntensity,
+        ])
+        .unwrap();
+        let mut workspace = Workspace::new(serde_yaml::from_str(MATMUL).unwrap(), None);
+        pipeline.run(&mut workspace).unwrap();
+        for feature in [
+            Feature::MemAccess,
+            Feature::DependenceInfo,
+            Feature::Parallelizable,
+            Feature::ComputeCount,
+            Feature::MemoryFootprint,
+            Feature::ArithmeticIntensity,
+        ] {
+            assert!(workspace.feature_available(&feature), "{}", feature);
+        }
+        assert!(!workspace.feature_available(&Feature::Vectorization));
+
+        // the architecture and the mapping have no built-in producer
+        let error = match PassPipeline::from_feature_requirements(vec![Feature::BandwidthDemand]) {
+            Ok(_) => panic!("BandwidthDemand cannot be produced without an architecture"),
+            Err(error) => error,
+        };
+        assert!(
+            error.contains("is not produced by any known pass"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_feature_graph() {
+        let graph = Workspace::feature_graph();
+        // the pipeline tests may register custom passes requiring MemAccess
+        assert!(graph["MemAccess"].contains(&"Dependence".to_string()));
+        assert_eq!(graph["Dependence"], vec!["Parallelizable", "Vectorization"]);
+        assert!(graph["ArithmeticIntensity"].is_empty());
+        assert!(graph["ArchInfo"].contains(&"BandwidthDemand".to_string()));
+
+        let dot = Workspace::feature_graph_dot();
+        assert!(dot.starts_with("digraph features {\n"));
+        assert!(dot.contains("    \"Dependence\" -> \"Parallelizable\";\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
+
+mod mapper {
+    use loopana::passes::mapper::Mapper;
+    use loopana::representations::arch::Arch;
+    use loopana::representations::loops::LoopNest;
+    use loopana::representations::mapping::MappingType;
+
+    #[test]
+    fn test_generate_all_mappings() {
+        let loop_nest: LoopNest = serde_yaml::from_str(
+            r#"
+iters:
+  - for i in (0..8)
+  - for j in (0..8)
+body:
+  - Ra <= A[i][j]
+"#,
+        )
+        .unwrap();
+        let manifest = env!("CARGO_MANIFEST_DIR");
+        let arch = Arch::from_file(
+            &std::path::Path::new(manifest).join("example/mesh_distributed-mem.arch"),
+        )
+        .unwrap();
+
+        // 5 choices for i ($x, $y and the 3 others), then 4 for j if i is spatial, 5 otherwise
+        let mappings = Mapper::new(arch.clone(), loop_nest.clone()).generate_all_mappings();
+        assert_eq!(mappings.len(), 2 * 4 + 3 * 5);
+        assert!(mappings
+            .iter()
+            .all(|mapping| mapping.is_complete(&loop_nest)));
+        assert!(mappings
+            .iter()
+            .all(|mapping| mapping.types["i"] != mapping.types["j"]
+                || !matches!(mapping.types["i"], MappingType::Spatial(_))));
+        assert!(mappings
+            .iter()
+            .all(|mapping| mapping.validate_against_arch(&arch, &loop_nest).is_ok()));
+
+        // without a symmetric NoC, nothing is mapped spatially
+        let mut asymmetric_arch = arch.clone();
+        asymmetric_arch.pe_arch.data_ports.remove(0);
+        let mappings = Mapper::new(asymmetric_arch, loop_nest).generate_all_mappings();
+        assert_eq!(mappings.len(), 3 * 3);
+    }
 }
@@ -0,0 +1,132 @@
+use loopana::representations::affine_expr::AffineExpr;
+use loopana::representations::arch::Arch;
+use loopana::representations::instruction::Instruction;
+use loopana::representations::loops::{LoopIter, LoopNest};
+use loopana::representations::mapping::Mapping;
+use loopana::representations::transforms::{Transform, Transforms};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+use std::{fs, path::Path};
+
+fn read_example(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("example")
+        .join(name);
+    fs::read_to_string(path).expect("Failed to read the example file")
+}
+
+fn assert_json_round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let serialized = serde_json::to_string(value).unwrap();
+    let deserialized: T = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(&deserialized, value, "{}", serialized);
+}
+
+#[test]
+fn test_affine_expr() {
+    for input in [
+        "(x + M_a * y) / 3 - 3 * z % 5",
+        "max(0, i - 4)",
+        "ceildiv(N, 8)",
+    ] {
+        let expr: AffineExpr = serde_json::from_str(&format!("\"{}\"", input)).unwrap();
+        assert_json_round_trip(&expr);
+    }
+    assert_eq!(
+        serde_json::to_string(&AffineExpr::Var("x".to_string())).unwrap(),
+        "\"x\""
+    );
+    assert!(serde_json::from_str::<AffineExpr>("3").is_err());
+}
+
+#[test]
+fn test_loop_iter_and_instruction() {
+    let iter: LoopIter = serde_json::from_str("\"for n in (0..N).step(SIMD_W)\"").unwrap();
+    assert_json_round_trip(&iter);
+    for input in [
+        "Ra <= A[i][j + 1]",
+        "mul Rc Ra, Rb",
+        "mul Rc Ra, $0.5",
+        "Rc => C[m][n]",
+    ] {
+        let inst: Instruction = serde_json::from_str(&format!("\"{}\"", input)).unwrap();
+        assert_json_round_trip(&inst);
+    }
+}
+
+#[test]
+fn test_loop_nest() {
+    let loop_nest: LoopNest = serde_yaml::from_str(&read_example("prob.loop")).unwrap();
+    let json = loop_nest.to_json_str().unwrap();
+    assert_eq!(LoopNest::from_json_str(&json).unwrap(), loop_nest);
+
+    // nested loop nests are written as JSON objects
+    let loop_nest = LoopNest::from_json_str(
+        r#"{
+            "iters": ["for i in (0..8)"],
+            "body": [
+                {"iters": ["for j in (0..4)"], "body": ["Ra <= A[i][j]"]},
+                "Ra => B[i]"
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert!(matches!(loop_nest.body[0], Instruction::LoopBody(_)));
+    assert_json_round_trip(&loop_nest);
+
+    assert!(LoopNest::from_json_str(r#"{"iters": ["for i in 8"], "body": []}"#).is_err());
+}
+
+#[test]
+fn test_transform() {
+    let transforms = Transforms::from_str(&read_example("transforms.trf")).unwrap();
+    for transform in &transforms.transforms {
+        assert_json_round_trip(transform);
+    }
+    let transform: Transform = serde_json::from_str("\"!Skew i by 2 * j\"").unwrap();
+    assert_eq!(
+        transform,
+        Transform::Skew("i".to_string(), "j".to_string(), 2)
+    );
+    assert_json_round_trip(&transform);
+}
+
+#[test]
+fn test_arch() {
+    let arch: Arch = serde_yaml::from_str(&read_example("mesh_distributed-mem.arch")).unwrap();
+    assert_json_round_trip(&arch);
+}
+
+#[test]
+fn test_mapping() {
+    let mapping = Mapping::from_str(&read_example("mapping.map")).unwrap();
+    assert_json_round_trip(&mapping);
+}
+
+#[test]
+fn test_binary() {
+    let loop_nest: LoopNest = serde_yaml::from_str(&read_example("prob.loop")).unwrap();
+    assert_eq!(
+        LoopNest::from_binary(&loop_nest.to_binary()).unwrap(),
+        loop_nest
+    );
+
+    // nested loop nests are encoded in place of their instruction
+    let loop_nest = LoopNest::from_json_str(
+        r#"{
+            "iters": ["for i in (0..8)"],
+            "body": [
+                {"iters": ["for j in (0..4)"], "body": ["Ra <= A[i][j]"]},
+                "Ra => B[i]"
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        LoopNest::from_binary(&loop_nest.to_binary()).unwrap(),
+        loop_nest
+    );
+    assert!(LoopNest::from_binary(&[1, 2, 3]).is_err());
+
+    let arch: Arch = serde_yaml::from_str(&read_example("mesh_distributed-mem.arch")).unwrap();
+    assert_eq!(Arch::from_binary(&arch.to_binary()).unwrap(), arch);
+}
@@ -1,26 +1,508 @@
 use loopana::passes::transform_pass::Transforming;
-use loopana::representations::loops::LoopNest;
+use loopana::representations::instruction::Instruction;
+use loopana::representations::loops::{LoopIter, LoopNest};
 use loopana::representations::transforms::Transforms;
-use std::{fs, path::Path};
+use std::collections::HashMap;
+use std::path::Path;
 #[test]
 fn test_transforms() {
     let manifest = env!("CARGO_MANIFEST_DIR");
     let file_path = Path::new(manifest).join("example/transforms.trf");
-    let input_str = fs::read_to_string(file_path).expect("Failed to read YAML file");
-    let transforms: Transforms =
-        Transforms::from_str(&input_str).expect("Failed to deserialize YAML");
-    let problem_file_path = Path::new(manifest).join("example/prob.loop");
-    let yaml_str = fs::read_to_string(problem_file_path).expect("Failed to read YAML file");
-    let loop_prob: LoopNest = serde_yaml::from_str(&yaml_str).expect("Failed to deserialize YAML");
+    let transforms = Transforms::from_trf_file(&file_path).expect("Failed to read the transforms");
+    let loop_prob =
+        LoopNest::from_loop_file(&Path::new(manifest).join("example/prob.loop")).unwrap();
     let transformed_loop_prob = loop_prob.apply_all(&transforms);
-    // Serialize the transformed loop prob
-    let serialized = serde_yaml::to_string(&transformed_loop_prob).unwrap();
-    // Save to file
+    // Save the transformed loop prob
     let transformed_file_path = Path::new(manifest).join("example/transformed_prob.loop");
-    fs::write(transformed_file_path, serialized).expect("Failed to write to file");
+    transformed_loop_prob
+        .to_loop_file(&transformed_file_path)
+        .unwrap();
 
     // try to load it again
-    let transformed_file_path = Path::new(manifest).join("example/transformed_prob.loop");
-    let yaml_str = fs::read_to_string(transformed_file_path).expect("Failed to read YAML file");
-    let _loop_prob: LoopNest = serde_yaml::from_str(&yaml_str).expect("Failed to deserialize YAML");
+    let loop_prob = LoopNest::from_loop_file(&transformed_file_path).unwrap();
+    assert_eq!(loop_prob, transformed_loop_prob);
+
+    // the transforms survive a round trip through a file
+    let transforms_file_path = std::env::temp_dir().join("loopana_test_transforms.trf");
+    transforms.to_yaml_file(&transforms_file_path).unwrap();
+    assert_eq!(
+        Transforms::from_yaml_file(&transforms_file_path).unwrap(),
+        transforms
+    );
+    assert_eq!(
+        Transforms::from_trf_file(&transforms_file_path).unwrap(),
+        transforms
+    );
+    assert!(Transforms::from_trf_file(&Path::new(manifest).join("example/missing.trf")).is_err());
+
+    // and so does the loop nest
+    let loop_file_path = std::env::temp_dir().join("loopana_test_loop_nest.yaml");
+    loop_prob.to_yaml_file(&loop_file_path).unwrap();
+    assert_eq!(
+        LoopNest::from_yaml_file(&loop_file_path).unwrap(),
+        loop_prob
+    );
+}
+
+#[test]
+fn test_unroll() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..8)
+  - for j in (0..16).step(2)
+body:
+  - Ra <= A[i][j]
+  - add Rb Ra, $1
+  - Rb => B[j][i]
+"#,
+    )
+    .unwrap();
+    let transforms = Transforms::from_str(" - !Unroll j by 2").unwrap();
+    let unrolled = loop_nest.apply_all(&transforms);
+    let expected: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..8)
+body:
+  - Ra <= A[i][0]
+  - add Rb Ra, $1
+  - Rb => B[0][i]
+  - Ra <= A[i][2]
+  - add Rb Ra, $1
+  - Rb => B[2][i]
+  - iters:
+      - for j in (4..16).step(2)
+    body:
+      - Ra <= A[i][j]
+      - add Rb Ra, $1
+      - Rb => B[j][i]
+"#,
+    )
+    .unwrap();
+    assert_eq!(unrolled, expected);
+}
+
+#[test]
+fn test_unroll_with_remainder() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..4)
+  - for j in (0..10)
+  - for k in (0..j)
+body:
+  - Ra <= A[i][j + 1][k]
+"#,
+    )
+    .unwrap();
+    let unrolled = loop_nest.apply_all(&Transforms::from_str(" - !Unroll j by 4").unwrap());
+    let expected: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..4)
+body:
+  - iters:
+      - for k in (0..0)
+    body:
+      - Ra <= A[i][1][k]
+  - iters:
+      - for k in (0..1)
+    body:
+      - Ra <= A[i][2][k]
+  - iters:
+      - for k in (0..2)
+    body:
+      - Ra <= A[i][3][k]
+  - iters:
+      - for k in (0..3)
+    body:
+      - Ra <= A[i][4][k]
+  - iters:
+      - for j in (4..10)
+      - for k in (0..j)
+    body:
+      - Ra <= A[i][j + 1][k]
+"#,
+    )
+    .unwrap();
+    assert_eq!(unrolled, expected);
+
+    // unrolling all the iterations leaves no residual loop
+    let unrolled = loop_nest.apply_all(&Transforms::from_str(" - !Unroll j by 10").unwrap());
+    assert_eq!(unrolled.body.len(), 10);
+    assert_eq!(unrolled.all_iters().len(), 11);
+}
+
+#[test]
+fn test_unroll_factor_too_large() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..4)
+body:
+  - Ra <= A[i]
+"#,
+    )
+    .unwrap();
+    let result = loop_nest.try_apply_all(&Transforms::from_str(" - !Unroll i by 8").unwrap());
+    assert!(result.is_err());
+}
+
+/// Values of the iterators of a perfect loop nest, in execution order
+fn iterations(loop_nest: &LoopNest) -> Vec<Vec<i32>> {
+    fn visit(iters: &[LoopIter], values: &mut HashMap<String, i32>, out: &mut Vec<Vec<i32>>) {
+        let Some((iter, inner)) = iters.split_first() else {
+            out.push(Vec::new());
+            return;
+        };
+        let (lower, upper) = iter.concrete_bounds(values).unwrap();
+        let step = iter.concrete_step(values).unwrap();
+        for value in (lower..upper).step_by(step as usize) {
+            values.insert(iter.iter_name.clone(), value);
+            let first = out.len();
+            visit(inner, values, out);
+            for iteration in &mut out[first..] {
+                iteration.insert(0, value);
+            }
+        }
+        values.remove(&iter.iter_name);
+    }
+    let mut out = Vec::new();
+    visit(&loop_nest.iters, &mut HashMap::new(), &mut out);
+    out
+}
+
+#[test]
+fn test_skew() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..8)
+  - for j in (0..4)
+body:
+  - Ra <= A[i][j]
+  - Ra => B[i + 1]
+"#,
+    )
+    .unwrap();
+    let transforms = Transforms::from_str(" - !Skew i by 2 * j").unwrap();
+    let skewed = loop_nest.apply_all(&transforms);
+    let expected: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..14)
+  - for j in (max(0, ceildiv(-7 + i, 2))..min(4, 1 + i / 2))
+body:
+  - Ra <= A[i - 2 * j][j]
+  - Ra => B[i - 2 * j + 1]
+"#,
+    )
+    .unwrap();
+    assert_eq!(skewed, expected);
+    assert_eq!(skewed.to_string(), expected.to_string());
+    // the skewed loops run over the same 32 iterations
+    assert_eq!(iterations(&skewed).len(), 32);
+}
+
+#[test]
+fn test_skew_domain() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..8)
+  - for j in (0..4)
+body:
+  - Ra <= A[i][j]
+"#,
+    )
+    .unwrap();
+    // (i, j) for each iteration of the original loop nest
+    let mut domain: Vec<(i32, i32)> = Vec::new();
+    for i in 0..8 {
+        for j in 0..4 {
+            domain.push((i, j));
+        }
+    }
+    for (transform, outer, factor) in [
+        (" - !Skew i by 2 * j", "i", 2),
+        (" - !Skew i by -3 * j", "i", -3),
+        (" - !Skew j by 1 * i", "j", 1),
+        (" - !Skew j by -2 * i", "j", -2),
+    ] {
+        let skewed = loop_nest.apply_all(&Transforms::from_str(transform).unwrap());
+        let names = skewed.iter_names();
+        // skewed iterations mapped back with i = i' - factor * j or j = j' - factor * i
+        let mut skewed_domain: Vec<(i32, i32)> = Vec::new();
+        for values in iterations(&skewed) {
+            let value = |name: &str| values[names.iter().position(|n| *n == name).unwrap()];
+            let (i, j) = (value("i"), value("j"));
+            skewed_domain.push(if outer == "i" {
+                (i - factor * j, j)
+            } else {
+                (i, j - factor * i)
+            });
+        }
+        assert_eq!(skewed_domain.len(), 32, "{}", transform);
+        skewed_domain.sort();
+        assert_eq!(skewed_domain, domain, "{}", transform);
+    }
+}
+
+#[test]
+fn test_shift() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (1..8)
+  - for j in (0..4)
+body:
+  - PREFETCH A[i + 1][j]
+  - Ra <= A[i][j]
+  - Ra => B[i - 1]
+"#,
+    )
+    .unwrap();
+    let transforms = Transforms::from_str(" - !Shift i by -1").unwrap();
+    let shifted = loop_nest.apply_all(&transforms);
+    let expected: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..7)
+  - for j in (0..4)
+body:
+  - PREFETCH A[i + 1 + 1][j]
+  - Ra <= A[i + 1][j]
+  - Ra => B[i + 1 - 1]
+"#,
+    )
+    .unwrap();
+    assert_eq!(shifted, expected);
+
+    // shifting back restores the original bounds
+    let restored = shifted.apply_all(&Transforms::from_str(" - !Shift i by 1").unwrap());
+    assert_eq!(restored.iters, loop_nest.iters);
+
+    // the opposite of i32::MIN does not fit in an i32
+    let transforms = Transforms::from_str(" - !Shift i by -2147483648").unwrap();
+    let shifted = loop_nest.try_apply_all(&transforms).unwrap();
+    assert_eq!(
+        shifted.iters[0].concrete_bounds(&HashMap::new()),
+        Some((i32::MIN + 1, i32::MIN + 8))
+    );
+    let values = HashMap::from([("i".to_string(), i32::MIN + 1)]);
+    match &shifted.body[1] {
+        Instruction::DataLoad(access) => {
+            assert_eq!(access.addr[0].evaluate(&values, &HashMap::new()), Ok(1))
+        }
+        inst => panic!("unexpected instruction {}", inst),
+    }
+}
+
+#[test]
+fn test_scale() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..8)
+  - for j in (0..4)
+body:
+  - Ra <= A[i][j]
+  - Ra => B[2 * i + j]
+"#,
+    )
+    .unwrap();
+    let transforms = Transforms::from_str(" - !Scale i by 4").unwrap();
+    let scaled = loop_nest.apply_all(&transforms);
+    let expected: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..32).step(4)
+  - for j in (0..4)
+body:
+  - Ra <= A[i / 4][j]
+  - Ra => B[2 * (i / 4) + j]
+"#,
+    )
+    .unwrap();
+    assert_eq!(scaled, expected);
+    assert_eq!(
+        scaled.total_iteration_count(&Default::default()),
+        loop_nest.total_iteration_count(&Default::default())
+    );
+
+    // the scaled accesses survive a serialization round-trip
+    let serialized = serde_yaml::to_string(&scaled).unwrap();
+    let deserialized: LoopNest = serde_yaml::from_str(&serialized).unwrap();
+    assert_eq!(scaled, deserialized);
+}
+
+#[test]
+fn test_inverse() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for m in (0..16)
+  - for n in (1..17)
+  - for k in (0..8)
+body:
+  - Ra <= A[m][k]
+  - Rb <= B[k][n - 1]
+  - mul Rc Ra, Rb
+  - Rc => C[m][n - 1]
+"#,
+    )
+    .unwrap();
+    let transforms = Transforms::from_str(
+        r#"
+ - !Shift n by -1
+ - m -> y
+ - !Interchange y <-> k
+ - n -> x
+"#,
+    )
+    .unwrap();
+    let inverse = transforms.inverse(&loop_nest).unwrap();
+    assert_eq!(
+        inverse,
+        Transforms::from_str(
+            r#"
+ - x -> n
+ - !Interchange y <-> k
+ - y -> m
+ - !Shift n by 1
+"#
+        )
+        .unwrap()
+    );
+    let transformed = loop_nest.apply_all(&transforms);
+    assert_ne!(transformed, loop_nest);
+    let restored = transformed.apply_all(&inverse);
+    assert_eq!(restored.iters, loop_nest.iters);
+    // the shifted accesses are equivalent but not simplified
+    assert_eq!(restored.body[0], loop_nest.body[0]);
+    assert_eq!(restored.body[1].to_string(), "Rb <= B[k][n - 1 + 1 - 1]");
+
+    let transforms = Transforms::from_str(" - m -> y\n - !Tiling y -> (y, ty) by 4").unwrap();
+    assert!(transforms
+        .inverse(&loop_nest)
+        .unwrap_err()
+        .contains("not invertible"));
+    let transforms = Transforms::from_str(" - z -> y").unwrap();
+    assert!(transforms.inverse(&loop_nest).is_err());
+    let transforms = Transforms::from_str(" - !Shift n by -2147483648").unwrap();
+    assert!(transforms
+        .inverse(&loop_nest)
+        .unwrap_err()
+        .contains("not invertible"));
+}
+
+#[test]
+fn test_symbolic_tiling() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for m in (0..4 * M)
+  - for n in (0..N)
+body:
+  - Ra <= A[m][n]
+"#,
+    )
+    .unwrap();
+    let transforms = Transforms::from_str(
+        r#"
+ - m -> (m, tm) by 4
+ - n -> (n, tn) by 8
+"#,
+    )
+    .unwrap();
+    let tiled = loop_nest.apply_all(&transforms);
+    let expected: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for m in (0..M)
+  - for tm in (0..4)
+  - for n in (0..ceildiv(N, 8))
+  - for tn in (0..8)
+body:
+  - Ra <= A[m][tm][n][tn]
+"#,
+    )
+    .unwrap();
+    assert_eq!(tiled, expected);
+}
+
+#[test]
+fn test_distribution_fusion() {
+    let loop_nest: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..8)
+  - for j in (0..4)
+body:
+  - Ra <= A[i][j]
+  - Ra => B[i][j]
+  - Rb <= C[i][j]
+  - Rb => D[i][j]
+"#,
+    )
+    .unwrap();
+    let distributed = loop_nest.apply_all(&Transforms::from_str(" - !Distribute j at 2").unwrap());
+    let expected: LoopNest = serde_yaml::from_str(
+        r#"
+iters:
+  - for i in (0..8)
+body:
+  - iters:
+      - for j in (0..4)
+    body:
+      - Ra <= A[i][j]
+      - Ra => B[i][j]
+  - iters:
+      - for j in (0..4)
+    body:
+      - Rb <= C[i][j]
+      - Rb => D[i][j]
+"#,
+    )
+    .unwrap();
+    assert_eq!(distributed, expected);
+
+    // the transforms are applied to the nested loop nests owning the iterators
+    let renamed = distributed.apply_all(&Transforms::from_str(" - j -> k").unwrap());
+    assert_eq!(renamed.all_iters()[2].iter_name, "k");
+    assert_eq!(renamed.all_instructions()[2].to_string(), "Rb <= C[i][k]");
+
+    // fusing the distributed loops restores the perfect loop nest
+    let fused = distributed.apply_all(&Transforms::from_str(" - !Fuse j j").unwrap());
+    assert_eq!(fused, loop_nest);
+}
+
+#[test]
+fn test_distribution_through_register() {
+    let distribute = |body: &str, index: usize| {
+        let loop_nest: LoopNest = serde_yaml::from_str(&format!(
+            "iters:\n  - for i in (0..8)\n  - for j in (0..4)\nbody:\n{}",
+            body
+        ))
+        .unwrap();
+        let transforms = Transforms::from_str(&format!(" - !Distribute j at {}", index)).unwrap();
+        loop_nest.try_apply_all(&transforms)
+    };
+    let body = "  - Ra <= A[i][j]\n  - add Rb Ra, $1\n  - Rb => B[i][j]\n";
+    // the register is written before the split and read after it
+    let error = distribute(body, 2).unwrap_err();
+    assert!(error.contains("the register Rb"), "{}", error);
+    let error = distribute(body, 1).unwrap_err();
+    assert!(error.contains("the register Ra"), "{}", error);
+    // the register is read before the split and written after it, by the previous iteration
+    let error = distribute("  - Rb => B[i][j]\n  - Rb <= A[i][j]\n", 1).unwrap_err();
+    assert!(error.contains("the register Rb"), "{}", error);
+    // the accumulator Rc is only used after the split
+    let body = "  - Ra <= A[i][j]\n  - Ra => B[i][j]\n  - Rb <= C[i][j]\n  - add Rc Rc, Rb\n";
+    assert!(distribute(body, 2).is_ok());
+    // the vector register V0 is written before the split and read after it
+    let body = "  - add V0:4 V1:4, V2:4\n  - mul V3:4 V0:4, V1:4\n";
+    let error = distribute(body, 1).unwrap_err();
+    assert!(error.contains("the register V0"), "{}", error);
 }